@@ -1,12 +1,19 @@
-use crate::application::commands::{add, check, discover, doctor, list, record, remove, tui};
+use crate::application::commands::{
+    add, buffer, check, discover, doctor, library, list, record, remove, similar, stats, tui,
+};
 use crate::application::utils::{
-    aplicar_ffmpeg_path, normalizar_modelos, resolver_ffmpeg_path, resolver_ruta_opcional,
-    validar_ffmpeg, ParametrosGrabacion, FFMPEG_ENV, SESSION_COOKIE_ENV,
+    aplicar_ffmpeg_path, aplicar_perfil_ffmpeg, aplicar_red, normalizar_modelos,
+    resolver_ffmpeg_path, resolver_proxy, resolver_ruta_opcional, validar_ffmpeg,
+    ParametrosGrabacion, FFMPEG_ENV, SESSION_COOKIE_ENV,
 };
 use crate::application::watch_service::{self, ConsoleWatchPrompter, WatchParams};
-use crate::domain::value_objects::VideoQuality;
-use crate::infrastructure::{AppConfig, ChaturbateClient, ConfigWarning, WatchedModels};
-use crate::presentation::{Cli, Commands, ConsoleOutput, Output};
+use crate::domain::value_objects::{
+    parsear_bitrate, parsear_calidades, CodecVideo, FormatoAudio, PoliticaVariante, VideoQuality,
+};
+use crate::infrastructure::{
+    AppConfig, ChaturbateClient, ConfigWarning, OnlineHistory, WatchState, WatchedModels,
+};
+use crate::presentation::{Cli, Commands, ConsoleOutput, LibraryCommands, Output};
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::watch;
@@ -27,7 +34,15 @@ pub async fn ejecutar_cli(
         quality: calidad_principal,
         jobs,
         duration,
+        max_bitrate,
+        codec,
+        fps,
+        strict,
+        from_start,
+        audio_format,
         ffmpeg_path,
+        ffmpeg_profile,
+        proxy: proxy_cli,
         session_cookie: cookie_cli,
         quiet,
         verbose,
@@ -48,12 +63,21 @@ pub async fn ejecutar_cli(
     if duration == Some(0) {
         anyhow::bail!("La duracion debe ser mayor a 0");
     }
+    if fps == Some(0) {
+        anyhow::bail!("Los fps deben ser mayores a 0");
+    }
     if let Some(jobs) = jobs {
         if jobs > LIMITE_CONCURRENCIA_DEFECTO {
             salida.advertir_limite_concurrencia(LIMITE_CONCURRENCIA_DEFECTO, jobs);
         }
     }
 
+    if let Some(formato) = audio_format {
+        config.recording.audio_format = FormatoAudio::from_str(&formato)
+            .map_err(|e| anyhow::anyhow!(e))?
+            .to_string();
+    }
+
     let ruta_ffmpeg_cli = resolver_ruta_opcional(ffmpeg_path);
     let ffmpeg_env_explicito = std::env::var(FFMPEG_ENV)
         .map(|v| !v.trim().is_empty())
@@ -79,6 +103,25 @@ pub async fn ejecutar_cli(
         client
     };
     let client = client.with_min_free_space(config.min_free_space);
+    let client = client.with_politica_variante(PoliticaVariante {
+        max_bitrate: max_bitrate
+            .as_deref()
+            .map(parsear_bitrate)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!(e))?,
+        codec: codec
+            .as_deref()
+            .map(CodecVideo::from_str)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!(e))?,
+        fps,
+        estricta: strict,
+        ..VideoQuality::Best.into()
+    });
+    let client = client.with_inicio_ventana(from_start || config.recording.start_from_oldest);
+    let client = aplicar_perfil_ffmpeg(client, ffmpeg_profile.as_deref(), &config.ffmpeg)?;
+    let proxy = resolver_proxy(proxy_cli, config.network.proxy.as_deref())?;
+    let client = aplicar_red(client, proxy, &config.network)?;
     let client = client.with_cancel_receiver(cancel_rx);
     let min_file_size = if duration.is_some() {
         None
@@ -95,10 +138,10 @@ pub async fn ejecutar_cli(
             let limite_concurrencia = jobs.unwrap_or(LIMITE_CONCURRENCIA_DEFECTO);
             validar_ffmpeg(&ruta_ffmpeg, ffmpeg_explicito).await?;
             let client = aplicar_ffmpeg_path(client, ruta_ffmpeg);
-            let v_quality = VideoQuality::from_str(&quality).map_err(|e| anyhow::anyhow!(e))?;
+            let calidades = parsear_calidades(&quality).map_err(|e| anyhow::anyhow!(e))?;
             let parametros = ParametrosGrabacion {
                 raiz_salida: resolver_ruta_opcional(output),
-                quality: v_quality,
+                calidades,
                 limite_concurrencia,
                 min_file_size,
                 cancel_rx: cancel_rx_worker,
@@ -117,6 +160,8 @@ pub async fn ejecutar_cli(
         Some(Commands::Doctor) => {
             doctor::ejecutar_doctor(
                 &config,
+                &config_warnings,
+                &client,
                 &ruta_ffmpeg,
                 ffmpeg_explicito,
                 resolver_ruta_opcional(salida_principal.clone()),
@@ -135,13 +180,37 @@ pub async fn ejecutar_cli(
                 discover::discover_rooms(&client, salida.as_ref(), &tag, limit).await
             }
         }
-        Some(Commands::Tui { tag, limit }) => tui::run(&client, &tag, limit).await,
+        Some(Commands::Similar { model, limit, json }) => {
+            if json {
+                println!(
+                    "{}",
+                    similar::similar_rooms_json(&client, &model, limit).await?
+                );
+                Ok(())
+            } else {
+                similar::similar_rooms(&client, salida.as_ref(), &model, limit).await
+            }
+        }
+        Some(Commands::Tui { tag, limit }) => {
+            let opciones = tui::OpcionesTui {
+                raiz_salida: resolver_ruta_opcional(salida_principal),
+                quality: VideoQuality::from_str(&calidad_principal)
+                    .map_err(|e| anyhow::anyhow!(e))?,
+                min_file_size,
+                limite_concurrencia: jobs.unwrap_or(LIMITE_CONCURRENCIA_DEFECTO),
+                ruta_ffmpeg,
+                ffmpeg_explicito,
+            };
+            tui::run(&client, config, Arc::clone(&salida), &tag, limit, opciones).await
+        }
         Some(Commands::Watch {
             modelos,
             ask,
             timeout,
             output,
             quality,
+            fresh,
+            timeline,
         }) => {
             let limite_concurrencia = jobs.unwrap_or(config.watch.max_simultaneous);
             // Override timeout from CLI si se especificó
@@ -153,7 +222,7 @@ pub async fn ejecutar_cli(
             let nombres: Vec<String> = if modelos.is_empty() {
                 let watched = WatchedModels::load_with_warnings();
                 mostrar_config_warnings(salida.as_ref(), &watched.warnings);
-                if watched.watched.models.is_empty() {
+                if watched.watched.models.is_empty() && config.discovery.rules.is_empty() {
                     anyhow::bail!(
                         "Sin modelos. Usa 'cbrec add <modelo>' o especifica modelos en el comando."
                     );
@@ -182,11 +251,17 @@ pub async fn ejecutar_cli(
                 }
             }
 
-            let v_quality = VideoQuality::from_str(&quality).map_err(|e| anyhow::anyhow!(e))?;
+            let calidades = parsear_calidades(&quality).map_err(|e| anyhow::anyhow!(e))?;
             let raiz_salida = resolver_ruta_opcional(output);
 
             validar_ffmpeg(&ruta_ffmpeg, ffmpeg_explicito).await?;
             let client = aplicar_ffmpeg_path(client, ruta_ffmpeg);
+            let historial = config.watch.learn_schedule.then(OnlineHistory::load);
+            let estado_guardado = if fresh {
+                WatchState::default()
+            } else {
+                WatchState::load(chrono::Utc::now().timestamp())
+            };
 
             watch_service::ejecutar_watch(WatchParams {
                 client: Arc::new(client),
@@ -194,17 +269,77 @@ pub async fn ejecutar_cli(
                 modelos: modelos_vobj,
                 ask,
                 raiz_salida,
-                quality: v_quality,
+                calidades,
                 limite_concurrencia,
                 min_file_size,
                 cancel_rx: cancel_rx_worker,
                 salida,
                 prompter: Arc::new(ConsoleWatchPrompter),
+                historial,
+                estado_guardado: Some(estado_guardado),
+                linea_temporal: resolver_ruta_opcional(timeline),
             })
             .await
         }
+        Some(Commands::Buffer {
+            modelos,
+            output,
+            quality,
+            minutes,
+        }) => {
+            if let Some(minutos) = minutes {
+                if !(1..=120).contains(&minutos) {
+                    anyhow::bail!("--minutes debe estar entre 1 y 120");
+                }
+                config.buffer.window_mins = minutos;
+            }
+            validar_ffmpeg(&ruta_ffmpeg, ffmpeg_explicito).await?;
+            let client = aplicar_ffmpeg_path(client, ruta_ffmpeg);
+            let v_quality = VideoQuality::from_str(&quality).map_err(|e| anyhow::anyhow!(e))?;
+            let parametros = buffer::ParametrosBuffer {
+                raiz_salida: resolver_ruta_opcional(output),
+                quality: v_quality,
+                cancel_rx: cancel_rx_worker,
+                salida: Arc::clone(&salida),
+            };
+            buffer::ejecutar_buffer(client, config, modelos, parametros).await
+        }
+        Some(Commands::Clip {
+            model,
+            after,
+            output,
+        }) => {
+            let raiz_salida = resolver_ruta_opcional(output);
+            buffer::solicitar_clip(
+                &config,
+                &model,
+                after,
+                raiz_salida.as_deref(),
+                salida.as_ref(),
+            )
+            .await
+        }
         Some(Commands::Add { models }) => add::agregar_modelos(models, salida.as_ref()),
         Some(Commands::Remove { models }) => remove::eliminar_modelos(models, salida.as_ref()),
+        Some(Commands::Stats { model }) => stats::mostrar_estadisticas(&model, salida.as_ref()),
+        Some(Commands::Library { command }) => match command {
+            LibraryCommands::Reorganize { output, dry_run } => {
+                library::reorganizar(
+                    &config,
+                    resolver_ruta_opcional(output).as_deref(),
+                    dry_run,
+                    salida.as_ref(),
+                )
+                .await
+            }
+            LibraryCommands::Rollback { log } => {
+                library::deshacer(
+                    &resolver_ruta_opcional(Some(log)).unwrap_or_default(),
+                    salida.as_ref(),
+                )
+                .await
+            }
+        },
         None => {
             if modelos_principales.is_empty() {
                 salida.mostrar_error_sin_modelo();
@@ -216,15 +351,23 @@ pub async fn ejecutar_cli(
             let client = aplicar_ffmpeg_path(client, ruta_ffmpeg);
 
             if listar {
-                list::listar_calidades_modelos(&client, salida.as_ref(), modelos_principales).await
+                let v_quality =
+                    VideoQuality::from_str(&calidad_principal).map_err(|e| anyhow::anyhow!(e))?;
+                list::listar_calidades_modelos(
+                    &client,
+                    salida.as_ref(),
+                    modelos_principales,
+                    v_quality,
+                )
+                .await
             } else if verificar {
                 check::verificar_modelos(&client, salida.as_ref(), modelos_principales).await
             } else {
-                let v_quality =
-                    VideoQuality::from_str(&calidad_principal).map_err(|e| anyhow::anyhow!(e))?;
+                let calidades =
+                    parsear_calidades(&calidad_principal).map_err(|e| anyhow::anyhow!(e))?;
                 let parametros = ParametrosGrabacion {
                     raiz_salida: resolver_ruta_opcional(salida_principal),
-                    quality: v_quality,
+                    calidades,
                     limite_concurrencia,
                     min_file_size,
                     cancel_rx: cancel_rx_worker,
@@ -0,0 +1,349 @@
+//! Rolling DVR buffer: `cbrec buffer` keeps the last minutes of each model on
+//! disk, and `cbrec clip` asks it to save that window plus what follows.
+//!
+//! The two commands talk through a request file in the model's buffer
+//! directory, so a clip can be asked for from any other terminal.
+
+use crate::application::recording::{
+    nuevo_id_sesion, preparar_ruta_grabacion, recuperar_parcial, ruta_parcial,
+    write_recording_metadata, InfoSesion, ResultadoGrabacion,
+};
+use crate::application::utils::{normalizar_modelo, normalizar_modelos};
+use crate::domain::value_objects::{ModelName, VideoQuality};
+use crate::infrastructure::external::segundos_en_playlist;
+use crate::infrastructure::{
+    AppConfig, ChaturbateClient, DatosNombre, EstadoStream, InfrastructureError,
+};
+use crate::presentation::Output;
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+const PLAYLIST_BUFFER: &str = "buffer.m3u8";
+const SOLICITUD_CLIP: &str = "clip.request";
+const REVISION_SOLICITUDES: Duration = Duration::from_secs(1);
+const MAX_MINUTOS_TRAS_CLIP: u64 = 120;
+
+pub(crate) struct ParametrosBuffer {
+    pub raiz_salida: Option<PathBuf>,
+    pub quality: VideoQuality,
+    pub cancel_rx: watch::Receiver<bool>,
+    pub salida: Arc<dyn Output>,
+}
+
+/// Keeps a rolling window for every model until cancelled.
+pub(crate) async fn ejecutar_buffer(
+    client: ChaturbateClient,
+    config: AppConfig,
+    modelos: Vec<String>,
+    parametros: ParametrosBuffer,
+) -> anyhow::Result<()> {
+    let (modelos, duplicados) = normalizar_modelos(modelos)?;
+    if duplicados > 0 {
+        parametros.salida.advertir_modelos_duplicados(duplicados);
+    }
+    let config = Arc::new(config);
+    let parametros = Arc::new(parametros);
+
+    let mut tareas = JoinSet::new();
+    for modelo in modelos {
+        let client = Arc::new(client.for_model(modelo.as_str()));
+        let config = Arc::clone(&config);
+        let parametros = Arc::clone(&parametros);
+        tareas.spawn(async move { mantener_buffer(client, config, modelo, parametros).await });
+    }
+
+    let mut errores = 0;
+    while let Some(resultado) = tareas.join_next().await {
+        match resultado {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                parametros.salida.error_tarea_abortada(&e.to_string());
+                errores += 1;
+            }
+            Err(e) => {
+                parametros.salida.error_tarea_abortada(&e.to_string());
+                errores += 1;
+            }
+        }
+    }
+    if errores > 0 {
+        anyhow::bail!("Fallo el buffer en {} modelo(s)", errores);
+    }
+    Ok(())
+}
+
+/// Records one model into its window whenever it is online and serves clip
+/// requests meanwhile.
+async fn mantener_buffer(
+    client: Arc<ChaturbateClient>,
+    config: Arc<AppConfig>,
+    modelo: ModelName,
+    parametros: Arc<ParametrosBuffer>,
+) -> Result<(), InfrastructureError> {
+    let directorio = config.ruta_buffer(modelo.as_str(), parametros.raiz_salida.as_deref());
+    tokio::fs::create_dir_all(&directorio).await?;
+    limpiar_buffer(&directorio).await;
+    let playlist = directorio.join(PLAYLIST_BUFFER);
+
+    let solicitudes = tokio::spawn(atender_solicitudes(
+        Arc::clone(&client),
+        Arc::clone(&config),
+        modelo.clone(),
+        Arc::clone(&parametros),
+    ));
+
+    let ventana_secs = config.buffer.window_mins.saturating_mul(60);
+    let mut cancel_rx = parametros.cancel_rx.clone();
+    while !*cancel_rx.borrow() {
+        if let Ok(EstadoStream::Online { stream_url }) = client.consultar_estado(&modelo).await {
+            limpiar_buffer(&directorio).await;
+            parametros
+                .salida
+                .buffer_activo(modelo.as_str(), config.buffer.window_mins);
+            match client
+                .grabar_ventana(
+                    &stream_url,
+                    &playlist,
+                    parametros.quality,
+                    ventana_secs,
+                    config.buffer.segment_secs,
+                )
+                .await
+            {
+                Ok(()) | Err(InfrastructureError::RecordingCancelled) => {}
+                Err(e) => parametros
+                    .salida
+                    .error_fallo_grabacion(modelo.as_str(), &e.to_string()),
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(config.watch.poll_interval_secs)) => {}
+            _ = cancel_rx.wait_for(|v| *v) => break,
+        }
+    }
+
+    // Los clips en curso terminan solos al recibir la cancelacion.
+    let _ = solicitudes.await;
+    Ok(())
+}
+
+/// Turns each request file into a clip while the buffer runs.
+async fn atender_solicitudes(
+    client: Arc<ChaturbateClient>,
+    config: Arc<AppConfig>,
+    modelo: ModelName,
+    parametros: Arc<ParametrosBuffer>,
+) {
+    let directorio = config.ruta_buffer(modelo.as_str(), parametros.raiz_salida.as_deref());
+    let mut cancel_rx = parametros.cancel_rx.clone();
+    let mut clips = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(REVISION_SOLICITUDES) => {}
+            _ = cancel_rx.wait_for(|v| *v) => break,
+        }
+        let Some(siguientes_mins) = tomar_solicitud(&directorio, config.buffer.after_mins).await
+        else {
+            continue;
+        };
+        let client = Arc::clone(&client);
+        let config = Arc::clone(&config);
+        let modelo = modelo.clone();
+        let parametros = Arc::clone(&parametros);
+        let playlist = directorio.join(PLAYLIST_BUFFER);
+        clips.spawn(async move {
+            let siguientes_secs = siguientes_mins.saturating_mul(60);
+            if let Err(e) = guardar_clip(
+                &client,
+                &config,
+                &modelo,
+                &parametros,
+                &playlist,
+                siguientes_secs,
+            )
+            .await
+            {
+                parametros
+                    .salida
+                    .error_fallo_grabacion(modelo.as_str(), &e.to_string());
+            }
+        });
+    }
+
+    while clips.join_next().await.is_some() {}
+}
+
+/// Copies the buffered window plus `siguientes_secs` of live stream into a
+/// normal recording with its sidecar.
+async fn guardar_clip(
+    client: &ChaturbateClient,
+    config: &AppConfig,
+    modelo: &ModelName,
+    parametros: &ParametrosBuffer,
+    playlist: &Path,
+    siguientes_secs: u64,
+) -> Result<(), InfrastructureError> {
+    let contenido = tokio::fs::read_to_string(playlist).await.map_err(|_| {
+        InfrastructureError::RecordingError("el buffer todavia no tiene segmentos".to_string())
+    })?;
+    let previos_secs = segundos_en_playlist(&contenido)?.round() as u64;
+    parametros
+        .salida
+        .buffer_clip_iniciado(modelo.as_str(), previos_secs, siguientes_secs);
+
+    let started_at = Utc::now() - chrono::Duration::seconds(previos_secs as i64);
+    let id_sesion = nuevo_id_sesion(modelo);
+    let datos = DatosNombre {
+        modelo: modelo.as_str(),
+        inicio: started_at,
+        quality: parametros.quality,
+        sesion: &id_sesion,
+        parte: 1,
+        subject: None,
+        ext: config.extension_grabacion(parametros.quality),
+    };
+    let ruta =
+        preparar_ruta_grabacion(config.ruta_grabacion(&datos, parametros.raiz_salida.as_deref()))
+            .await?;
+    let extraccion = client
+        .extraer_clip(
+            playlist,
+            &ruta_parcial(&ruta),
+            previos_secs.saturating_add(siguientes_secs),
+        )
+        .await;
+
+    let resultado = recuperar_parcial(&ruta, None).await?;
+    let Some(resultado) = resultado else {
+        return match extraccion {
+            Err(e) => Err(e),
+            Ok(()) => Err(InfrastructureError::RecordingError(
+                "el clip no produjo un archivo valido".to_string(),
+            )),
+        };
+    };
+    write_recording_metadata(
+        &resultado,
+        modelo.as_str(),
+        parametros.quality,
+        started_at,
+        &[],
+        InfoSesion::default(),
+    )
+    .await?;
+    if let ResultadoGrabacion::Guardado(ruta) = &resultado {
+        parametros
+            .salida
+            .mostrar_archivo_guardado_resumido(modelo.as_str(), ruta);
+    }
+    Ok(())
+}
+
+/// Asks a running `cbrec buffer` to save a clip of `modelo`.
+pub(crate) async fn solicitar_clip(
+    config: &AppConfig,
+    modelo: &str,
+    siguientes_mins: Option<u64>,
+    raiz_salida: Option<&Path>,
+    salida: &dyn Output,
+) -> anyhow::Result<()> {
+    let modelo = normalizar_modelo(modelo)?;
+    let siguientes_mins = siguientes_mins.unwrap_or(config.buffer.after_mins);
+    if siguientes_mins > MAX_MINUTOS_TRAS_CLIP {
+        anyhow::bail!(
+            "--after debe estar entre 0 y {} minutos",
+            MAX_MINUTOS_TRAS_CLIP
+        );
+    }
+    let directorio = config.ruta_buffer(modelo.as_str(), raiz_salida);
+    if !tokio::fs::try_exists(directorio.join(PLAYLIST_BUFFER))
+        .await
+        .unwrap_or(false)
+    {
+        anyhow::bail!(
+            "No hay buffer activo para {}; inicia 'cbrec buffer {}'",
+            modelo,
+            modelo
+        );
+    }
+
+    let destino = directorio.join(SOLICITUD_CLIP);
+    let temporal = directorio.join(format!(".{SOLICITUD_CLIP}.{}", std::process::id()));
+    tokio::fs::write(&temporal, format!("{siguientes_mins}\n")).await?;
+    tokio::fs::rename(&temporal, &destino).await?;
+    salida.clip_solicitado(modelo.as_str(), siguientes_mins);
+    Ok(())
+}
+
+/// Consumes a pending request and returns its minutes after the trigger.
+async fn tomar_solicitud(directorio: &Path, por_defecto: u64) -> Option<u64> {
+    let ruta = directorio.join(SOLICITUD_CLIP);
+    let contenido = tokio::fs::read_to_string(&ruta).await.ok()?;
+    tokio::fs::remove_file(&ruta).await.ok()?;
+    Some(
+        contenido
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|m| *m <= MAX_MINUTOS_TRAS_CLIP)
+            .unwrap_or(por_defecto),
+    )
+}
+
+/// Drops the previous window and any request left from an earlier run.
+async fn limpiar_buffer(directorio: &Path) {
+    let Ok(mut entradas) = tokio::fs::read_dir(directorio).await else {
+        return;
+    };
+    while let Ok(Some(entrada)) = entradas.next_entry().await {
+        let nombre = entrada.file_name();
+        let nombre = nombre.to_string_lossy();
+        if nombre == PLAYLIST_BUFFER || nombre == SOLICITUD_CLIP || nombre.ends_with(".ts") {
+            let _ = tokio::fs::remove_file(entrada.path()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presentation::ConsoleOutput;
+
+    #[tokio::test]
+    async fn clip_request_is_written_for_running_buffer_and_consumed_once() {
+        let raiz = std::env::temp_dir().join(format!(
+            "cbrec_buffer_{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default()
+        ));
+        let config = AppConfig::default();
+        let salida = ConsoleOutput::new(false, true);
+
+        let sin_buffer = solicitar_clip(&config, "alice", None, Some(&raiz), &salida).await;
+        assert!(sin_buffer.is_err());
+
+        let directorio = config.ruta_buffer("alice", Some(&raiz));
+        tokio::fs::create_dir_all(&directorio).await.unwrap();
+        tokio::fs::write(directorio.join(PLAYLIST_BUFFER), "#EXTM3U\n")
+            .await
+            .unwrap();
+        solicitar_clip(&config, "alice", Some(3), Some(&raiz), &salida)
+            .await
+            .expect("solicitud escrita");
+
+        assert_eq!(tomar_solicitud(&directorio, 2).await, Some(3));
+        assert_eq!(tomar_solicitud(&directorio, 2).await, None);
+
+        limpiar_buffer(&directorio).await;
+        assert!(!directorio.join(PLAYLIST_BUFFER).exists());
+        let _ = std::fs::remove_dir_all(raiz);
+    }
+}
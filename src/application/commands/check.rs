@@ -70,6 +70,7 @@ pub(crate) async fn check_model_json(
 }
 
 async fn check_model(client: &ChaturbateClient, model: &ModelName) -> anyhow::Result<ModelCheck> {
+    let client = client.for_model(model.as_str());
     let (status, detail) = match client.consultar_estado(model).await? {
         EstadoStream::Online { .. } => (CheckStatus::Online, None),
         EstadoStream::Offline => (CheckStatus::Offline, None),
@@ -5,7 +5,7 @@ use crate::infrastructure::ChaturbateClient;
 use crate::presentation::Output;
 use serde::Serialize;
 
-const MAX_RESULTS: usize = 50;
+pub(crate) const MAX_RESULTS: usize = 50;
 
 /// Validated discovery response shared by terminal presentation adapters.
 #[derive(Serialize)]
@@ -22,7 +22,7 @@ pub(crate) async fn discover_rooms(
     raw_tag: &str,
     limit: usize,
 ) -> anyhow::Result<()> {
-    let result = find_rooms(client, raw_tag, limit).await?;
+    let result = find_rooms(client, raw_tag, limit, 0).await?;
     if result.rooms.is_empty() {
         output.discovery_empty(&result.tag);
         return Ok(());
@@ -42,22 +42,24 @@ pub(crate) async fn discover_rooms_json(
     limit: usize,
 ) -> anyhow::Result<String> {
     Ok(serde_json::to_string(
-        &find_rooms(client, raw_tag, limit).await?,
+        &find_rooms(client, raw_tag, limit, 0).await?,
     )?)
 }
 
-/// Validates the query and returns at most 50 matching public rooms.
+/// Validates the query and returns at most 50 matching public rooms,
+/// skipping the first `offset` results.
 pub(crate) async fn find_rooms(
     client: &ChaturbateClient,
     raw_tag: &str,
     limit: usize,
+    offset: usize,
 ) -> anyhow::Result<DiscoveryResult> {
     let tag = normalize_tag(raw_tag)?;
     if !(1..=MAX_RESULTS).contains(&limit) {
         anyhow::bail!("El limite debe estar entre 1 y {MAX_RESULTS}");
     }
 
-    let rooms = client.discover_rooms_by_tag(&tag, limit).await?;
+    let rooms = client.discover_rooms_by_tag(&tag, limit, offset).await?;
     Ok(DiscoveryResult { tag, rooms })
 }
 
@@ -1,10 +1,15 @@
-use crate::application::utils::{normalizar_modelo, obtener_version_ffmpeg};
-use crate::infrastructure::{AppConfig, WatchedModels};
+use crate::application::utils::{espacio_disponible, normalizar_modelo, obtener_version_ffmpeg};
+use crate::infrastructure::external::EstadoCircuito;
+use crate::infrastructure::{
+    ocultar_credenciales_proxy, AppConfig, ChaturbateClient, ConfigWarning, WatchedModels,
+};
 use crate::presentation::Output;
 use std::path::Path;
 
 pub(crate) async fn ejecutar_doctor(
     config: &AppConfig,
+    config_warnings: &[ConfigWarning],
+    client: &ChaturbateClient,
     ffmpeg_path: &Path,
     ffmpeg_explicito: bool,
     raiz_salida: Option<std::path::PathBuf>,
@@ -34,8 +39,73 @@ pub(crate) async fn ejecutar_doctor(
         }
     }
 
+    if let Some(staging) = &config.staging_dir {
+        match probar_escritura(staging).await {
+            Ok(()) => salida.doctor_ok("staging", &format!("escribible: {}", staging.display())),
+            Err(e) => {
+                fallos += 1;
+                salida.doctor_error("staging", &format!("{}: {}", staging.display(), e));
+            }
+        }
+        if !revisar_espacio("staging", staging, config.min_free_space, true, salida).await {
+            fallos += 1;
+        }
+    }
+
+    // Con varios volumenes uno lleno solo avisa: la grabacion sigue en otro.
+    let volumenes = config.volumenes_salida(raiz_salida.as_deref());
+    let varios = volumenes.len() > 1;
+    let mut con_espacio = 0usize;
+    for (indice, volumen) in volumenes.iter().enumerate() {
+        if indice > 0 {
+            if let Err(e) = probar_escritura(&volumen.ruta).await {
+                fallos += 1;
+                salida.doctor_error("salida", &format!("{}: {}", volumen.ruta.display(), e));
+                continue;
+            }
+            salida.doctor_ok("salida", &format!("escribible: {}", volumen.ruta.display()));
+        }
+        let libre = revisar_espacio(
+            "espacio",
+            &volumen.ruta,
+            volumen.min_free_space,
+            !varios,
+            salida,
+        )
+        .await;
+        if libre {
+            con_espacio += 1;
+        } else if varios {
+            advertencias += 1;
+        } else {
+            fallos += 1;
+        }
+    }
+    if varios && con_espacio == 0 {
+        fallos += 1;
+        salida.doctor_error(
+            "espacio",
+            "ningun volumen de salida tiene mas espacio libre que su min_free_space",
+        );
+    }
+
     let ejemplo = config.get_output_path("alice", raiz_salida.as_deref());
-    if ejemplo.file_name().is_some() {
+    let plantilla_invalida = config_warnings.iter().find_map(|warning| match warning {
+        ConfigWarning::PlantillaInvalida { plantilla, error } => Some((plantilla, error)),
+        _ => None,
+    });
+    if let Some((plantilla, error)) = plantilla_invalida {
+        fallos += 1;
+        salida.doctor_error(
+            "nombres",
+            &format!(
+                "plantilla '{}' invalida: {}; se usa la de defecto, ejemplo: {}",
+                plantilla,
+                error,
+                ejemplo.display()
+            ),
+        );
+    } else if ejemplo.file_name().is_some() {
         salida.doctor_ok("nombres", &format!("ejemplo: {}", ejemplo.display()));
     } else {
         fallos += 1;
@@ -76,6 +146,38 @@ pub(crate) async fn ejecutar_doctor(
         );
     }
 
+    let (fallos_red, advertencias_red) = revisar_proxies(config, client, salida).await;
+    fallos += fallos_red;
+    advertencias += advertencias_red;
+
+    let limite = client.limite_peticiones();
+    let politica = format!(
+        "{} peticiones/min (rafaga {}), pausa tras {} limite(s) seguidos",
+        limite.por_minuto, limite.rafaga, limite.umbral_circuito
+    );
+    match client.estado_circuito() {
+        EstadoCircuito::Cerrado => {
+            salida.doctor_ok("limites", &format!("{politica}; circuito cerrado"))
+        }
+        EstadoCircuito::Abierto { restante } => {
+            advertencias += 1;
+            salida.doctor_warn(
+                "limites",
+                &format!(
+                    "circuito abierto por respuestas 429/bloqueo; consultas pausadas {} s",
+                    restante.as_secs().max(1)
+                ),
+            );
+        }
+        EstadoCircuito::SemiAbierto => {
+            advertencias += 1;
+            salida.doctor_warn(
+                "limites",
+                "circuito semiabierto; esperando una sonda exitosa",
+            );
+        }
+    }
+
     if config.auth.session_cookie.is_some() {
         salida.doctor_ok("auth", "cookie configurada");
     } else {
@@ -92,6 +194,93 @@ pub(crate) async fn ejecutar_doctor(
     Ok(())
 }
 
+/// Probes the global proxy and every per-model override with one request.
+async fn revisar_proxies(
+    config: &AppConfig,
+    client: &ChaturbateClient,
+    salida: &dyn Output,
+) -> (usize, usize) {
+    let mut fallos = 0usize;
+    let mut advertencias = 0usize;
+
+    let mut rutas = vec![("proxy".to_string(), client.clone())];
+    for modelo in config.network.model_proxies.keys() {
+        rutas.push((format!("proxy {modelo}"), client.for_model(modelo)));
+    }
+
+    for (area, client) in rutas {
+        let Some(proxy) = client.proxy().map(ocultar_credenciales_proxy) else {
+            salida.doctor_ok(&area, "conexion directa");
+            continue;
+        };
+        match client.probar_conexion().await {
+            Ok(status) => salida.doctor_ok(&area, &format!("{proxy} responde (HTTP {status})")),
+            Err(e) => {
+                fallos += 1;
+                salida.doctor_error(&area, &format!("{proxy}: {e}"));
+            }
+        }
+        if !proxy.starts_with("http://") {
+            advertencias += 1;
+            salida.doctor_warn(
+                &area,
+                "FFmpeg solo admite proxies http://; las grabaciones por esta ruta fallaran",
+            );
+        }
+    }
+
+    (fallos, advertencias)
+}
+
+/// Reports the free space under `ruta` against `minimo`, as an error when
+/// `grave` and as a warning otherwise; `false` when short.
+async fn revisar_espacio(
+    area: &str,
+    ruta: &Path,
+    minimo: u64,
+    grave: bool,
+    salida: &dyn Output,
+) -> bool {
+    let mb = |bytes: u64| format!("{:.1} MB", bytes as f64 / 1_048_576.0);
+    match espacio_disponible(ruta).await {
+        Ok(libre) if libre >= minimo => {
+            salida.doctor_ok(
+                area,
+                &format!(
+                    "{} libres en {} (minimo {})",
+                    mb(libre),
+                    ruta.display(),
+                    mb(minimo)
+                ),
+            );
+            true
+        }
+        Ok(libre) => {
+            let detalle = format!(
+                "{} libres en {}, por debajo del minimo de {}",
+                mb(libre),
+                ruta.display(),
+                mb(minimo)
+            );
+            if grave {
+                salida.doctor_error(area, &detalle);
+            } else {
+                salida.doctor_warn(area, &detalle);
+            }
+            false
+        }
+        Err(e) => {
+            let detalle = format!("{}: {}", ruta.display(), e);
+            if grave {
+                salida.doctor_error(area, &detalle);
+            } else {
+                salida.doctor_warn(area, &detalle);
+            }
+            false
+        }
+    }
+}
+
 async fn probar_salida(
     config: &AppConfig,
     raiz_salida: Option<&Path>,
@@ -100,9 +289,14 @@ async fn probar_salida(
     let dir = ruta
         .parent()
         .ok_or_else(|| anyhow::anyhow!("ruta de salida sin directorio padre"))?;
+    probar_escritura(dir).await?;
+    Ok(dir.to_path_buf())
+}
+
+async fn probar_escritura(dir: &Path) -> anyhow::Result<()> {
     tokio::fs::create_dir_all(dir).await?;
     let probe = dir.join(".cbrec_doctor_write_test");
     tokio::fs::write(&probe, b"ok").await?;
     tokio::fs::remove_file(&probe).await?;
-    Ok(dir.to_path_buf())
+    Ok(())
 }
@@ -0,0 +1,600 @@
+//! `cbrec library`: moves finished recordings to the path the current naming
+//! template gives them, using the facts stored in each file's sidecar.
+//!
+//! Each output volume is reorganized in place, and every move is appended to
+//! a JSON-lines log at the root of its volume, so `cbrec library rollback
+//! <log>` can put the files back.
+
+use crate::application::recording::{
+    path_with_suffix, ruta_con_calidad, ruta_con_sufijo, ruta_disponible, ruta_parcial,
+};
+use crate::application::staging::mover_archivo;
+use crate::domain::value_objects::VideoQuality;
+use crate::infrastructure::{AppConfig, DatosNombre, InfrastructureError};
+use crate::presentation::Output;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Sidecar fields needed to render a recording's path again.
+#[derive(Debug, Deserialize)]
+struct SidecarGrabacion {
+    schema_version: u8,
+    model: String,
+    requested_quality: String,
+    started_at: DateTime<Utc>,
+    #[serde(default)]
+    classification: String,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    subject: Option<String>,
+}
+
+struct Grabacion {
+    /// Index of the output volume the recording was found on.
+    volumen: usize,
+    media: PathBuf,
+    meta: SidecarGrabacion,
+    quality: VideoQuality,
+}
+
+/// One media file moved together with its sidecar.
+#[derive(Debug, Serialize, Deserialize)]
+struct Movimiento {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+#[derive(Default)]
+struct Resumen {
+    movidos: usize,
+    sin_cambios: usize,
+    errores: usize,
+}
+
+/// Moves every recording under the output volumes to its current template
+/// path on the same volume.
+pub(crate) async fn reorganizar(
+    config: &AppConfig,
+    raiz_salida: Option<&Path>,
+    simulado: bool,
+    salida: &dyn Output,
+) -> anyhow::Result<()> {
+    let volumenes = config.volumenes_salida(raiz_salida);
+    let mut grabaciones = Vec::new();
+    for (indice, volumen) in volumenes.iter().enumerate() {
+        // Un volumen de reserva que aun no se uso no tiene nada que mover.
+        if indice > 0 && !volumen.ruta.exists() {
+            continue;
+        }
+        grabaciones.extend(buscar_grabaciones(indice, &volumen.ruta, salida)?);
+    }
+    let plantilla = config.plantilla_nombre();
+
+    let mut calidades_por_sesion: HashMap<&str, BTreeSet<VideoQuality>> = HashMap::new();
+    let mut partes: BTreeMap<(&str, VideoQuality), Vec<DateTime<Utc>>> = BTreeMap::new();
+    for grabacion in &grabaciones {
+        if let Some(sesion) = grabacion.meta.session_id.as_deref() {
+            calidades_por_sesion
+                .entry(sesion)
+                .or_default()
+                .insert(grabacion.quality);
+            partes
+                .entry((sesion, grabacion.quality))
+                .or_default()
+                .push(grabacion.meta.started_at);
+        }
+    }
+    partes.values_mut().for_each(|inicios| inicios.sort());
+
+    let mut resumen = Resumen::default();
+    let mut reservadas = HashSet::new();
+    let mut registros: Vec<Option<(PathBuf, fs::File)>> = volumenes.iter().map(|_| None).collect();
+    for grabacion in &grabaciones {
+        let meta = &grabacion.meta;
+        let raiz = volumenes[grabacion.volumen].ruta.as_path();
+        if plantilla.usa_subject() && meta.subject.is_none() {
+            // Sin el titulo guardado, renombrar lo perderia para siempre.
+            resumen.sin_cambios += 1;
+            salida.biblioteca_omitido(
+                &grabacion.media,
+                "la plantilla usa {subject} y el sidecar no lo guarda",
+            );
+            continue;
+        }
+        let sesion = meta.session_id.clone().unwrap_or_else(|| {
+            format!("{}-{}", meta.model, meta.started_at.format("%Y%m%dT%H%M%S"))
+        });
+        let parte = meta
+            .session_id
+            .as_deref()
+            .and_then(|id| partes.get(&(id, grabacion.quality)))
+            .and_then(|inicios| inicios.iter().position(|inicio| *inicio == meta.started_at))
+            .map_or(1, |posicion| posicion as u32 + 1);
+        let ext = grabacion
+            .media
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_else(|| config.extension_grabacion(grabacion.quality));
+        let datos = DatosNombre {
+            modelo: &meta.model,
+            inicio: meta.started_at,
+            quality: grabacion.quality,
+            sesion: &sesion,
+            parte,
+            subject: meta.subject.as_deref(),
+            ext,
+        };
+
+        let mut destino = config.ruta_grabacion(&datos, Some(raiz));
+        let hermanas = meta
+            .session_id
+            .as_deref()
+            .and_then(|id| calidades_por_sesion.get(id))
+            .is_some_and(|calidades| calidades.len() > 1);
+        if hermanas && !plantilla.usa_calidad() {
+            destino = ruta_con_calidad(&destino, grabacion.quality);
+        }
+        let pequena = meta.classification == "small";
+
+        let (reserva, destino) =
+            match ruta_libre(&destino, pequena, &grabacion.media, &reservadas).await {
+                Ok(Some(libre)) => libre,
+                Ok(None) => {
+                    resumen.sin_cambios += 1;
+                    continue;
+                }
+                Err(e) => {
+                    resumen.errores += 1;
+                    salida.biblioteca_error(&grabacion.media, &e.to_string());
+                    continue;
+                }
+            };
+        reservadas.insert(reserva);
+        salida.biblioteca_movimiento(&grabacion.media, &destino, simulado);
+        if simulado {
+            resumen.movidos += 1;
+            continue;
+        }
+
+        if let Err(e) = mover_grabacion(&grabacion.media, &destino).await {
+            resumen.errores += 1;
+            salida.biblioteca_error(&grabacion.media, &e.to_string());
+            continue;
+        }
+        resumen.movidos += 1;
+        let movimiento = Movimiento {
+            from: grabacion.media.clone(),
+            to: destino,
+        };
+        if let Err(e) = anotar_movimiento(&mut registros[grabacion.volumen], raiz, &movimiento) {
+            resumen.errores += 1;
+            salida.biblioteca_error(
+                &movimiento.to,
+                &format!("no se pudo anotar en el registro: {e}"),
+            );
+        }
+        if let Some(dir) = grabacion.media.parent() {
+            quitar_directorios_vacios(dir, raiz);
+        }
+    }
+
+    let registros: Vec<PathBuf> = registros
+        .into_iter()
+        .flatten()
+        .map(|(ruta, _)| ruta)
+        .collect();
+    salida.biblioteca_resumen(
+        resumen.movidos,
+        resumen.sin_cambios,
+        resumen.errores,
+        &registros,
+        simulado,
+    );
+    if resumen.errores > 0 {
+        anyhow::bail!("No se pudieron mover {} grabacion(es)", resumen.errores);
+    }
+    Ok(())
+}
+
+/// Puts back the files moved by the reorganization recorded in `log`.
+pub(crate) async fn deshacer(log: &Path, salida: &dyn Output) -> anyhow::Result<()> {
+    let archivo = fs::File::open(log)
+        .map_err(|e| anyhow::anyhow!("No se pudo abrir {}: {}", log.display(), e))?;
+    let mut movimientos = Vec::new();
+    for linea in BufReader::new(archivo).lines() {
+        let linea = linea?;
+        if linea.trim().is_empty() {
+            continue;
+        }
+        let movimiento: Movimiento = serde_json::from_str(&linea)
+            .map_err(|e| anyhow::anyhow!("Registro invalido {}: {}", log.display(), e))?;
+        movimientos.push(movimiento);
+    }
+
+    let mut resumen = Resumen::default();
+    for movimiento in movimientos.iter().rev() {
+        if movimiento.from.exists() || !movimiento.to.exists() {
+            resumen.sin_cambios += 1;
+            salida.biblioteca_omitido(&movimiento.to, "ya no esta donde lo dejo la reorganizacion");
+            continue;
+        }
+        salida.biblioteca_movimiento(&movimiento.to, &movimiento.from, false);
+        match mover_grabacion(&movimiento.to, &movimiento.from).await {
+            Ok(()) => {
+                resumen.movidos += 1;
+                if let (Some(dir), Some(raiz)) = (movimiento.to.parent(), log.parent()) {
+                    quitar_directorios_vacios(dir, raiz);
+                }
+            }
+            Err(e) => {
+                resumen.errores += 1;
+                salida.biblioteca_error(&movimiento.to, &e.to_string());
+            }
+        }
+    }
+
+    salida.biblioteca_resumen(
+        resumen.movidos,
+        resumen.sin_cambios,
+        resumen.errores,
+        &[],
+        false,
+    );
+    if resumen.errores > 0 {
+        anyhow::bail!("No se pudieron restaurar {} grabacion(es)", resumen.errores);
+    }
+    Ok(())
+}
+
+/// Media files with a readable sidecar, sorted by path. Hidden directories
+/// such as `.buffer` and unfinished `.part` files are skipped.
+fn buscar_grabaciones(
+    volumen: usize,
+    raiz: &Path,
+    salida: &dyn Output,
+) -> anyhow::Result<Vec<Grabacion>> {
+    let mut sidecars = Vec::new();
+    let mut pendientes = vec![raiz.to_path_buf()];
+    while let Some(dir) = pendientes.pop() {
+        let entradas = match fs::read_dir(&dir) {
+            Ok(entradas) => entradas,
+            Err(e) if dir == raiz => {
+                anyhow::bail!("No se pudo leer {}: {}", raiz.display(), e)
+            }
+            Err(_) => continue,
+        };
+        for entrada in entradas.flatten() {
+            let ruta = entrada.path();
+            let nombre = entrada.file_name().to_string_lossy().into_owned();
+            let Ok(tipo) = entrada.file_type() else {
+                continue;
+            };
+            if nombre.starts_with('.') {
+                continue;
+            }
+            if tipo.is_dir() {
+                pendientes.push(ruta);
+            } else if tipo.is_file() && nombre.ends_with(".json") && !nombre.contains(".part.") {
+                sidecars.push(ruta);
+            }
+        }
+    }
+    sidecars.sort();
+
+    let mut grabaciones = Vec::new();
+    for sidecar in sidecars {
+        let media = sidecar.with_extension("");
+        if !media.is_file() {
+            continue;
+        }
+        let meta = fs::read_to_string(&sidecar)
+            .ok()
+            .and_then(|contenido| serde_json::from_str::<SidecarGrabacion>(&contenido).ok());
+        let Some(meta) = meta else {
+            salida.biblioteca_omitido(&media, "sidecar ilegible");
+            continue;
+        };
+        if meta.schema_version != 1 {
+            salida.biblioteca_omitido(&media, "version de sidecar desconocida");
+            continue;
+        }
+        let Ok(quality) = VideoQuality::from_str(&meta.requested_quality) else {
+            salida.biblioteca_omitido(&media, "calidad desconocida en el sidecar");
+            continue;
+        };
+        grabaciones.push(Grabacion {
+            volumen,
+            media,
+            meta,
+            quality,
+        });
+    }
+    Ok(grabaciones)
+}
+
+/// First free name for `destino`, numbered and checked like new recordings,
+/// as `(reserved name, target)`; small recordings go to its `small/`
+/// directory. `None` when the recording already sits there.
+async fn ruta_libre(
+    destino: &Path,
+    pequena: bool,
+    actual: &Path,
+    reservadas: &HashSet<PathBuf>,
+) -> Result<Option<(PathBuf, PathBuf)>, InfrastructureError> {
+    for intento in 0..1000 {
+        let ruta = ruta_con_sufijo(destino, intento);
+        let objetivo = match (pequena, ruta.parent(), ruta.file_name()) {
+            (true, Some(dir), Some(nombre)) => dir.join("small").join(nombre),
+            _ => ruta.clone(),
+        };
+        if objetivo == actual {
+            return Ok(None);
+        }
+        if !reservadas.contains(&ruta) && ruta_disponible(&ruta).await? {
+            return Ok(Some((ruta, objetivo)));
+        }
+    }
+    Ok(None)
+}
+
+/// Moves the media file and then its sidecar, copying across file systems;
+/// the media file goes back if the sidecar cannot follow.
+async fn mover_grabacion(origen: &Path, destino: &Path) -> std::io::Result<()> {
+    if let Some(dir) = destino.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    mover_archivo(origen, destino, &ruta_parcial(destino)).await?;
+    let sidecar = path_with_suffix(origen, ".json");
+    let destino_sidecar = path_with_suffix(destino, ".json");
+    let temporal = path_with_suffix(&destino_sidecar, ".part");
+    if let Err(e) = mover_archivo(&sidecar, &destino_sidecar, &temporal).await {
+        mover_archivo(destino, origen, &ruta_parcial(origen)).await?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Appends a move to the rollback log, creating it on the first move.
+fn anotar_movimiento(
+    registro: &mut Option<(PathBuf, fs::File)>,
+    raiz: &Path,
+    movimiento: &Movimiento,
+) -> std::io::Result<()> {
+    if registro.is_none() {
+        let ruta = raiz.join(format!(
+            ".cbrec-reorganize-{}.jsonl",
+            Utc::now().format("%Y%m%dT%H%M%S")
+        ));
+        let archivo = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&ruta)?;
+        *registro = Some((ruta, archivo));
+    }
+    let Some((_, archivo)) = registro.as_mut() else {
+        return Ok(());
+    };
+    let linea = serde_json::to_string(movimiento).map_err(std::io::Error::other)?;
+    writeln!(archivo, "{linea}")?;
+    archivo.sync_data()
+}
+
+/// Removes `dir` and its parents while they are empty, stopping at `raiz`.
+fn quitar_directorios_vacios(dir: &Path, raiz: &Path) {
+    let mut actual = Some(dir);
+    while let Some(dir) = actual {
+        if dir == raiz || !dir.starts_with(raiz) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        actual = dir.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::VolumenSalida;
+    use crate::presentation::ConsoleOutput;
+
+    fn grabacion_legacy(raiz: &Path, sesion: &str) -> PathBuf {
+        let media = raiz
+            .join("cb_rec")
+            .join("alice")
+            .join("2026.03.04_05.06.07_alice.mp4");
+        fs::create_dir_all(media.parent().unwrap()).unwrap();
+        fs::write(&media, b"mp4").unwrap();
+        fs::write(
+            path_with_suffix(&media, ".json"),
+            format!(
+                r#"{{"schema_version":1,"model":"alice","requested_quality":"480p","started_at":"2026-03-04T05:06:07Z","classification":"saved","session_id":"{sesion}"}}"#
+            ),
+        )
+        .unwrap();
+        media
+    }
+
+    fn raiz_temporal(nombre: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "cbrec_library_{}_{}",
+            nombre,
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default()
+        ))
+    }
+
+    fn plantilla(plantilla: &str) -> AppConfig {
+        AppConfig {
+            naming_template: plantilla.to_string(),
+            ..AppConfig::default()
+        }
+    }
+
+    fn registro(raiz: &Path) -> PathBuf {
+        fs::read_dir(raiz)
+            .unwrap()
+            .flatten()
+            .map(|entrada| entrada.path())
+            .find(|ruta| ruta.to_string_lossy().ends_with(".jsonl"))
+            .expect("registro de la reorganizacion")
+    }
+
+    #[tokio::test]
+    async fn reorganize_moves_media_and_sidecar_and_rollback_restores_them() {
+        let raiz = raiz_temporal("mueve");
+        let media = grabacion_legacy(&raiz, "alice-s1");
+        let config = plantilla("{model}/{quality}/{session}_{part:02}.{ext}");
+        let salida = ConsoleOutput::new(false, true);
+
+        reorganizar(&config, Some(&raiz), true, &salida)
+            .await
+            .expect("simulacion");
+        assert!(media.exists());
+
+        let destino = raiz.join("alice").join("480p").join("alice-s1_01.mp4");
+        fs::create_dir_all(destino.parent().unwrap()).unwrap();
+        fs::write(&destino, b"otro").unwrap();
+        reorganizar(&config, Some(&raiz), false, &salida)
+            .await
+            .expect("reorganiza");
+
+        let movido = raiz.join("alice").join("480p").join("alice-s1_01_001.mp4");
+        assert!(!media.exists());
+        assert!(!raiz.join("cb_rec").exists());
+        assert_eq!(fs::read(&movido).unwrap(), b"mp4");
+        assert!(path_with_suffix(&movido, ".json").exists());
+
+        deshacer(&registro(&raiz), &salida).await.expect("deshace");
+        assert!(media.exists());
+        assert!(path_with_suffix(&media, ".json").exists());
+        assert!(!movido.exists());
+        let _ = fs::remove_dir_all(raiz);
+    }
+
+    #[tokio::test]
+    async fn reorganize_keeps_each_recording_on_its_volume() {
+        let raiz = raiz_temporal("volumenes");
+        let (ssd, nas) = (raiz.join("ssd"), raiz.join("nas"));
+        fs::create_dir_all(&ssd).unwrap();
+        let media = grabacion_legacy(&nas, "alice-s1");
+        let config = AppConfig {
+            output_volumes: [&ssd, &nas]
+                .into_iter()
+                .map(|ruta| VolumenSalida {
+                    ruta: ruta.clone(),
+                    min_free_space: 0,
+                })
+                .collect(),
+            ..plantilla("{model}/{session}.{ext}")
+        };
+        let salida = ConsoleOutput::new(false, true);
+
+        reorganizar(&config, None, false, &salida)
+            .await
+            .expect("reorganiza");
+
+        let movido = nas.join("alice").join("alice-s1.mp4");
+        assert!(!media.exists());
+        assert!(movido.exists());
+        assert!(!ssd.join("alice").exists());
+        deshacer(&registro(&nas), &salida).await.expect("deshace");
+        assert!(media.exists());
+        let _ = fs::remove_dir_all(raiz);
+    }
+
+    #[tokio::test]
+    async fn reorganize_skips_names_the_recorder_would_not_use() {
+        let raiz = raiz_temporal("colision");
+        grabacion_legacy(&raiz, "alice-s1");
+        let dir = raiz.join("alice");
+        fs::create_dir_all(dir.join("small")).unwrap();
+        // Grabacion en curso y otra pequena con los dos primeros nombres.
+        fs::write(ruta_parcial(&dir.join("alice-s1.mp4")), b"").unwrap();
+        fs::write(dir.join("small").join("alice-s1_001.mp4"), b"mp4").unwrap();
+        let config = plantilla("{model}/{session}.{ext}");
+
+        reorganizar(
+            &config,
+            Some(&raiz),
+            false,
+            &ConsoleOutput::new(false, true),
+        )
+        .await
+        .expect("reorganiza");
+
+        assert_eq!(fs::read(dir.join("alice-s1_002.mp4")).unwrap(), b"mp4");
+        assert!(!dir.join("alice-s1.mp4").exists());
+        assert!(!dir.join("alice-s1_001.mp4").exists());
+        let _ = fs::remove_dir_all(raiz);
+    }
+
+    #[tokio::test]
+    async fn reorganize_keeps_recordings_without_the_subject_the_template_needs() {
+        let raiz = raiz_temporal("subject");
+        let media = grabacion_legacy(&raiz, "alice-s1");
+        let config = plantilla("{model}/{subject}.{ext}");
+
+        reorganizar(
+            &config,
+            Some(&raiz),
+            false,
+            &ConsoleOutput::new(false, true),
+        )
+        .await
+        .expect("reorganiza");
+
+        assert!(media.exists());
+        assert!(!raiz.join("alice").exists());
+        let _ = fs::remove_dir_all(raiz);
+    }
+
+    #[tokio::test]
+    async fn failed_sidecar_move_puts_the_media_back() {
+        let raiz = raiz_temporal("revierte");
+        let media = grabacion_legacy(&raiz, "alice-s1");
+        let destino = raiz.join("alice").join("alice-s1.mp4");
+        fs::create_dir_all(path_with_suffix(&destino, ".json").join("ocupado")).unwrap();
+
+        assert!(mover_grabacion(&media, &destino).await.is_err());
+
+        assert_eq!(fs::read(&media).unwrap(), b"mp4");
+        assert!(path_with_suffix(&media, ".json").exists());
+        assert!(!destino.exists());
+        let _ = fs::remove_dir_all(raiz);
+    }
+
+    #[tokio::test]
+    async fn rollback_restores_what_it_can_and_skips_files_moved_again() {
+        let raiz = raiz_temporal("deshace");
+        let media = grabacion_legacy(&raiz, "alice-s1");
+        let movido = raiz.join("alice").join("alice-s1.mp4");
+        mover_grabacion(&media, &movido).await.unwrap();
+        let log = raiz.join(".cbrec-reorganize-prueba.jsonl");
+        fs::write(
+            &log,
+            format!(
+                "{}\n{}\n",
+                serde_json::json!({"from": media, "to": movido}),
+                serde_json::json!({"from": raiz.join("a.mp4"), "to": raiz.join("b.mp4")}),
+            ),
+        )
+        .unwrap();
+
+        deshacer(&log, &ConsoleOutput::new(false, true))
+            .await
+            .expect("deshace");
+
+        assert_eq!(fs::read(&media).unwrap(), b"mp4");
+        assert!(path_with_suffix(&media, ".json").exists());
+        assert!(!movido.exists());
+        assert!(!raiz.join("alice").exists());
+        let _ = fs::remove_dir_all(raiz);
+    }
+}
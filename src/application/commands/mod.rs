@@ -1,8 +1,12 @@
 pub(crate) mod add;
+pub(crate) mod buffer;
 pub(crate) mod check;
 pub(crate) mod discover;
 pub(crate) mod doctor;
+pub(crate) mod library;
 pub(crate) mod list;
 pub(crate) mod record;
 pub(crate) mod remove;
+pub(crate) mod similar;
+pub(crate) mod stats;
 pub(crate) mod tui;
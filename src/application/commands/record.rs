@@ -1,10 +1,10 @@
 use crate::application::recording::{
-    descargar_grabacion, detener_tarea_progreso, preparar_ruta_grabacion, ruta_parcial,
-    write_recording_metadata, ResultadoGrabacion,
+    grabar_calidades, ResultadoGrabacion, SesionGrabacion, VigilanciaSala,
 };
-use crate::application::utils::{normalizar_modelos, ParametrosGrabacion};
+use crate::application::retry_policy::{HistorialFallos, Reintento};
+use crate::application::utils::{esperar_reintento, normalizar_modelos, ParametrosGrabacion};
 use crate::domain::value_objects::{ModelName, VideoQuality};
-use crate::infrastructure::{AppConfig, ChaturbateClient, EstadoStream};
+use crate::infrastructure::{AppConfig, ChaturbateClient, EstadoStream, InfrastructureError};
 use crate::presentation::Output;
 use std::path::Path;
 use std::sync::Arc;
@@ -13,11 +13,11 @@ use tokio::sync::mpsc;
 use tokio::task::JoinSet;
 
 #[derive(Clone, Copy)]
-struct OpcionesGrabacionModelo<'a> {
-    raiz_salida_override: Option<&'a Path>,
-    quality: VideoQuality,
-    min_file_size: Option<u64>,
-    modo_detallado: bool,
+pub(crate) struct OpcionesGrabacionModelo<'a> {
+    pub(crate) raiz_salida_override: Option<&'a Path>,
+    pub(crate) calidades: &'a [VideoQuality],
+    pub(crate) min_file_size: Option<u64>,
+    pub(crate) modo_detallado: bool,
 }
 
 pub(crate) async fn grabar_modelos(
@@ -28,7 +28,7 @@ pub(crate) async fn grabar_modelos(
 ) -> anyhow::Result<()> {
     let ParametrosGrabacion {
         raiz_salida,
-        quality,
+        calidades,
         limite_concurrencia,
         min_file_size,
         cancel_rx,
@@ -36,6 +36,7 @@ pub(crate) async fn grabar_modelos(
     } = parametros;
     let client = Arc::new(client);
     let config = Arc::new(config);
+    let calidades: Arc<[VideoQuality]> = calidades.into();
 
     let (modelos, duplicados) = normalizar_modelos(modelos)?;
     if duplicados > 0 {
@@ -66,6 +67,7 @@ pub(crate) async fn grabar_modelos(
         let rx = Arc::clone(&rx);
         let cancel_rx = cancel_rx.clone();
         let salida = Arc::clone(&salida);
+        let calidades = Arc::clone(&calidades);
 
         tareas.spawn(async move {
             let mut errores = Vec::new();
@@ -93,7 +95,7 @@ pub(crate) async fn grabar_modelos(
                     &modelo,
                     OpcionesGrabacionModelo {
                         raiz_salida_override: raiz_salida.as_deref(),
-                        quality,
+                        calidades: &calidades,
                         min_file_size,
                         modo_detallado,
                     },
@@ -128,7 +130,7 @@ pub(crate) async fn grabar_modelos(
     Ok(())
 }
 
-async fn grabar_modelo(
+pub(crate) async fn grabar_modelo(
     client: &ChaturbateClient,
     config: &AppConfig,
     target: &str,
@@ -137,108 +139,151 @@ async fn grabar_modelo(
 ) -> anyhow::Result<()> {
     let OpcionesGrabacionModelo {
         raiz_salida_override,
-        quality,
+        calidades,
         min_file_size,
         modo_detallado,
     } = opciones;
+    let Some(&quality) = calidades.first() else {
+        anyhow::bail!("Sin calidades que grabar");
+    };
 
+    let calidades_texto = calidades
+        .iter()
+        .map(VideoQuality::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
     if modo_detallado {
-        salida.mostrar_inicio_detallado(target, &quality.to_string());
+        salida.mostrar_inicio_detallado(target, &calidades_texto);
     } else {
-        salida.mostrar_inicio_resumido(target, &quality.to_string());
+        salida.mostrar_inicio_resumido(target, &calidades_texto);
     }
 
     let model_name = ModelName::try_from(target)?;
+    let client = &client.for_model(model_name.as_str());
 
-    if modo_detallado {
-        salida.mostrar_verificando_disponibilidad();
-    }
+    let cancel_rx = client.cancel_receiver();
+    let mut fallos = HistorialFallos::default();
+    let mut ultimo_error: Option<InfrastructureError> = None;
 
-    let stream_url = match client.consultar_estado(&model_name).await? {
-        EstadoStream::Online { stream_url } => stream_url,
-        EstadoStream::Offline => {
-            if modo_detallado {
-                salida.mostrar_modelo_offline_detallado(model_name.as_str());
-            } else {
-                salida.mostrar_modelo_offline_resumido(model_name.as_str());
-            }
-            return Ok(());
+    loop {
+        if modo_detallado {
+            salida.mostrar_verificando_disponibilidad();
         }
-        EstadoStream::RequiereSesion { detalle } => {
-            anyhow::bail!(
-                "{} requiere sesion o acceso privado: {}",
-                model_name,
-                detalle
-            );
-        }
-        EstadoStream::RateLimited => {
-            anyhow::bail!("Chaturbate limito las consultas; reintenta mas tarde");
-        }
-        EstadoStream::Bloqueado { detalle } => {
-            anyhow::bail!("Respuesta bloqueada por Chaturbate: {}", detalle);
-        }
-        EstadoStream::RespuestaInesperada { detalle } => {
-            anyhow::bail!("Respuesta inesperada de Chaturbate: {}", detalle);
-        }
-    };
 
-    if modo_detallado {
-        salida.mostrar_modelo_online_detallado();
-    }
+        let stream_url = match client.consultar_estado(&model_name).await? {
+            EstadoStream::Online { stream_url } => stream_url,
+            EstadoStream::Offline => {
+                if let Some(error) = ultimo_error {
+                    return Err(error.into());
+                }
+                if modo_detallado {
+                    salida.mostrar_modelo_offline_detallado(model_name.as_str());
+                } else {
+                    salida.mostrar_modelo_offline_resumido(model_name.as_str());
+                }
+                return Ok(());
+            }
+            EstadoStream::RequiereSesion { detalle } => {
+                anyhow::bail!(
+                    "{} requiere sesion o acceso privado: {}",
+                    model_name,
+                    detalle
+                );
+            }
+            EstadoStream::RateLimited => {
+                anyhow::bail!("Chaturbate limito las consultas; reintenta mas tarde");
+            }
+            EstadoStream::Bloqueado { detalle } => {
+                anyhow::bail!("Respuesta bloqueada por Chaturbate: {}", detalle);
+            }
+            EstadoStream::RespuestaInesperada { detalle } => {
+                anyhow::bail!("Respuesta inesperada de Chaturbate: {}", detalle);
+            }
+        };
 
-    let ruta_base = config.get_output_path(model_name.as_str(), raiz_salida_override);
-    let ruta = preparar_ruta_grabacion(ruta_base).await?;
+        if modo_detallado {
+            salida.mostrar_modelo_online_detallado();
+        }
 
-    if modo_detallado {
-        salida.mostrar_detalle_inicio_grabacion(&ruta);
-    }
+        let sesion = SesionGrabacion {
+            modelo: &model_name,
+            config,
+            raiz_salida: raiz_salida_override,
+            quality,
+            id_sesion: None,
+            subject: None,
+            min_file_size,
+            vigilancia: VigilanciaSala::desde_config(&config.recording),
+            cancel_rx: cancel_rx.clone(),
+            salida: Arc::clone(&salida),
+        };
+        let (segmentos, resultado) = grabar_calidades(
+            &Arc::new(client.clone()),
+            sesion,
+            calidades,
+            stream_url,
+            |ruta| {
+                if modo_detallado {
+                    salida.mostrar_detalle_inicio_grabacion(ruta);
+                }
+            },
+        )
+        .await;
+        mostrar_segmentos(salida.as_ref(), target, segmentos, modo_detallado);
 
-    let parcial = ruta_parcial(&ruta);
-    let salida_p = Arc::clone(&salida);
-    let nombre_p = target.to_string();
-    let parcial_p = parcial.clone();
-    let progress_task = tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(Duration::from_secs(5)).await;
-            if let Ok(meta) = tokio::fs::metadata(&parcial_p).await {
-                salida_p.mostrar_progreso_grabacion(&nombre_p, meta.len());
-            }
+        let Err(error) = resultado else {
+            return Ok(());
+        };
+        let espera = match fallos.registrar(model_name.as_str(), error.clase_fallo(), &config.retry)
+        {
+            Reintento::Inmediato => Duration::ZERO,
+            Reintento::Esperar(espera) | Reintento::PausarTodo(espera) => espera,
+            Reintento::Abandonar(_) => return Err(error.into()),
+        };
+        salida.error_fallo_grabacion(target, &error.to_string());
+        salida.mostrar_reintento_grabacion(
+            target,
+            &error.clase_fallo().to_string(),
+            espera.as_secs(),
+        );
+        if !esperar_reintento(espera, cancel_rx.clone()).await {
+            return Ok(());
         }
-    });
-
-    let started_at = chrono::Utc::now();
-    let result = descargar_grabacion(client, &stream_url, ruta, quality, min_file_size).await;
-    detener_tarea_progreso(progress_task).await;
-    if let Ok(recording) = &result {
-        write_recording_metadata(recording, model_name.as_str(), quality, started_at).await?;
+        ultimo_error = Some(error);
     }
+}
 
-    match result {
-        Ok(ResultadoGrabacion::Guardado(ruta)) => {
-            if modo_detallado {
-                salida.mostrar_archivo_guardado_detallado(&ruta);
-            } else {
-                salida.mostrar_archivo_guardado_resumido(target, &ruta);
+fn mostrar_segmentos(
+    salida: &dyn Output,
+    target: &str,
+    segmentos: Vec<ResultadoGrabacion>,
+    modo_detallado: bool,
+) {
+    for segmento in segmentos {
+        match segmento {
+            ResultadoGrabacion::Guardado(ruta) => {
+                if modo_detallado {
+                    salida.mostrar_archivo_guardado_detallado(&ruta);
+                } else {
+                    salida.mostrar_archivo_guardado_resumido(target, &ruta);
+                }
             }
-        }
-        Ok(ResultadoGrabacion::Pequeno(ruta, bytes)) => {
-            if modo_detallado {
-                salida.mostrar_archivo_pequeno_detallado(bytes, &ruta);
-            } else {
-                salida.mostrar_archivo_pequeno_resumido(target, &ruta);
+            ResultadoGrabacion::Pequeno(ruta, bytes) => {
+                if modo_detallado {
+                    salida.mostrar_archivo_pequeno_detallado(bytes, &ruta);
+                } else {
+                    salida.mostrar_archivo_pequeno_resumido(target, &ruta);
+                }
             }
-        }
-        Ok(ResultadoGrabacion::Cancelado) => {
-            if modo_detallado {
-                salida.mostrar_cancelacion_detallada();
-            } else {
-                salida.mostrar_cancelacion_resumida(target);
+            ResultadoGrabacion::Cancelado => {
+                if modo_detallado {
+                    salida.mostrar_cancelacion_detallada();
+                } else {
+                    salida.mostrar_cancelacion_resumida(target);
+                }
             }
         }
-        Err(e) => return Err(e.into()),
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
@@ -251,7 +296,7 @@ mod tests {
         let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(true);
         let parameters = ParametrosGrabacion {
             raiz_salida: None,
-            quality: VideoQuality::Best,
+            calidades: vec![VideoQuality::Best],
             limite_concurrencia: 1,
             min_file_size: None,
             cancel_rx,
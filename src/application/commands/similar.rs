@@ -0,0 +1,100 @@
+//! Finds rooms similar to a model without changing account or recording state.
+
+use crate::application::commands::discover::MAX_RESULTS;
+use crate::application::utils::normalizar_modelo;
+use crate::infrastructure::external::DiscoveredRoom;
+use crate::infrastructure::ChaturbateClient;
+use crate::presentation::Output;
+use serde::Serialize;
+
+/// Validated recommendation response shared by terminal presentation adapters.
+#[derive(Serialize)]
+pub(crate) struct SimilarResult {
+    /// Normalized model used as the recommendation source.
+    pub(crate) model: String,
+    /// Public rooms recommended for the model, without the model itself.
+    pub(crate) rooms: Vec<DiscoveredRoom>,
+}
+
+pub(crate) async fn similar_rooms(
+    client: &ChaturbateClient,
+    output: &dyn Output,
+    raw_model: &str,
+    limit: usize,
+) -> anyhow::Result<()> {
+    let result = find_similar(client, raw_model, limit).await?;
+    if result.rooms.is_empty() {
+        output.similar_empty(&result.model);
+        return Ok(());
+    }
+
+    output.similar_started(&result.model, result.rooms.len());
+    for room in result.rooms {
+        output.discovery_room(&room.username, room.viewers, &room.show, &room.subject);
+    }
+    Ok(())
+}
+
+/// Returns one compact JSON document for a recommendation query.
+pub(crate) async fn similar_rooms_json(
+    client: &ChaturbateClient,
+    raw_model: &str,
+    limit: usize,
+) -> anyhow::Result<String> {
+    Ok(serde_json::to_string(
+        &find_similar(client, raw_model, limit).await?,
+    )?)
+}
+
+/// Validates the query and returns at most 50 recommended public rooms.
+pub(crate) async fn find_similar(
+    client: &ChaturbateClient,
+    raw_model: &str,
+    limit: usize,
+) -> anyhow::Result<SimilarResult> {
+    let model = normalizar_modelo(raw_model)?;
+    if !(1..=MAX_RESULTS).contains(&limit) {
+        anyhow::bail!("El limite debe estar entre 1 y {MAX_RESULTS}");
+    }
+
+    let rooms = client.similar_rooms(&model, limit).await?;
+    Ok(SimilarResult {
+        model: model.as_str().to_string(),
+        rooms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similar_json_has_model_and_rooms() {
+        let result = SimilarResult {
+            model: "alice".to_string(),
+            rooms: vec![DiscoveredRoom {
+                username: "bob".to_string(),
+                subject: "hello".to_string(),
+                viewers: 7,
+                show: "public".to_string(),
+            }],
+        };
+
+        assert_eq!(
+            serde_json::to_string(&result).unwrap(),
+            r#"{"model":"alice","rooms":[{"username":"bob","subject":"hello","viewers":7,"show":"public"}]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn find_similar_rejects_invalid_limit_before_requesting() {
+        let client = ChaturbateClient::new().expect("create client");
+
+        let error = find_similar(&client, "alice", 0)
+            .await
+            .err()
+            .expect("limit zero must fail");
+
+        assert!(error.to_string().contains("El limite"));
+    }
+}
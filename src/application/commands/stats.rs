@@ -0,0 +1,16 @@
+use crate::application::utils::normalizar_modelo;
+use crate::infrastructure::OnlineHistory;
+use crate::presentation::Output;
+
+/// Shows the weekly heat map learned by `watch` for one model.
+pub(crate) fn mostrar_estadisticas(modelo: &str, salida: &dyn Output) -> anyhow::Result<()> {
+    let modelo = normalizar_modelo(modelo)?;
+    let historial = OnlineHistory::load();
+    match historial.heat_map(modelo.as_str()) {
+        Some(horas) if horas.iter().any(|h| *h > 0) => {
+            salida.stats_mapa_actividad(modelo.as_str(), horas)
+        }
+        _ => salida.stats_sin_datos(modelo.as_str()),
+    }
+    Ok(())
+}
@@ -1,20 +1,319 @@
-//! Loads public discovery data before handing control to the TUI adapter.
+//! Loads public discovery data and runs TUI actions on behalf of the adapter.
+//!
+//! The terminal loop is synchronous, so every request is driven to completion
+//! on the current runtime while background recordings keep running as tasks.
 
-use crate::application::commands::discover;
-use crate::infrastructure::ChaturbateClient;
-use crate::presentation::{run_discovery_tui, TuiRoom};
+use crate::application::commands::record::{self, OpcionesGrabacionModelo};
+use crate::application::commands::{discover, similar};
+use crate::application::utils::{normalizar_modelo, validar_ffmpeg};
+use crate::domain::value_objects::{ProgresoGrabacion, VarianteDisponible, VideoQuality};
+use crate::infrastructure::external::DiscoveredRoom;
+use crate::infrastructure::{AppConfig, ChaturbateClient, WatchedModels};
+use crate::presentation::output::formatear_progreso;
+use crate::presentation::{run_discovery_tui, DiscoverySource, Output, TuiRoom};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+/// Recording settings resolved from the global CLI flags.
+pub(crate) struct OpcionesTui {
+    pub(crate) raiz_salida: Option<PathBuf>,
+    pub(crate) quality: VideoQuality,
+    pub(crate) min_file_size: Option<u64>,
+    pub(crate) limite_concurrencia: usize,
+    pub(crate) ruta_ffmpeg: PathBuf,
+    pub(crate) ffmpeg_explicito: bool,
+}
+
+/// Loads follow-up discovery queries and owns background recordings.
+struct ClientSource {
+    client: Arc<ChaturbateClient>,
+    config: Arc<AppConfig>,
+    limit: usize,
+    opciones: OpcionesTui,
+    ffmpeg_validado: bool,
+    activas: Arc<Mutex<HashSet<String>>>,
+    tareas: JoinSet<()>,
+    eventos: Arc<EventosTui>,
+    eventos_rx: mpsc::Receiver<String>,
+    cancel_tx: watch::Sender<bool>,
+}
+
+impl ClientSource {
+    fn bloquear<F: std::future::Future>(future: F) -> F::Output {
+        tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+    }
+
+    /// Stops background recordings so FFmpeg can finalize each file.
+    fn cerrar(mut self) {
+        self.eventos.abierta.store(false, Ordering::SeqCst);
+        if self.tareas.is_empty() {
+            return;
+        }
+        self.eventos.consola.watch_deteniendo();
+        let _ = self.cancel_tx.send(true);
+        Self::bloquear(async { while self.tareas.join_next().await.is_some() {} });
+    }
+}
+
+impl DiscoverySource for ClientSource {
+    fn rooms_by_tag(&mut self, tag: &str, page: usize) -> anyhow::Result<(String, Vec<TuiRoom>)> {
+        let offset = page.saturating_mul(self.limit);
+        let result = Self::bloquear(discover::find_rooms(&self.client, tag, self.limit, offset))?;
+        Ok((result.tag, result.rooms.into_iter().map(tui_room).collect()))
+    }
+
+    fn similar(&mut self, username: &str) -> anyhow::Result<Vec<TuiRoom>> {
+        let result = Self::bloquear(similar::find_similar(&self.client, username, self.limit))?;
+        Ok(result.rooms.into_iter().map(tui_room).collect())
+    }
+
+    fn add_to_watchlist(&mut self, username: &str) -> anyhow::Result<bool> {
+        let modelo = normalizar_modelo(username)?;
+        Ok(WatchedModels::update(|watched| {
+            let agregado = watched.add(modelo.as_str());
+            (agregado, agregado)
+        })?)
+    }
+
+    fn start_recording(&mut self, username: &str) -> anyhow::Result<()> {
+        let modelo = normalizar_modelo(username)?.as_str().to_string();
+        {
+            let activas = self.activas.lock().expect("activas");
+            if activas.contains(&modelo) {
+                anyhow::bail!("{modelo} ya se esta grabando");
+            }
+            if activas.len() >= self.opciones.limite_concurrencia {
+                anyhow::bail!(
+                    "Limite de {} grabacion(es) simultanea(s) alcanzado",
+                    self.opciones.limite_concurrencia
+                );
+            }
+        }
+        if !self.ffmpeg_validado {
+            Self::bloquear(validar_ffmpeg(
+                &self.opciones.ruta_ffmpeg,
+                self.opciones.ffmpeg_explicito,
+            ))?;
+            self.ffmpeg_validado = true;
+        }
+
+        self.activas.lock().expect("activas").insert(modelo.clone());
+        let client = Arc::clone(&self.client);
+        let config = Arc::clone(&self.config);
+        let activas = Arc::clone(&self.activas);
+        let eventos = Arc::clone(&self.eventos);
+        let raiz_salida = self.opciones.raiz_salida.clone();
+        let quality = self.opciones.quality;
+        let min_file_size = self.opciones.min_file_size;
+        self.tareas.spawn_on(
+            async move {
+                let salida: Arc<dyn Output> = eventos.clone();
+                let resultado = record::grabar_modelo(
+                    &client,
+                    &config,
+                    &modelo,
+                    OpcionesGrabacionModelo {
+                        raiz_salida_override: raiz_salida.as_deref(),
+                        calidades: &[quality],
+                        min_file_size,
+                        modo_detallado: false,
+                    },
+                    salida,
+                )
+                .await;
+                if let Err(err) = resultado {
+                    eventos.error_fallo_grabacion(&modelo, &err.to_string());
+                }
+                activas.lock().expect("activas").remove(&modelo);
+            },
+            &tokio::runtime::Handle::current(),
+        );
+        Ok(())
+    }
+
+    fn poll_event(&mut self) -> Option<String> {
+        while let Some(Ok(())) = self.tareas.try_join_next() {}
+        self.eventos_rx.try_iter().last()
+    }
+
+    fn active_recordings(&self) -> usize {
+        self.activas.lock().expect("activas").len()
+    }
+}
+
+/// Routes recording events to the TUI status line while it is open and to
+/// the console once it closes.
+struct EventosTui {
+    tx: mpsc::Sender<String>,
+    abierta: AtomicBool,
+    consola: Arc<dyn Output>,
+}
+
+impl EventosTui {
+    fn enviar(&self, mensaje: String) -> bool {
+        self.abierta.load(Ordering::SeqCst) && self.tx.send(mensaje).is_ok()
+    }
+}
+
+impl Output for EventosTui {
+    fn advertir_limite_concurrencia(&self, _recomendado: usize, _solicitado: usize) {}
+    fn mostrar_error_sin_modelo(&self) {}
+    fn advertir_modelos_duplicados(&self, _duplicados: usize) {}
+    fn advertir_modelos_sobre_limite(&self, _total: usize, _limite: usize) {}
+    fn advertir_no_se_pudo_guardar_lista(&self, _error: &str) {}
+    fn advertir_error_consulta_estado(&self, _modelo: &str, _error: &str) {}
+    fn advertir_config(&self, _warning: &str) {}
+    fn modelo_agregado(&self, _modelo: &str) {}
+    fn modelo_ya_en_lista(&self, _modelo: &str) {}
+    fn modelo_eliminado(&self, _modelo: &str) {}
+    fn modelo_no_encontrado_en_lista(&self, _modelo: &str) {}
+
+    fn error_fallo_grabacion(&self, modelo: &str, error: &str) {
+        if !self.enviar(format!("{modelo}: error: {error}")) {
+            self.consola.error_fallo_grabacion(modelo, error);
+        }
+    }
+
+    fn error_tarea_abortada(&self, _error: &str) {}
+    fn mostrar_inicio_detallado(&self, _modelo: &str, _calidad: &str) {}
+
+    fn mostrar_inicio_resumido(&self, modelo: &str, calidad: &str) {
+        self.enviar(format!("{modelo}: verificando ({calidad})"));
+    }
+
+    fn mostrar_verificando_disponibilidad(&self) {}
+    fn mostrar_modelo_offline_detallado(&self, _modelo: &str) {}
+
+    fn mostrar_modelo_offline_resumido(&self, modelo: &str) {
+        if !self.enviar(format!("{modelo}: offline")) {
+            self.consola.mostrar_modelo_offline_resumido(modelo);
+        }
+    }
+
+    fn mostrar_modelo_online_detallado(&self) {}
+    fn mostrar_detalle_inicio_grabacion(&self, _ruta: &Path) {}
+    fn mostrar_cancelacion_detallada(&self) {}
+
+    fn mostrar_cancelacion_resumida(&self, modelo: &str) {
+        if !self.enviar(format!("{modelo}: cancelado")) {
+            self.consola.mostrar_cancelacion_resumida(modelo);
+        }
+    }
+
+    fn mostrar_archivo_pequeno_detallado(&self, _bytes: u64, _destino: &Path) {}
+
+    fn mostrar_archivo_pequeno_resumido(&self, modelo: &str, destino: &Path) {
+        if !self.enviar(format!("{modelo}: archivo pequeño {}", destino.display())) {
+            self.consola
+                .mostrar_archivo_pequeno_resumido(modelo, destino);
+        }
+    }
+
+    fn mostrar_archivo_guardado_detallado(&self, _ruta: &Path) {}
+
+    fn mostrar_archivo_guardado_resumido(&self, modelo: &str, ruta: &Path) {
+        if !self.enviar(format!("{modelo}: guardado {}", ruta.display())) {
+            self.consola.mostrar_archivo_guardado_resumido(modelo, ruta);
+        }
+    }
+
+    fn mostrar_inicio_verificacion(&self, _modelo: &str) {}
+    fn mostrar_estado_modelo(&self, _modelo: &str, _online: bool) {}
+    fn mostrar_modelo_sin_variantes(&self, _modelo: &str) {}
+    fn mostrar_calidades(&self, _modelo: &str, _calidades: &[VarianteDisponible]) {}
+
+    fn mostrar_progreso_grabacion(&self, modelo: &str, progreso: &ProgresoGrabacion) {
+        self.enviar(format!(
+            "{modelo}: grabando {}",
+            formatear_progreso(progreso)
+        ));
+    }
+
+    fn watch_inicio(&self, _modelos: &[&str]) {}
+    fn watch_tick_online(&self, _modelo: &str) {}
+    fn watch_tick_offline(&self, _modelo: &str) {}
+    fn watch_inicio_grabacion(&self, _modelo: &str) {}
+    fn watch_fin_grabacion(&self, _modelo: &str, _ruta: &Path) {}
+    fn watch_modelo_omitido(&self, _modelo: &str) {}
+    fn watch_proximo_check(&self, _secs: u64) {}
+    fn watch_deteniendo(&self) {}
+}
 
 /// Loads a validated discovery snapshot and opens its interactive view.
 pub(crate) async fn run(
     client: &ChaturbateClient,
+    config: AppConfig,
+    salida: Arc<dyn Output>,
     raw_tag: &str,
     limit: usize,
+    opciones: OpcionesTui,
 ) -> anyhow::Result<()> {
-    let result = discover::find_rooms(client, raw_tag, limit).await?;
-    let rooms = result
-        .rooms
-        .into_iter()
-        .map(|room| TuiRoom::new(room.username, room.viewers, room.show, room.subject))
-        .collect();
-    run_discovery_tui(result.tag, rooms)
+    let result = discover::find_rooms(client, raw_tag, limit, 0).await?;
+    let rooms = result.rooms.into_iter().map(tui_room).collect();
+
+    let (cancel_tx, cancel_rx) = watch::channel(false);
+    let (tx, eventos_rx) = mpsc::channel();
+    let client = client
+        .clone()
+        .with_ffmpeg_path(opciones.ruta_ffmpeg.clone())
+        .with_cancel_receiver(cancel_rx);
+    let mut source = ClientSource {
+        client: Arc::new(client),
+        config: Arc::new(config),
+        limit,
+        opciones,
+        ffmpeg_validado: false,
+        activas: Arc::default(),
+        tareas: JoinSet::new(),
+        eventos: Arc::new(EventosTui {
+            tx,
+            abierta: AtomicBool::new(true),
+            consola: salida,
+        }),
+        eventos_rx,
+        cancel_tx,
+    };
+
+    let resultado =
+        tokio::task::block_in_place(|| run_discovery_tui(result.tag, rooms, &mut source));
+    source.cerrar();
+    resultado
+}
+
+fn tui_room(room: DiscoveredRoom) -> TuiRoom {
+    TuiRoom::new(room.username, room.viewers, room.show, room.subject)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presentation::ConsoleOutput;
+
+    fn eventos() -> (EventosTui, mpsc::Receiver<String>) {
+        let (tx, rx) = mpsc::channel();
+        let eventos = EventosTui {
+            tx,
+            abierta: AtomicBool::new(true),
+            consola: Arc::new(ConsoleOutput::new(false, true)),
+        };
+        (eventos, rx)
+    }
+
+    #[test]
+    fn recording_events_reach_status_line_only_while_open() {
+        let (eventos, rx) = eventos();
+
+        eventos.mostrar_archivo_guardado_resumido("alice", Path::new("/tmp/a.mp4"));
+        eventos.abierta.store(false, Ordering::SeqCst);
+        eventos.mostrar_cancelacion_resumida("bob");
+
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            ["alice: guardado /tmp/a.mp4"]
+        );
+    }
 }
@@ -0,0 +1,235 @@
+//! Temporary watch targets produced by `[[discovery.rules]]`.
+//!
+//! Targets live only in memory: they are refreshed while their rule keeps
+//! matching, expire after `discovery.target_ttl_mins` and are never written
+//! to `watched.toml`.
+
+use crate::domain::value_objects::ModelName;
+use crate::infrastructure::external::DiscoveredRoom;
+use crate::infrastructure::{DiscoveryConfig, DiscoveryRule};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+struct EstadoRegla {
+    regla: DiscoveryRule,
+    /// Rooms listed by the previous successful query; `None` until the first one.
+    vistas: Option<HashSet<String>>,
+}
+
+struct ObjetivoDinamico {
+    expira: Instant,
+}
+
+pub(crate) struct ObjetivosDinamicos {
+    reglas: Vec<EstadoRegla>,
+    ttl: Duration,
+    fijos: HashSet<String>,
+    objetivos: HashMap<String, ObjetivoDinamico>,
+}
+
+impl ObjetivosDinamicos {
+    pub(crate) fn new(config: &DiscoveryConfig, fijos: &[ModelName]) -> Self {
+        Self {
+            reglas: config
+                .rules
+                .iter()
+                .cloned()
+                .map(|regla| EstadoRegla {
+                    regla,
+                    vistas: None,
+                })
+                .collect(),
+            ttl: Duration::from_secs(config.target_ttl_mins.saturating_mul(60)),
+            fijos: fijos.iter().map(|m| m.as_str().to_string()).collect(),
+            objetivos: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn tiene_reglas(&self) -> bool {
+        !self.reglas.is_empty()
+    }
+
+    /// Tags to query this cycle, in rule order.
+    pub(crate) fn tags(&self) -> Vec<String> {
+        self.reglas.iter().map(|r| r.regla.tag.clone()).collect()
+    }
+
+    /// Human readable rule descriptions for startup and event messages.
+    pub(crate) fn descripciones(&self) -> Vec<String> {
+        self.reglas.iter().map(|r| describir(&r.regla)).collect()
+    }
+
+    /// Applies one rule to a fresh room listing and returns newly added
+    /// targets with the rule that matched them.
+    pub(crate) fn evaluar(
+        &mut self,
+        indice: usize,
+        salas: &[DiscoveredRoom],
+        ahora: Instant,
+    ) -> Vec<(String, String)> {
+        let Some(estado) = self.reglas.get_mut(indice) else {
+            return Vec::new();
+        };
+        let actuales: HashSet<String> = salas.iter().map(|s| s.username.clone()).collect();
+        let previas = estado.vistas.replace(actuales);
+        if estado.regla.new_rooms && previas.is_none() {
+            // La primera consulta solo fija la linea base de salas conocidas.
+            return Vec::new();
+        }
+
+        let regla = estado.regla.clone();
+        let expira = ahora.checked_add(self.ttl).unwrap_or(ahora);
+        let mut nuevos = Vec::new();
+        for sala in salas {
+            if sala.viewers < regla.min_viewers
+                || previas
+                    .as_ref()
+                    .is_some_and(|p| regla.new_rooms && p.contains(&sala.username))
+                || self.fijos.contains(&sala.username)
+            {
+                continue;
+            }
+            if ModelName::try_from(sala.username.as_str()).is_err() {
+                continue;
+            }
+            match self.objetivos.get_mut(&sala.username) {
+                Some(objetivo) => objetivo.expira = objetivo.expira.max(expira),
+                None => {
+                    self.objetivos
+                        .insert(sala.username.clone(), ObjetivoDinamico { expira });
+                    nuevos.push((sala.username.clone(), describir(&regla)));
+                }
+            }
+        }
+        nuevos
+    }
+
+    /// Removes expired targets, keeping those still recording until they finish.
+    pub(crate) fn expirar(
+        &mut self,
+        ahora: Instant,
+        grabando: impl Fn(&str) -> bool,
+    ) -> Vec<String> {
+        let expirados: Vec<String> = self
+            .objetivos
+            .iter()
+            .filter(|(nombre, objetivo)| ahora >= objetivo.expira && !grabando(nombre))
+            .map(|(nombre, _)| nombre.clone())
+            .collect();
+        for nombre in &expirados {
+            self.objetivos.remove(nombre);
+        }
+        expirados
+    }
+
+    pub(crate) fn contiene(&self, nombre: &str) -> bool {
+        self.objetivos.contains_key(nombre)
+    }
+}
+
+fn describir(regla: &DiscoveryRule) -> String {
+    let mut descripcion = format!("#{}", regla.tag);
+    if regla.new_rooms {
+        descripcion.push_str(" nuevas");
+    }
+    if regla.min_viewers > 0 {
+        descripcion.push_str(&format!(" >={} espectadores", regla.min_viewers));
+    }
+    descripcion
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sala(username: &str, viewers: u64) -> DiscoveredRoom {
+        DiscoveredRoom {
+            username: username.to_string(),
+            subject: String::new(),
+            viewers,
+            show: "public".to_string(),
+        }
+    }
+
+    fn objetivos(reglas: Vec<DiscoveryRule>, fijos: &[&str]) -> ObjetivosDinamicos {
+        let config = DiscoveryConfig {
+            rules: reglas,
+            target_ttl_mins: 1,
+            ..DiscoveryConfig::default()
+        };
+        let fijos: Vec<ModelName> = fijos
+            .iter()
+            .map(|f| ModelName::try_from(*f).unwrap())
+            .collect();
+        ObjetivosDinamicos::new(&config, &fijos)
+    }
+
+    #[test]
+    fn min_viewers_selects_rooms_and_skips_fixed_models() {
+        let mut objetivos = objetivos(
+            vec![DiscoveryRule {
+                tag: "gaming".to_string(),
+                min_viewers: 100,
+                new_rooms: false,
+            }],
+            &["bob"],
+        );
+
+        let nuevos = objetivos.evaluar(
+            0,
+            &[sala("alice", 150), sala("bob", 900), sala("carol", 10)],
+            Instant::now(),
+        );
+
+        assert_eq!(
+            nuevos,
+            [(
+                "alice".to_string(),
+                "#gaming >=100 espectadores".to_string()
+            )]
+        );
+        assert!(objetivos.contiene("alice"));
+        assert!(!objetivos.contiene("bob"));
+    }
+
+    #[test]
+    fn new_rooms_ignores_first_listing() {
+        let mut objetivos = objetivos(
+            vec![DiscoveryRule {
+                tag: "music".to_string(),
+                min_viewers: 0,
+                new_rooms: true,
+            }],
+            &[],
+        );
+        let ahora = Instant::now();
+
+        assert!(objetivos.evaluar(0, &[sala("alice", 5)], ahora).is_empty());
+        let nuevos = objetivos.evaluar(0, &[sala("alice", 5), sala("dave", 1)], ahora);
+
+        assert_eq!(nuevos.len(), 1);
+        assert_eq!(nuevos[0].0, "dave");
+        assert_eq!(nuevos[0].1, "#music nuevas");
+    }
+
+    #[test]
+    fn expired_targets_wait_for_running_recordings() {
+        let mut objetivos = objetivos(
+            vec![DiscoveryRule {
+                tag: "gaming".to_string(),
+                min_viewers: 0,
+                new_rooms: false,
+            }],
+            &[],
+        );
+        let ahora = Instant::now();
+        objetivos.evaluar(0, &[sala("alice", 1), sala("bob", 1)], ahora);
+        let despues = ahora + Duration::from_secs(61);
+
+        let expirados = objetivos.expirar(despues, |nombre| nombre == "bob");
+
+        assert_eq!(expirados, ["alice"]);
+        assert!(!objetivos.contiene("alice"));
+        assert!(objetivos.contiene("bob"));
+    }
+}
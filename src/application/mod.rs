@@ -1,6 +1,11 @@
 pub mod cli_controller;
 pub(crate) mod commands;
+pub(crate) mod dynamic_targets;
+pub(crate) mod model_tracker;
+pub(crate) mod poll_scheduler;
 pub(crate) mod recording;
+pub(crate) mod retry_policy;
+pub(crate) mod staging;
 pub(crate) mod utils;
 pub(crate) mod watch_service;
 
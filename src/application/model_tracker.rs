@@ -0,0 +1,235 @@
+//! Per-model state machine for `watch`.
+//!
+//! Every watched model has exactly one `EstadoModelo` with the time it was
+//! entered and an optional reason; cooldowns also carry their deadline. Each
+//! change is queued as a `TransicionEstado` so `watch --timeline` can export
+//! the history as JSON lines.
+
+use crate::domain::value_objects::{EstadoModelo, TransicionEstado};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+struct Registro {
+    estado: EstadoModelo,
+    desde: DateTime<Utc>,
+    motivo: Option<String>,
+    /// Deadline of a `Cooldown`.
+    hasta: Option<Instant>,
+}
+
+pub(crate) struct SeguimientoModelos {
+    modelos: HashMap<String, Registro>,
+    pendientes: Vec<TransicionEstado>,
+}
+
+impl SeguimientoModelos {
+    pub(crate) fn new<'a>(nombres: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut seguimiento = Self {
+            modelos: HashMap::new(),
+            pendientes: Vec::new(),
+        };
+        for nombre in nombres {
+            seguimiento.agregar(nombre);
+        }
+        seguimiento
+    }
+
+    /// Starts following `nombre` as offline; known models are left untouched.
+    pub(crate) fn agregar(&mut self, nombre: &str) {
+        self.modelos
+            .entry(nombre.to_string())
+            .or_insert_with(|| Registro {
+                estado: EstadoModelo::Offline,
+                desde: Utc::now(),
+                motivo: None,
+                hasta: None,
+            });
+    }
+
+    pub(crate) fn quitar(&mut self, nombre: &str) {
+        self.modelos.remove(nombre);
+    }
+
+    pub(crate) fn estado(&self, nombre: &str) -> Option<EstadoModelo> {
+        self.modelos.get(nombre).map(|r| r.estado)
+    }
+
+    /// Moves `nombre` to `estado`; repeating the current state and reason is a no-op.
+    pub(crate) fn cambiar(&mut self, nombre: &str, estado: EstadoModelo, motivo: Option<String>) {
+        self.transicionar(nombre, estado, motivo, None);
+    }
+
+    /// Suspends checks of `nombre` until `hasta`.
+    pub(crate) fn bloquear(&mut self, nombre: &str, hasta: Instant, motivo: String) {
+        self.transicionar(nombre, EstadoModelo::Cooldown, Some(motivo), Some(hasta));
+    }
+
+    /// Reapplies a state saved by a previous run, keeping its original start time.
+    pub(crate) fn restaurar(
+        &mut self,
+        nombre: &str,
+        estado: EstadoModelo,
+        desde: DateTime<Utc>,
+        hasta: Option<Instant>,
+    ) {
+        self.transicionar(nombre, estado, Some("estado guardado".to_string()), hasta);
+        if let Some(registro) = self.modelos.get_mut(nombre) {
+            registro.desde = desde;
+        }
+    }
+
+    fn transicionar(
+        &mut self,
+        nombre: &str,
+        estado: EstadoModelo,
+        motivo: Option<String>,
+        hasta: Option<Instant>,
+    ) {
+        let Some(registro) = self.modelos.get_mut(nombre) else {
+            return;
+        };
+        if registro.estado == estado && registro.motivo == motivo {
+            registro.hasta = hasta;
+            return;
+        }
+        let ahora = Utc::now();
+        self.pendientes.push(TransicionEstado {
+            modelo: nombre.to_string(),
+            desde: registro.estado,
+            hacia: estado,
+            instante: ahora,
+            motivo: motivo.clone(),
+        });
+        *registro = Registro {
+            estado,
+            desde: ahora,
+            motivo,
+            hasta,
+        };
+    }
+
+    /// Deadline of an active cooldown.
+    pub(crate) fn bloqueado_hasta(&self, nombre: &str) -> Option<Instant> {
+        self.modelos
+            .get(nombre)
+            .filter(|r| r.estado == EstadoModelo::Cooldown)
+            .and_then(|r| r.hasta)
+    }
+
+    /// Whether a status check of `nombre` is allowed at `ahora`.
+    pub(crate) fn consultable(&self, nombre: &str, ahora: Instant) -> bool {
+        match self.modelos.get(nombre) {
+            None => false,
+            Some(r) if r.estado == EstadoModelo::Grabando || r.estado.es_final() => false,
+            Some(r) if r.estado == EstadoModelo::Cooldown => r.hasta.is_none_or(|h| ahora >= h),
+            Some(_) => true,
+        }
+    }
+
+    pub(crate) fn con_estado(&self, estado: EstadoModelo) -> impl Iterator<Item = &str> + '_ {
+        self.modelos
+            .iter()
+            .filter(move |(_, r)| r.estado == estado)
+            .map(|(nombre, _)| nombre.as_str())
+    }
+
+    pub(crate) fn grabando(&self) -> usize {
+        self.con_estado(EstadoModelo::Grabando).count()
+    }
+
+    /// When `nombre` entered its current state.
+    pub(crate) fn desde(&self, nombre: &str) -> Option<DateTime<Utc>> {
+        self.modelos.get(nombre).map(|r| r.desde)
+    }
+
+    /// Takes the transitions recorded since the previous call.
+    pub(crate) fn drenar_transiciones(&mut self) -> Vec<TransicionEstado> {
+        std::mem::take(&mut self.pendientes)
+    }
+}
+
+/// Appends `transiciones` to `ruta` as JSON lines.
+pub(crate) fn exportar_transiciones(
+    ruta: &Path,
+    transiciones: &[TransicionEstado],
+) -> std::io::Result<()> {
+    if transiciones.is_empty() {
+        return Ok(());
+    }
+    let mut lineas = String::new();
+    for transicion in transiciones {
+        let linea = serde_json::to_string(transicion)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        lineas.push_str(&linea);
+        lineas.push('\n');
+    }
+    let mut archivo = OpenOptions::new().create(true).append(true).open(ruta)?;
+    archivo.write_all(lineas.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn transitions_are_recorded_once_with_reason() {
+        let mut seguimiento = SeguimientoModelos::new(["alice"]);
+
+        seguimiento.cambiar("alice", EstadoModelo::Privado, Some("private".to_string()));
+        seguimiento.cambiar("alice", EstadoModelo::Privado, Some("private".to_string()));
+        seguimiento.cambiar("alice", EstadoModelo::Grabando, None);
+        seguimiento.cambiar("bob", EstadoModelo::Grabando, None);
+
+        let transiciones = seguimiento.drenar_transiciones();
+        assert_eq!(transiciones.len(), 2);
+        assert_eq!(transiciones[0].desde, EstadoModelo::Offline);
+        assert_eq!(transiciones[0].motivo.as_deref(), Some("private"));
+        assert_eq!(transiciones[1].hacia, EstadoModelo::Grabando);
+        assert!(seguimiento.drenar_transiciones().is_empty());
+    }
+
+    #[test]
+    fn consultable_honors_recording_final_states_and_cooldowns() {
+        let ahora = Instant::now();
+        let mut seguimiento = SeguimientoModelos::new(["alice", "bob", "carol", "dave"]);
+        seguimiento.cambiar("alice", EstadoModelo::Grabando, None);
+        seguimiento.cambiar("bob", EstadoModelo::Omitido, None);
+        seguimiento.bloquear("carol", ahora + Duration::from_secs(60), "HTTP 503".into());
+
+        assert!(!seguimiento.consultable("alice", ahora));
+        assert!(!seguimiento.consultable("bob", ahora));
+        assert!(!seguimiento.consultable("carol", ahora));
+        assert!(seguimiento.consultable("carol", ahora + Duration::from_secs(60)));
+        assert!(seguimiento.consultable("dave", ahora));
+        assert!(!seguimiento.consultable("nadie", ahora));
+        assert_eq!(seguimiento.grabando(), 1);
+    }
+
+    #[test]
+    fn exported_timeline_is_one_json_object_per_line() {
+        let ruta = std::env::temp_dir().join(format!(
+            "cbrec_timeline_{}_{}.jsonl",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let mut seguimiento = SeguimientoModelos::new(["alice"]);
+        seguimiento.cambiar("alice", EstadoModelo::Online, None);
+        exportar_transiciones(&ruta, &seguimiento.drenar_transiciones()).unwrap();
+        seguimiento.cambiar("alice", EstadoModelo::Offline, None);
+        exportar_transiciones(&ruta, &seguimiento.drenar_transiciones()).unwrap();
+
+        let contenido = std::fs::read_to_string(&ruta).unwrap();
+        let lineas: Vec<TransicionEstado> = contenido
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lineas.len(), 2);
+        assert_eq!(lineas[1].hacia, EstadoModelo::Offline);
+        let _ = std::fs::remove_file(ruta);
+    }
+}
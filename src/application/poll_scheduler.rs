@@ -0,0 +1,312 @@
+//! Per-model poll deadlines for `watch`.
+//!
+//! Each model keeps its own next-check instant. The initial list is spread
+//! across one interval, every reschedule adds jitter, priority models use
+//! `watch.priority_interval_secs`, models seen online within
+//! `watch.idle_threshold_mins` use `watch.poll_interval_secs`, and the rest
+//! back off individually up to `watch.poll_interval_idle_secs`. The learned
+//! online history halves the interval around usual start times and jumps
+//! straight to the idle interval at hours the model never streams. Deadlines
+//! and intervals are saved in `watch_state.json` so a restart keeps them.
+
+use crate::infrastructure::{ActividadPrevista, WatchConfig};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+struct Entrada {
+    proxima: Instant,
+    intervalo: Duration,
+    /// Last time the model was seen online, or when it started being watched.
+    ultima_actividad: Instant,
+}
+
+pub(crate) struct PlanificadorConsultas {
+    entradas: HashMap<String, Entrada>,
+    base: Duration,
+    idle: Duration,
+    umbral_idle: Duration,
+    intervalo_prioridad: Duration,
+    prioridades: HashSet<String>,
+    jitter_pct: u64,
+    semilla: u64,
+}
+
+impl PlanificadorConsultas {
+    pub(crate) fn new(config: &WatchConfig) -> Self {
+        let semilla = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self {
+            entradas: HashMap::new(),
+            base: Duration::from_secs(config.poll_interval_secs),
+            idle: Duration::from_secs(config.poll_interval_idle_secs),
+            umbral_idle: Duration::from_secs(config.idle_threshold_mins.saturating_mul(60)),
+            intervalo_prioridad: Duration::from_secs(config.priority_interval_secs),
+            prioridades: config.priority_models.iter().cloned().collect(),
+            jitter_pct: config.poll_jitter_pct,
+            semilla: semilla | 1,
+        }
+    }
+
+    /// Spreads the first check of `nombres` evenly over one base interval.
+    pub(crate) fn agregar_escalonados(&mut self, nombres: &[&str], ahora: Instant) {
+        let total = u32::try_from(nombres.len()).unwrap_or(u32::MAX).max(1);
+        for (indice, nombre) in nombres.iter().enumerate() {
+            let indice = u32::try_from(indice).unwrap_or(u32::MAX);
+            let desfase = self.base.saturating_mul(indice) / total;
+            self.insertar(nombre, ahora.checked_add(desfase).unwrap_or(ahora), ahora);
+        }
+    }
+
+    /// Adds a model whose first check is due immediately.
+    pub(crate) fn agregar(&mut self, nombre: &str, ahora: Instant) {
+        if !self.entradas.contains_key(nombre) {
+            self.insertar(nombre, ahora, ahora);
+        }
+    }
+
+    fn insertar(&mut self, nombre: &str, proxima: Instant, ahora: Instant) {
+        self.entradas.insert(
+            nombre.to_string(),
+            Entrada {
+                proxima,
+                intervalo: self.base,
+                ultima_actividad: ahora,
+            },
+        );
+    }
+
+    pub(crate) fn quitar(&mut self, nombre: &str) {
+        self.entradas.remove(nombre);
+    }
+
+    /// Models whose deadline passed, earliest first, at most `max`.
+    pub(crate) fn vencidos(&self, ahora: Instant, max: usize) -> Vec<String> {
+        let mut vencidos: Vec<(&String, Instant)> = self
+            .entradas
+            .iter()
+            .filter(|(_, entrada)| entrada.proxima <= ahora)
+            .map(|(nombre, entrada)| (nombre, entrada.proxima))
+            .collect();
+        vencidos.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        vencidos
+            .into_iter()
+            .take(max)
+            .map(|(nombre, _)| nombre.clone())
+            .collect()
+    }
+
+    /// Reschedules `nombre` after a check; offline models drift toward the idle interval.
+    pub(crate) fn registrar(
+        &mut self,
+        nombre: &str,
+        online: bool,
+        prevision: ActividadPrevista,
+        ahora: Instant,
+    ) {
+        let Some(entrada) = self.entradas.get(nombre) else {
+            return;
+        };
+        let ultima_actividad = if online {
+            ahora
+        } else {
+            entrada.ultima_actividad
+        };
+        let intervalo = if self.prioridades.contains(nombre) {
+            self.intervalo_prioridad
+        } else if online {
+            self.base
+        } else if prevision == ActividadPrevista::Probable {
+            self.base / 2
+        } else if ahora.saturating_duration_since(ultima_actividad) < self.umbral_idle {
+            self.base
+        } else if prevision == ActividadPrevista::Improbable {
+            self.idle.max(self.base)
+        } else {
+            entrada
+                .intervalo
+                .saturating_mul(2)
+                .clamp(self.base, self.idle.max(self.base))
+        };
+        let espera = self.con_jitter(intervalo);
+        if let Some(entrada) = self.entradas.get_mut(nombre) {
+            entrada.intervalo = intervalo;
+            entrada.ultima_actividad = ultima_actividad;
+            entrada.proxima = ahora.checked_add(espera).unwrap_or(ahora);
+        }
+    }
+
+    /// Pushes the next check back, e.g. while a cooldown is active.
+    pub(crate) fn posponer_hasta(&mut self, nombre: &str, instante: Instant) {
+        if let Some(entrada) = self.entradas.get_mut(nombre) {
+            entrada.proxima = entrada.proxima.max(instante);
+        }
+    }
+
+    /// Brings the next check forward, e.g. to re-arm right after a recording.
+    pub(crate) fn adelantar_a(&mut self, nombre: &str, instante: Instant) {
+        if let Some(entrada) = self.entradas.get_mut(nombre) {
+            entrada.proxima = entrada.proxima.min(instante);
+        }
+    }
+
+    /// Next deadline, current interval and last activity of `nombre`.
+    pub(crate) fn instantanea(&self, nombre: &str) -> Option<(Instant, Duration, Instant)> {
+        self.entradas
+            .get(nombre)
+            .map(|e| (e.proxima, e.intervalo, e.ultima_actividad))
+    }
+
+    /// Restores the cadence saved by a previous run; the deadline only moves later.
+    pub(crate) fn restaurar(
+        &mut self,
+        nombre: &str,
+        proxima: Instant,
+        intervalo: Duration,
+        ultima_actividad: Instant,
+    ) {
+        let maximo = self.idle.max(self.base);
+        if let Some(entrada) = self.entradas.get_mut(nombre) {
+            entrada.proxima = entrada.proxima.max(proxima);
+            entrada.intervalo = intervalo.min(maximo);
+            entrada.ultima_actividad = entrada.ultima_actividad.min(ultima_actividad);
+        }
+    }
+
+    pub(crate) fn proxima(&self) -> Option<Instant> {
+        self.entradas.values().map(|e| e.proxima).min()
+    }
+
+    pub(crate) fn intervalo_base(&self) -> Duration {
+        self.base
+    }
+
+    fn con_jitter(&mut self, intervalo: Duration) -> Duration {
+        if self.jitter_pct == 0 || intervalo.is_zero() {
+            return intervalo;
+        }
+        // xorshift64: suficiente para dispersar consultas, sin depender de `rand`.
+        self.semilla ^= self.semilla << 13;
+        self.semilla ^= self.semilla >> 7;
+        self.semilla ^= self.semilla << 17;
+        let rango_ms = intervalo.as_millis() as u64 * self.jitter_pct / 100;
+        let desvio = self.semilla % (rango_ms.saturating_mul(2).saturating_add(1));
+        (intervalo + Duration::from_millis(desvio)).saturating_sub(Duration::from_millis(rango_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> WatchConfig {
+        WatchConfig {
+            poll_interval_secs: 60,
+            poll_interval_idle_secs: 300,
+            idle_threshold_mins: 10,
+            poll_jitter_pct: 0,
+            priority_models: vec!["vip".to_string()],
+            priority_interval_secs: 20,
+            ..WatchConfig::default()
+        }
+    }
+
+    #[test]
+    fn initial_checks_are_spread_over_one_interval() {
+        let mut plan = PlanificadorConsultas::new(&config());
+        let ahora = Instant::now();
+        plan.agregar_escalonados(&["alice", "bob", "carol"], ahora);
+
+        assert_eq!(plan.vencidos(ahora, 10), ["alice"]);
+        assert_eq!(
+            plan.vencidos(ahora + Duration::from_secs(20), 10),
+            ["alice", "bob"]
+        );
+        assert_eq!(plan.vencidos(ahora + Duration::from_secs(40), 2).len(), 2);
+    }
+
+    #[test]
+    fn dead_models_back_off_while_priority_and_recent_ones_do_not() {
+        let mut plan = PlanificadorConsultas::new(&config());
+        let inicio = Instant::now();
+        plan.agregar("alice", inicio);
+        plan.agregar("vip", inicio);
+        let ahora = inicio + Duration::from_secs(11 * 60);
+
+        plan.registrar("alice", false, ActividadPrevista::SinDatos, ahora);
+        plan.registrar("vip", false, ActividadPrevista::SinDatos, ahora);
+        assert_eq!(plan.vencidos(ahora + Duration::from_secs(20), 10), ["vip"]);
+        assert!(plan
+            .vencidos(ahora + Duration::from_secs(119), 10)
+            .iter()
+            .all(|n| n != "alice"));
+
+        plan.registrar("alice", true, ActividadPrevista::SinDatos, ahora);
+        plan.registrar("alice", false, ActividadPrevista::SinDatos, ahora);
+        assert!(plan
+            .vencidos(ahora + Duration::from_secs(60), 10)
+            .contains(&"alice".to_string()));
+    }
+
+    #[test]
+    fn learned_history_tightens_or_relaxes_offline_interval() {
+        let mut plan = PlanificadorConsultas::new(&config());
+        let inicio = Instant::now();
+        plan.agregar("alice", inicio);
+        plan.agregar("bob", inicio);
+        let ahora = inicio + Duration::from_secs(11 * 60);
+
+        plan.registrar("alice", false, ActividadPrevista::Probable, ahora);
+        plan.registrar("bob", false, ActividadPrevista::Improbable, ahora);
+
+        assert_eq!(
+            plan.vencidos(ahora + Duration::from_secs(30), 10),
+            ["alice"]
+        );
+        assert!(plan
+            .vencidos(ahora + Duration::from_secs(299), 10)
+            .iter()
+            .all(|n| n != "bob"));
+        assert_eq!(plan.vencidos(ahora + Duration::from_secs(300), 10).len(), 2);
+    }
+
+    #[test]
+    fn restored_cadence_keeps_backoff_of_previous_run() {
+        let mut plan = PlanificadorConsultas::new(&config());
+        let ahora = Instant::now() + Duration::from_secs(3600);
+        plan.agregar_escalonados(&["alice"], ahora);
+
+        plan.restaurar(
+            "alice",
+            ahora + Duration::from_secs(200),
+            Duration::from_secs(300),
+            ahora - Duration::from_secs(3600),
+        );
+        plan.registrar(
+            "alice",
+            false,
+            ActividadPrevista::SinDatos,
+            ahora + Duration::from_secs(200),
+        );
+
+        assert!(plan
+            .vencidos(ahora + Duration::from_secs(199), 10)
+            .is_empty());
+        let (proxima, intervalo, _) = plan.instantanea("alice").unwrap();
+        assert_eq!(intervalo, Duration::from_secs(300));
+        assert_eq!(proxima, ahora + Duration::from_secs(500));
+    }
+
+    #[test]
+    fn jitter_stays_within_configured_fraction() {
+        let mut plan = PlanificadorConsultas::new(&WatchConfig {
+            poll_jitter_pct: 10,
+            ..config()
+        });
+        for _ in 0..100 {
+            let espera = plan.con_jitter(Duration::from_secs(60));
+            assert!(espera >= Duration::from_secs(54) && espera <= Duration::from_secs(66));
+        }
+    }
+}
@@ -2,16 +2,32 @@
 //!
 //! This module owns output reservation, partial-file recovery, MP4 validation,
 //! and metadata persistence. It does not resolve streams or start FFmpeg.
-
+//! While a recording runs the room is re-checked: when the show turns private
+//! or away the current file is closed, and a new one starts once it is public.
+//! An expired stream URL is resolved again and recording continues likewise.
+//! With `staging_dir` set, files are recorded there and archived once finished.
+//! Each file goes to the first output volume with room; when that disk fills
+//! up mid-recording the file is closed and a new part starts on the next one.
+
+use crate::application::staging::{ruta_en_staging, Archivador};
+use crate::application::utils::espacio_disponible;
 use crate::domain::repositories::StreamRepository;
-use crate::domain::value_objects::{StreamUrl, VideoQuality};
-use crate::infrastructure::InfrastructureError;
+use crate::domain::value_objects::{ModelName, ProgresoGrabacion, StreamUrl, VideoQuality};
+use crate::infrastructure::{
+    AppConfig, ChaturbateClient, DatosNombre, InfrastructureError, RecordingConfig, VolumenSalida,
+};
+use crate::presentation::Output;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
 use serde::Serialize;
 use std::ffi::OsStr;
 use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::fs::OpenOptions;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 
 pub(crate) enum ResultadoGrabacion {
@@ -29,6 +45,192 @@ struct RecordingMetadata<'a> {
     finished_at: DateTime<Utc>,
     file_size_bytes: u64,
     classification: &'static str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    room_transitions: &'a [CambioSala],
+    /// Stream URL refreshes in the session before this file was closed.
+    #[serde(skip_serializing_if = "is_zero")]
+    url_refreshes: u32,
+    /// Seconds before the recording started that came from the playlist window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recovered_secs: Option<u64>,
+    /// Shared by the files of one stream recorded at several qualities.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<&'a str>,
+    /// Room subject when the naming template uses `{subject}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio_codec: Option<&'static str>,
+    /// Average bitrate FFmpeg reported for an audio-only file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio_bitrate_kbps: Option<u64>,
+}
+
+/// Session facts stored in each file's sidecar.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct InfoSesion<'a> {
+    pub(crate) session_id: Option<&'a str>,
+    pub(crate) subject: Option<&'a str>,
+    pub(crate) url_refreshes: u32,
+    pub(crate) recovered_secs: Option<u64>,
+    pub(crate) audio: Option<InfoAudio>,
+}
+
+/// Codec and bitrate of an audio-only recording.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InfoAudio {
+    pub(crate) codec: &'static str,
+    pub(crate) bitrate_kbps: Option<u64>,
+}
+
+fn is_zero(valor: &u32) -> bool {
+    *valor == 0
+}
+
+/// Expired-URL refreshes allowed in a row without recording anything.
+const MAX_RENOVACIONES_SEGUIDAS: u32 = 3;
+
+/// Room status change seen while recording, stored in the sidecar.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CambioSala {
+    at: DateTime<Utc>,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl CambioSala {
+    fn ahora(status: &'static str, detail: Option<String>) -> Self {
+        Self {
+            at: Utc::now(),
+            status,
+            detail,
+        }
+    }
+}
+
+/// Why the room check stopped a running segment.
+enum CorteSala {
+    Restringida(String),
+    Offline,
+}
+
+/// Room checks during a recording, from `[recording]`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct VigilanciaSala {
+    pub(crate) intervalo: Duration,
+    pub(crate) espera_max: Duration,
+}
+
+impl VigilanciaSala {
+    pub(crate) fn desde_config(config: &RecordingConfig) -> Option<Self> {
+        (config.status_check_secs > 0).then(|| Self {
+            intervalo: Duration::from_secs(config.status_check_secs),
+            espera_max: Duration::from_secs(config.private_wait_mins.saturating_mul(60)),
+        })
+    }
+}
+
+/// Recorder whose running download can be stopped without cancelling the others.
+#[async_trait]
+pub(crate) trait DescargaDetenible: StreamRepository<Error = InfrastructureError> {
+    fn con_parada(self: &Arc<Self>, parada: watch::Receiver<bool>) -> Arc<Self>;
+
+    /// Same recorder publishing FFmpeg's progress reports to `progreso`.
+    fn con_progreso(
+        self: &Arc<Self>,
+        _progreso: Arc<watch::Sender<ProgresoGrabacion>>,
+    ) -> Arc<Self> {
+        Arc::clone(self)
+    }
+
+    /// Same recorder stopping once the output disk has less than `bytes` free.
+    fn con_espacio_minimo(self: &Arc<Self>, _bytes: u64) -> Arc<Self> {
+        Arc::clone(self)
+    }
+
+    /// Same recorder starting at the live edge, so a refreshed URL does not
+    /// record again what the previous file already holds.
+    fn desde_borde_vivo(self: &Arc<Self>) -> Arc<Self> {
+        Arc::clone(self)
+    }
+
+    /// Stream already broadcast that the download will recover by starting
+    /// at the oldest listed segment; `None` when it starts at the live edge.
+    async fn inicio_recuperable(
+        &self,
+        _stream_url: &StreamUrl,
+        _quality: VideoQuality,
+    ) -> Option<Duration> {
+        None
+    }
+
+    /// Current room subject for the `{subject}` naming variable.
+    async fn titulo_sala(&self, _modelo: &ModelName) -> Option<String> {
+        None
+    }
+}
+
+#[async_trait]
+impl DescargaDetenible for ChaturbateClient {
+    fn con_parada(self: &Arc<Self>, parada: watch::Receiver<bool>) -> Arc<Self> {
+        Arc::new(self.as_ref().clone().with_stop_receiver(parada))
+    }
+
+    fn con_progreso(
+        self: &Arc<Self>,
+        progreso: Arc<watch::Sender<ProgresoGrabacion>>,
+    ) -> Arc<Self> {
+        Arc::new(self.as_ref().clone().with_progreso(progreso))
+    }
+
+    fn con_espacio_minimo(self: &Arc<Self>, bytes: u64) -> Arc<Self> {
+        Arc::new(self.as_ref().clone().with_min_free_space(bytes))
+    }
+
+    fn desde_borde_vivo(self: &Arc<Self>) -> Arc<Self> {
+        if !self.inicio_ventana() {
+            return Arc::clone(self);
+        }
+        Arc::new(self.as_ref().clone().with_inicio_ventana(false))
+    }
+
+    async fn inicio_recuperable(
+        &self,
+        stream_url: &StreamUrl,
+        quality: VideoQuality,
+    ) -> Option<Duration> {
+        if !self.inicio_ventana() {
+            return None;
+        }
+        let segundos = self.segundos_recuperables(stream_url, quality).await.ok()?;
+        (segundos > 0.0).then(|| Duration::from_secs_f64(segundos))
+    }
+
+    async fn titulo_sala(&self, modelo: &ModelName) -> Option<String> {
+        ChaturbateClient::titulo_sala(self, modelo)
+            .await
+            .ok()
+            .flatten()
+    }
+}
+
+/// Everything needed to record one model until its show ends.
+#[derive(Clone)]
+pub(crate) struct SesionGrabacion<'a> {
+    pub(crate) modelo: &'a ModelName,
+    pub(crate) config: &'a AppConfig,
+    pub(crate) raiz_salida: Option<&'a Path>,
+    pub(crate) quality: VideoQuality,
+    /// Set for siblings recording the same stream at other qualities; their
+    /// files get a quality suffix.
+    pub(crate) id_sesion: Option<&'a str>,
+    /// Room subject for `{subject}`, fetched once per session by `grabar_calidades`.
+    pub(crate) subject: Option<&'a str>,
+    pub(crate) min_file_size: Option<u64>,
+    pub(crate) vigilancia: Option<VigilanciaSala>,
+    pub(crate) cancel_rx: Option<watch::Receiver<bool>>,
+    pub(crate) salida: Arc<dyn Output>,
 }
 
 pub(crate) fn ruta_parcial(ruta: &Path) -> PathBuf {
@@ -64,12 +266,15 @@ pub(crate) async fn preparar_ruta_grabacion(
     ))
 }
 
+/// Records `stream_url` into `ruta`. With `sigue_en_otro_volumen`, a full disk
+/// leaves the partial file for the next volume's part instead of removing it.
 pub(crate) async fn descargar_grabacion<R>(
     client: &R,
     stream_url: &StreamUrl,
     ruta: PathBuf,
     quality: VideoQuality,
     min_file_size: Option<u64>,
+    sigue_en_otro_volumen: bool,
 ) -> Result<ResultadoGrabacion, InfrastructureError>
 where
     R: StreamRepository<Error = InfrastructureError>,
@@ -92,23 +297,50 @@ where
                 return Ok(ResultadoGrabacion::Cancelado);
             }
         }
+        // El parcial queda para que grabar_sesion lo recupere antes de renovar la
+        // URL o de seguir en otro volumen.
+        Err(e) if conserva_parcial(&e, sigue_en_otro_volumen) => return Err(e),
         Err(e) => {
             limpiar_parcial(&parcial).await;
             return Err(e);
         }
     }
 
-    if !archivo_finalizable(&parcial).await {
-        limpiar_parcial(&parcial).await;
+    finalizar_parcial(ruta, &parcial, min_file_size).await
+}
+
+/// Keeps what was recorded before the stream URL failed; `None` when the
+/// partial file is empty or not a finished MP4.
+pub(crate) async fn recuperar_parcial(
+    ruta: &Path,
+    min_file_size: Option<u64>,
+) -> Result<Option<ResultadoGrabacion>, InfrastructureError> {
+    let parcial = ruta_parcial(ruta);
+    if parcial_aprovechable(&parcial).await && archivo_finalizable(&parcial).await {
+        return finalizar_parcial(ruta.to_path_buf(), &parcial, min_file_size)
+            .await
+            .map(Some);
+    }
+    limpiar_parcial(&parcial).await;
+    Ok(None)
+}
+
+async fn finalizar_parcial(
+    ruta: PathBuf,
+    parcial: &Path,
+    min_file_size: Option<u64>,
+) -> Result<ResultadoGrabacion, InfrastructureError> {
+    if !archivo_finalizable(parcial).await {
+        limpiar_parcial(parcial).await;
         return Err(InfrastructureError::RecordingError(
             "archivo parcial no parece un MP4 finalizado".to_string(),
         ));
     }
 
-    let meta = match tokio::fs::metadata(&parcial).await {
+    let meta = match tokio::fs::metadata(parcial).await {
         Ok(meta) => meta,
         Err(e) => {
-            limpiar_parcial(&parcial).await;
+            limpiar_parcial(parcial).await;
             return Err(e.into());
         }
     };
@@ -123,20 +355,488 @@ where
             ruta.file_name()
                 .unwrap_or_else(|| OsStr::new("cbrec.partial")),
         );
-        tokio::fs::rename(&parcial, &destino).await?;
+        tokio::fs::rename(parcial, &destino).await?;
         Ok(ResultadoGrabacion::Pequeno(destino, meta.len()))
     } else {
-        tokio::fs::rename(&parcial, &ruta).await?;
+        tokio::fs::rename(parcial, &ruta).await?;
         Ok(ResultadoGrabacion::Guardado(ruta))
     }
 }
 
+/// Records `stream_url` into one file per public stretch of the show.
+///
+/// `al_iniciar` receives each reserved output path. Returns the finished
+/// segments and, separately, the error that ended the session, so files saved
+/// before a failure are still reported. When the stream URL expires or its
+/// edge fails, the URL is resolved again and recording goes on in a new file.
+/// With `staging_dir`, finished files are archived in the background while
+/// the next part records, and the session waits for them before returning.
+pub(crate) async fn grabar_sesion<R>(
+    client: &Arc<R>,
+    sesion: SesionGrabacion<'_>,
+    stream_url: StreamUrl,
+    al_iniciar: impl Fn(&Path),
+) -> (Vec<ResultadoGrabacion>, Result<(), InfrastructureError>)
+where
+    R: DescargaDetenible,
+{
+    let archivador = sesion.config.staging_dir.as_ref().map(|_| {
+        Archivador::iniciar(
+            sesion.modelo.as_str(),
+            sesion.cancel_rx.clone(),
+            Arc::clone(&sesion.salida),
+        )
+    });
+    let (mut segmentos, resultado) =
+        grabar_partes(client, sesion, stream_url, al_iniciar, archivador.as_ref()).await;
+    if let Some(archivador) = archivador {
+        segmentos.extend(archivador.terminar().await);
+    }
+    (segmentos, resultado)
+}
+
+async fn grabar_partes<R>(
+    client: &Arc<R>,
+    sesion: SesionGrabacion<'_>,
+    stream_url: StreamUrl,
+    al_iniciar: impl Fn(&Path),
+    archivador: Option<&Archivador>,
+) -> (Vec<ResultadoGrabacion>, Result<(), InfrastructureError>)
+where
+    R: DescargaDetenible,
+{
+    let modelo = sesion.modelo.as_str();
+    let mut segmentos = Vec::new();
+    let mut stream_url = stream_url;
+    let mut cambios_iniciales = Vec::new();
+    let mut renovaciones = 0_u32;
+    let mut renovaciones_seguidas = 0_u32;
+    // Tras renovar la URL se sigue desde el borde vivo para no duplicar video.
+    let mut descarga = Arc::clone(client);
+    let id_sesion = match sesion.id_sesion {
+        Some(id) => id.to_string(),
+        None => nuevo_id_sesion(sesion.modelo),
+    };
+    let plantilla = sesion.config.plantilla_nombre();
+    let mut parte = 0_u32;
+    let volumenes = sesion.config.volumenes_salida(sesion.raiz_salida);
+    let mut primer_volumen = 0;
+
+    loop {
+        parte += 1;
+        let started_at = Utc::now();
+        let datos = DatosNombre {
+            modelo,
+            inicio: started_at,
+            quality: sesion.quality,
+            sesion: &id_sesion,
+            parte,
+            subject: sesion.subject,
+            ext: sesion.config.extension_grabacion(sesion.quality),
+        };
+        let volumen = match siguiente_volumen(&volumenes, primer_volumen).await {
+            Ok(volumen) => volumen,
+            Err(e) => return (segmentos, Err(e)),
+        };
+        let min_free_space = volumenes[volumen].min_free_space;
+        let mut ruta_base = sesion
+            .config
+            .ruta_grabacion(&datos, Some(&volumenes[volumen].ruta));
+        if sesion.id_sesion.is_some() && !plantilla.usa_calidad() {
+            ruta_base = ruta_con_calidad(&ruta_base, sesion.quality);
+        }
+        let (ruta_base, archivo) = match &sesion.config.staging_dir {
+            Some(staging) => (ruta_en_staging(staging, &ruta_base), Some(ruta_base)),
+            None => (ruta_base, None),
+        };
+        let ruta = match preparar_ruta_grabacion(ruta_base).await {
+            Ok(ruta) => ruta,
+            Err(e) => return (segmentos, Err(e)),
+        };
+        al_iniciar(&ruta);
+
+        let salida_p = Arc::clone(&sesion.salida);
+        let nombre_p = match sesion.id_sesion {
+            Some(_) => format!("{} {}", modelo, sesion.quality),
+            None => modelo.to_string(),
+        };
+        let (progreso_tx, mut progreso_rx) = watch::channel(ProgresoGrabacion::default());
+        let progreso_final = progreso_rx.clone();
+        let grabadora = match archivo {
+            Some(_) => Arc::clone(&descarga),
+            None => descarga.con_espacio_minimo(min_free_space),
+        }
+        .con_progreso(Arc::new(progreso_tx));
+        let progress_task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                match progreso_rx.has_changed() {
+                    Ok(true) => {
+                        let progreso = *progreso_rx.borrow_and_update();
+                        salida_p.mostrar_progreso_grabacion(&nombre_p, &progreso);
+                    }
+                    Ok(false) => {}
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let recuperado = descarga
+            .inicio_recuperable(&stream_url, sesion.quality)
+            .await;
+        if let Some(recuperado) = recuperado {
+            sesion
+                .salida
+                .grabacion_inicio_recuperado(modelo, recuperado.as_secs());
+        }
+
+        // Con staging_dir el disco lleno es el de staging; otro volumen no ayuda.
+        let sigue_en_otro_volumen = archivo.is_none() && volumen + 1 < volumenes.len();
+        let (resultado, mut corte) = descargar_vigilando(
+            &grabadora,
+            &sesion,
+            &stream_url,
+            ruta.clone(),
+            sigue_en_otro_volumen,
+        )
+        .await;
+        detener_tarea_progreso(progress_task).await;
+
+        let (resultado, interrupcion) = match resultado {
+            Ok(resultado) => (Some(resultado), None),
+            Err(e) if conserva_parcial(&e, sigue_en_otro_volumen) => {
+                match recuperar_parcial(&ruta, sesion.min_file_size).await {
+                    Ok(recuperado) => (recuperado, Some(e)),
+                    Err(err) => return (segmentos, Err(err)),
+                }
+            }
+            Err(e) => return (segmentos, Err(e)),
+        };
+        let (url_caducada, disco_lleno) = match interrupcion {
+            Some(e) if e.requiere_nueva_url() => (Some(e), None),
+            otra => (None, otra),
+        };
+
+        let mut cambios = std::mem::take(&mut cambios_iniciales);
+        match &corte {
+            Some(CorteSala::Restringida(detalle)) => {
+                cambios.push(CambioSala::ahora("restricted", Some(detalle.clone())));
+            }
+            Some(CorteSala::Offline) => cambios.push(CambioSala::ahora("offline", None)),
+            None => {}
+        }
+        if let Some(resultado) = resultado {
+            if let Err(e) = write_recording_metadata(
+                &resultado,
+                modelo,
+                sesion.quality,
+                started_at,
+                &cambios,
+                InfoSesion {
+                    session_id: Some(&id_sesion),
+                    subject: sesion.subject,
+                    url_refreshes: renovaciones,
+                    recovered_secs: recuperado.map(|d| d.as_secs()),
+                    audio: (sesion.quality == VideoQuality::AudioOnly).then(|| InfoAudio {
+                        codec: sesion.config.recording.formato_audio().codec(),
+                        bitrate_kbps: progreso_final
+                            .borrow()
+                            .bitrate_kbps
+                            .map(|kbps| kbps.round() as u64),
+                    }),
+                },
+            )
+            .await
+            {
+                return (segmentos, Err(e));
+            }
+            match (archivador, &archivo) {
+                (Some(archivador), Some(destino)) => {
+                    archivador.enviar(resultado, destino.clone(), min_free_space)
+                }
+                _ => segmentos.push(resultado),
+            }
+            renovaciones_seguidas = 0;
+        }
+        let cancelado = sesion.cancel_rx.as_ref().is_some_and(|rx| *rx.borrow());
+
+        if let Some(error) = disco_lleno {
+            if cancelado {
+                return (segmentos, Err(error));
+            }
+            let Ok(siguiente) = siguiente_volumen(&volumenes, volumen + 1).await else {
+                return (segmentos, Err(error));
+            };
+            sesion
+                .salida
+                .grabacion_volumen_lleno(modelo, &volumenes[siguiente].ruta);
+            primer_volumen = siguiente;
+            descarga = client.desde_borde_vivo();
+            continue;
+        }
+
+        if let (Some(error), None, false) = (url_caducada, &corte, cancelado) {
+            renovaciones_seguidas += 1;
+            if renovaciones_seguidas > MAX_RENOVACIONES_SEGUIDAS {
+                return (segmentos, Err(error));
+            }
+            sesion
+                .salida
+                .grabacion_url_renovada(modelo, &error.to_string());
+            match client.get_stream_url(sesion.modelo).await {
+                Ok(Some(url)) => {
+                    renovaciones += 1;
+                    stream_url = url;
+                    descarga = client.desde_borde_vivo();
+                    continue;
+                }
+                Ok(None) => return (segmentos, Ok(())),
+                Err(InfrastructureError::RoomRestricted(detalle)) => {
+                    corte = Some(CorteSala::Restringida(detalle));
+                }
+                Err(_) => return (segmentos, Err(error)),
+            }
+        }
+
+        let (Some(CorteSala::Restringida(detalle)), Some(vigilancia), false) =
+            (corte, sesion.vigilancia, cancelado)
+        else {
+            return (segmentos, Ok(()));
+        };
+        sesion.salida.grabacion_sala_restringida(modelo, &detalle);
+        let Some(url) = esperar_sala_publica(
+            client.as_ref(),
+            sesion.modelo,
+            vigilancia,
+            sesion.cancel_rx.clone(),
+        )
+        .await
+        else {
+            return (segmentos, Ok(()));
+        };
+        sesion.salida.grabacion_sala_reanudada(modelo);
+        cambios_iniciales.push(CambioSala::ahora("public", None));
+        stream_url = url;
+        descarga = Arc::clone(client);
+    }
+}
+
+/// Records the stream once per quality in parallel.
+///
+/// With several qualities each one runs its own session under a shared
+/// session id, and its files carry the quality as a suffix. Segments of every
+/// quality are returned together with the first error among them.
+pub(crate) async fn grabar_calidades<R>(
+    client: &Arc<R>,
+    sesion: SesionGrabacion<'_>,
+    calidades: &[VideoQuality],
+    stream_url: StreamUrl,
+    al_iniciar: impl Fn(&Path),
+) -> (Vec<ResultadoGrabacion>, Result<(), InfrastructureError>)
+where
+    R: DescargaDetenible,
+{
+    // Una sola consulta por sesion, compartida por partes y calidades hermanas.
+    let subject = if sesion.config.plantilla_nombre().usa_subject() {
+        client.titulo_sala(sesion.modelo).await
+    } else {
+        None
+    };
+    let sesion = SesionGrabacion {
+        subject: subject.as_deref(),
+        ..sesion
+    };
+    if let [quality] = calidades {
+        let sesion = SesionGrabacion {
+            quality: *quality,
+            ..sesion
+        };
+        return grabar_sesion(client, sesion, stream_url, al_iniciar).await;
+    }
+
+    let id_sesion = nuevo_id_sesion(sesion.modelo);
+    let al_iniciar = &al_iniciar;
+    let hermanas = calidades.iter().map(|quality| {
+        let sesion = SesionGrabacion {
+            quality: *quality,
+            id_sesion: Some(id_sesion.as_str()),
+            ..sesion.clone()
+        };
+        grabar_sesion(client, sesion, stream_url.clone(), al_iniciar)
+    });
+
+    let mut segmentos = Vec::new();
+    let mut resultado = Ok(());
+    for (segmentos_calidad, resultado_calidad) in join_all(hermanas).await {
+        segmentos.extend(segmentos_calidad);
+        if resultado.is_ok() {
+            resultado = resultado_calidad;
+        }
+    }
+    (segmentos, resultado)
+}
+
+/// First volume from `desde` on with more free space than it must keep, or
+/// `LowDiskSpace` for the last one probed when every volume is full.
+async fn siguiente_volumen(
+    volumenes: &[VolumenSalida],
+    desde: usize,
+) -> Result<usize, InfrastructureError> {
+    let mut lleno = InfrastructureError::LowDiskSpace {
+        available: 0,
+        required: 0,
+    };
+    for (indice, volumen) in volumenes.iter().enumerate().skip(desde) {
+        if volumen.min_free_space == 0 {
+            return Ok(indice);
+        }
+        let disponible = espacio_disponible(&volumen.ruta).await?;
+        if disponible >= volumen.min_free_space {
+            return Ok(indice);
+        }
+        lleno = InfrastructureError::LowDiskSpace {
+            available: disponible,
+            required: volumen.min_free_space,
+        };
+    }
+    Err(lleno)
+}
+
+/// Errors after which the partial file is kept for `recuperar_parcial`; a
+/// full disk only when the recording continues on another volume.
+fn conserva_parcial(error: &InfrastructureError, sigue_en_otro_volumen: bool) -> bool {
+    error.requiere_nueva_url()
+        || (sigue_en_otro_volumen && matches!(error, InfrastructureError::LowDiskSpace { .. }))
+}
+
+/// Session id shared by every file of one session, e.g. `alice-20260304T050607`.
+pub(crate) fn nuevo_id_sesion(modelo: &ModelName) -> String {
+    format!("{}-{}", modelo, Utc::now().format("%Y%m%dT%H%M%S"))
+}
+
+/// `ruta` with `_<quality>` appended to the file stem.
+pub(crate) fn ruta_con_calidad(ruta: &Path, quality: VideoQuality) -> PathBuf {
+    let stem = ruta
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let nombre = match ruta.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, quality, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, quality),
+    };
+    ruta.with_file_name(nombre)
+}
+
+/// Downloads one segment while re-checking the room every `vigilancia.intervalo`.
+async fn descargar_vigilando<R>(
+    client: &Arc<R>,
+    sesion: &SesionGrabacion<'_>,
+    stream_url: &StreamUrl,
+    ruta: PathBuf,
+    sigue_en_otro_volumen: bool,
+) -> (
+    Result<ResultadoGrabacion, InfrastructureError>,
+    Option<CorteSala>,
+)
+where
+    R: DescargaDetenible,
+{
+    let Some(vigilancia) = sesion.vigilancia else {
+        let resultado = descargar_grabacion(
+            client.as_ref(),
+            stream_url,
+            ruta,
+            sesion.quality,
+            sesion.min_file_size,
+            sigue_en_otro_volumen,
+        )
+        .await;
+        return (resultado, None);
+    };
+
+    let (parada_tx, parada_rx) = watch::channel(false);
+    let detenible = client.con_parada(parada_rx);
+    let descarga = descargar_grabacion(
+        detenible.as_ref(),
+        stream_url,
+        ruta,
+        sesion.quality,
+        sesion.min_file_size,
+        sigue_en_otro_volumen,
+    );
+    tokio::pin!(descarga);
+
+    tokio::select! {
+        resultado = &mut descarga => (resultado, None),
+        corte = vigilar_sala(client.as_ref(), sesion.modelo, vigilancia.intervalo) => {
+            let _ = parada_tx.send(true);
+            (descarga.await, Some(corte))
+        }
+    }
+}
+
+/// Returns once the room is no longer showing a public stream.
+async fn vigilar_sala<R>(client: &R, modelo: &ModelName, intervalo: Duration) -> CorteSala
+where
+    R: StreamRepository<Error = InfrastructureError>,
+{
+    loop {
+        tokio::time::sleep(intervalo).await;
+        match client.get_stream_url(modelo).await {
+            Err(InfrastructureError::RoomRestricted(detalle)) => {
+                return CorteSala::Restringida(detalle)
+            }
+            Ok(None) => return CorteSala::Offline,
+            // Errores de red o de limite: FFmpeg sigue y se reintenta luego.
+            Ok(Some(_)) | Err(_) => {}
+        }
+    }
+}
+
+/// Polls until the show is public again; `None` when the room went offline,
+/// the wait expired or the recording was cancelled.
+async fn esperar_sala_publica<R>(
+    client: &R,
+    modelo: &ModelName,
+    vigilancia: VigilanciaSala,
+    cancel_rx: Option<watch::Receiver<bool>>,
+) -> Option<StreamUrl>
+where
+    R: StreamRepository<Error = InfrastructureError>,
+{
+    let limite = Instant::now() + vigilancia.espera_max;
+    let cancelado = async {
+        match cancel_rx {
+            Some(mut rx) => {
+                let _ = rx.wait_for(|v| *v).await;
+            }
+            None => std::future::pending::<()>().await,
+        }
+    };
+    tokio::pin!(cancelado);
+
+    while Instant::now() < limite {
+        tokio::select! {
+            _ = tokio::time::sleep(vigilancia.intervalo) => {}
+            _ = &mut cancelado => return None,
+        }
+        match client.get_stream_url(modelo).await {
+            Ok(Some(url)) => return Some(url),
+            Ok(None) => return None,
+            Err(_) => {}
+        }
+    }
+    None
+}
+
 /// Writes the versioned JSON sidecar for a completed recording.
 pub(crate) async fn write_recording_metadata(
     result: &ResultadoGrabacion,
     model: &str,
     quality: VideoQuality,
     started_at: DateTime<Utc>,
+    room_transitions: &[CambioSala],
+    sesion: InfoSesion<'_>,
 ) -> Result<(), InfrastructureError> {
     let (path, classification) = match result {
         ResultadoGrabacion::Guardado(path) => (path, "saved"),
@@ -151,6 +851,13 @@ pub(crate) async fn write_recording_metadata(
         finished_at: Utc::now(),
         file_size_bytes: tokio::fs::metadata(path).await?.len(),
         classification,
+        room_transitions,
+        url_refreshes: sesion.url_refreshes,
+        recovered_secs: sesion.recovered_secs,
+        session_id: sesion.session_id,
+        subject: sesion.subject,
+        audio_codec: sesion.audio.map(|audio| audio.codec),
+        audio_bitrate_kbps: sesion.audio.and_then(|audio| audio.bitrate_kbps),
     };
     let mut content = serde_json::to_vec_pretty(&metadata).map_err(|error| {
         InfrastructureError::RecordingError(format!(
@@ -173,7 +880,7 @@ pub(crate) async fn write_recording_metadata(
     Ok(())
 }
 
-fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+pub(crate) fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
     let mut name = path.as_os_str().to_os_string();
     name.push(suffix);
     PathBuf::from(name)
@@ -197,7 +904,7 @@ async fn probar_directorio_escribible(dir: &Path) -> Result<(), InfrastructureEr
     }
 }
 
-async fn ruta_disponible(ruta: &Path) -> Result<bool, InfrastructureError> {
+pub(crate) async fn ruta_disponible(ruta: &Path) -> Result<bool, InfrastructureError> {
     let metadata = path_with_suffix(ruta, ".json");
     if existe(ruta).await?
         || existe(&ruta_parcial(ruta)).await?
@@ -238,7 +945,7 @@ async fn existe(path: &Path) -> Result<bool, InfrastructureError> {
     }
 }
 
-fn ruta_con_sufijo(ruta: &Path, intento: usize) -> PathBuf {
+pub(crate) fn ruta_con_sufijo(ruta: &Path, intento: usize) -> PathBuf {
     if intento == 0 {
         return ruta.to_path_buf();
     }
@@ -279,7 +986,12 @@ async fn archivo_finalizable(path: &Path) -> bool {
 fn requiere_validacion_mp4(path: &Path) -> bool {
     path.extension()
         .and_then(|e| e.to_str())
-        .map(|e| matches!(e.to_ascii_lowercase().as_str(), "mp4" | "m4v" | "mov"))
+        .map(|e| {
+            matches!(
+                e.to_ascii_lowercase().as_str(),
+                "mp4" | "m4v" | "m4a" | "mov"
+            )
+        })
         .unwrap_or(false)
 }
 
@@ -463,6 +1175,151 @@ mod tests {
         }
     }
 
+    type RespuestaSala = Result<Option<StreamUrl>, InfrastructureError>;
+
+    /// Room that answers status checks from a script and records until stopped.
+    #[derive(Default)]
+    struct RepoSala {
+        respuestas: Arc<std::sync::Mutex<Vec<RespuestaSala>>>,
+        parada: Option<watch::Receiver<bool>>,
+    }
+
+    #[async_trait]
+    impl StreamRepository for RepoSala {
+        type Error = InfrastructureError;
+
+        async fn get_stream_url(
+            &self,
+            _model_name: &ModelName,
+        ) -> Result<Option<StreamUrl>, Self::Error> {
+            self.respuestas.lock().unwrap().pop().unwrap_or(Ok(None))
+        }
+
+        async fn download_stream(
+            &self,
+            _stream_url: &StreamUrl,
+            output_path: &Path,
+            _quality: VideoQuality,
+        ) -> Result<(), Self::Error> {
+            tokio::fs::write(output_path, mp4_minimo_valido()).await?;
+            if let Some(mut parada) = self.parada.clone() {
+                let _ = parada.wait_for(|v| *v).await;
+            }
+            Ok(())
+        }
+    }
+
+    impl DescargaDetenible for RepoSala {
+        fn con_parada(self: &Arc<Self>, parada: watch::Receiver<bool>) -> Arc<Self> {
+            Arc::new(Self {
+                respuestas: Arc::clone(&self.respuestas),
+                parada: Some(parada),
+            })
+        }
+    }
+
+    /// Stream whose first URL expires after writing a finished file.
+    #[derive(Default)]
+    struct RepoUrlCaducada {
+        urls: std::sync::Mutex<Vec<String>>,
+        titulos: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl StreamRepository for RepoUrlCaducada {
+        type Error = InfrastructureError;
+
+        async fn get_stream_url(
+            &self,
+            _model_name: &ModelName,
+        ) -> Result<Option<StreamUrl>, Self::Error> {
+            Ok(Some(StreamUrl::try_from(
+                "https://edge2.example.com/stream.m3u8?token=nuevo",
+            )?))
+        }
+
+        async fn download_stream(
+            &self,
+            stream_url: &StreamUrl,
+            output_path: &Path,
+            _quality: VideoQuality,
+        ) -> Result<(), Self::Error> {
+            self.urls.lock().unwrap().push(stream_url.to_string());
+            tokio::fs::write(output_path, mp4_minimo_valido()).await?;
+            if stream_url.as_str().contains("edge1") {
+                return Err(InfrastructureError::PlaylistChanged("403".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl DescargaDetenible for RepoUrlCaducada {
+        fn con_parada(self: &Arc<Self>, _parada: watch::Receiver<bool>) -> Arc<Self> {
+            Arc::clone(self)
+        }
+
+        async fn inicio_recuperable(
+            &self,
+            stream_url: &StreamUrl,
+            _quality: VideoQuality,
+        ) -> Option<Duration> {
+            stream_url
+                .as_str()
+                .contains("edge1")
+                .then(|| Duration::from_secs(42))
+        }
+
+        async fn titulo_sala(&self, _modelo: &ModelName) -> Option<String> {
+            self.titulos
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Some("hola".to_string())
+        }
+    }
+
+    /// Recorder whose first download fills the disk after writing a finished file.
+    #[derive(Default)]
+    struct RepoDiscoLleno {
+        descargas: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl StreamRepository for RepoDiscoLleno {
+        type Error = InfrastructureError;
+
+        async fn get_stream_url(
+            &self,
+            _model_name: &ModelName,
+        ) -> Result<Option<StreamUrl>, Self::Error> {
+            Ok(None)
+        }
+
+        async fn download_stream(
+            &self,
+            _stream_url: &StreamUrl,
+            output_path: &Path,
+            _quality: VideoQuality,
+        ) -> Result<(), Self::Error> {
+            tokio::fs::write(output_path, mp4_minimo_valido()).await?;
+            match self
+                .descargas
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+            {
+                0 => Err(InfrastructureError::LowDiskSpace {
+                    available: 1,
+                    required: 2,
+                }),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl DescargaDetenible for RepoDiscoLleno {
+        fn con_parada(self: &Arc<Self>, _parada: watch::Receiver<bool>) -> Arc<Self> {
+            Arc::clone(self)
+        }
+    }
+
     fn ruta_temporal(nombre: &str) -> PathBuf {
         let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -481,6 +1338,71 @@ mod tests {
         bytes
     }
 
+    /// Temporary output root and stream for a `grabar_sesion` test; the root
+    /// is removed on drop.
+    struct Escenario {
+        raiz: PathBuf,
+        modelo: ModelName,
+        url: StreamUrl,
+    }
+
+    impl Escenario {
+        fn nuevo(nombre: &str) -> Self {
+            Self {
+                raiz: ruta_temporal(nombre).with_extension(""),
+                modelo: ModelName::try_from("alice").unwrap(),
+                url: StreamUrl::try_from("https://edge1.example.com/stream.m3u8?token=viejo")
+                    .unwrap(),
+            }
+        }
+
+        /// Session recording `alice` at best quality into the temporary root.
+        fn sesion<'a>(&'a self, config: &'a AppConfig) -> SesionGrabacion<'a> {
+            SesionGrabacion {
+                modelo: &self.modelo,
+                config,
+                raiz_salida: Some(&self.raiz),
+                quality: VideoQuality::Best,
+                id_sesion: None,
+                subject: None,
+                min_file_size: None,
+                vigilancia: None,
+                cancel_rx: None,
+                salida: Arc::new(crate::presentation::ConsoleOutput::new(false, true)),
+            }
+        }
+    }
+
+    impl Drop for Escenario {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.raiz);
+        }
+    }
+
+    /// Default config with the free-space guard off, so sessions record the
+    /// same whatever space the host has left.
+    fn config_de_prueba() -> AppConfig {
+        AppConfig {
+            min_free_space: 0,
+            ..AppConfig::default()
+        }
+    }
+
+    fn guardados(segmentos: &[ResultadoGrabacion]) -> Vec<PathBuf> {
+        segmentos
+            .iter()
+            .map(|segmento| match segmento {
+                ResultadoGrabacion::Guardado(ruta) => ruta.clone(),
+                _ => panic!("se esperaba un archivo guardado"),
+            })
+            .collect()
+    }
+
+    fn sidecar(ruta: &Path) -> serde_json::Value {
+        let contenido = std::fs::read_to_string(path_with_suffix(ruta, ".json")).unwrap();
+        serde_json::from_str(&contenido).unwrap()
+    }
+
     #[test]
     fn ruta_parcial_conserva_extension_para_ffmpeg() {
         let ruta = PathBuf::from("/tmp/alice.mp4");
@@ -563,7 +1485,7 @@ mod tests {
         let ruta = ruta_temporal("cancelado");
 
         let resultado =
-            descargar_grabacion(&repo, &stream_url, ruta, VideoQuality::Best, Some(1)).await;
+            descargar_grabacion(&repo, &stream_url, ruta, VideoQuality::Best, Some(1), false).await;
 
         assert!(matches!(resultado, Ok(ResultadoGrabacion::Cancelado)));
     }
@@ -581,6 +1503,7 @@ mod tests {
             ruta.clone(),
             VideoQuality::Best,
             Some(1024),
+            false,
         )
         .await;
 
@@ -596,8 +1519,15 @@ mod tests {
         let stream_url = StreamUrl::try_from("https://example.com/stream.m3u8").unwrap();
         let ruta = ruta_temporal("cancelado_mp4_valido");
 
-        let resultado =
-            descargar_grabacion(&repo, &stream_url, ruta.clone(), VideoQuality::Best, None).await;
+        let resultado = descargar_grabacion(
+            &repo,
+            &stream_url,
+            ruta.clone(),
+            VideoQuality::Best,
+            None,
+            false,
+        )
+        .await;
 
         let Ok(ResultadoGrabacion::Guardado(destino)) = resultado else {
             panic!("se esperaba archivo guardado");
@@ -615,7 +1545,7 @@ mod tests {
         let parcial = ruta_parcial(&ruta);
 
         let resultado =
-            descargar_grabacion(&repo, &stream_url, ruta, VideoQuality::Best, Some(1)).await;
+            descargar_grabacion(&repo, &stream_url, ruta, VideoQuality::Best, Some(1), false).await;
 
         assert!(resultado.is_err());
         assert!(!parcial.exists());
@@ -627,8 +1557,15 @@ mod tests {
         let stream_url = StreamUrl::try_from("https://example.com/stream.m3u8").unwrap();
         let ruta = ruta_temporal("clip_corto");
 
-        let resultado =
-            descargar_grabacion(&repo, &stream_url, ruta.clone(), VideoQuality::Best, None).await;
+        let resultado = descargar_grabacion(
+            &repo,
+            &stream_url,
+            ruta.clone(),
+            VideoQuality::Best,
+            None,
+            false,
+        )
+        .await;
 
         let Ok(ResultadoGrabacion::Guardado(destino)) = resultado else {
             panic!("se esperaba archivo guardado");
@@ -647,9 +1584,16 @@ mod tests {
         let result = ResultadoGrabacion::Guardado(path.clone());
         let started_at = "2026-08-02T12:00:00Z".parse().expect("fecha valida");
 
-        write_recording_metadata(&result, "alice", VideoQuality::P720, started_at)
-            .await
-            .expect("escribe metadatos");
+        write_recording_metadata(
+            &result,
+            "alice",
+            VideoQuality::P720,
+            started_at,
+            &[],
+            InfoSesion::default(),
+        )
+        .await
+        .expect("escribe metadatos");
 
         let sidecar = path_with_suffix(&path, ".json");
         let content = tokio::fs::read_to_string(&sidecar)
@@ -664,6 +1608,7 @@ mod tests {
         assert_eq!(metadata["file_size_bytes"], 5);
         assert_eq!(metadata["classification"], "saved");
         assert!(metadata["finished_at"].as_str().is_some());
+        assert!(metadata.get("room_transitions").is_none());
         assert_eq!(
             sidecar.file_name().unwrap().to_string_lossy(),
             format!("{}.json", path.file_name().unwrap().to_string_lossy())
@@ -673,6 +1618,281 @@ mod tests {
         let _ = tokio::fs::remove_file(path).await;
     }
 
+    #[tokio::test]
+    async fn private_show_splits_recording_and_resumes_when_public() {
+        let url = StreamUrl::try_from("https://example.com/stream.m3u8").unwrap();
+        let respuestas = vec![
+            Ok(None),
+            Ok(Some(url.clone())),
+            Err(InfrastructureError::RoomRestricted("private".to_string())),
+        ];
+        let client = Arc::new(RepoSala {
+            respuestas: Arc::new(std::sync::Mutex::new(respuestas)),
+            parada: None,
+        });
+        let raiz = ruta_temporal("sesion").with_extension("");
+        let config = config_de_prueba();
+        let modelo = ModelName::try_from("alice").unwrap();
+        let sesion = SesionGrabacion {
+            modelo: &modelo,
+            config: &config,
+            raiz_salida: Some(&raiz),
+            quality: VideoQuality::Best,
+            id_sesion: None,
+            subject: None,
+            min_file_size: None,
+            vigilancia: Some(VigilanciaSala {
+                intervalo: Duration::from_millis(10),
+                espera_max: Duration::from_secs(5),
+            }),
+            cancel_rx: None,
+            salida: Arc::new(crate::presentation::ConsoleOutput::new(false, true)),
+        };
+
+        let (segmentos, resultado) = grabar_sesion(&client, sesion, url, |_| {}).await;
+
+        resultado.expect("sesion sin errores");
+        let rutas: Vec<PathBuf> = segmentos
+            .into_iter()
+            .map(|segmento| match segmento {
+                ResultadoGrabacion::Guardado(ruta) => ruta,
+                _ => panic!("se esperaba un archivo guardado"),
+            })
+            .collect();
+        assert_eq!(rutas.len(), 2);
+        assert_ne!(rutas[0], rutas[1]);
+        let estados = |ruta: &Path| -> Vec<String> {
+            let contenido = std::fs::read_to_string(path_with_suffix(ruta, ".json")).unwrap();
+            let metadata: serde_json::Value = serde_json::from_str(&contenido).unwrap();
+            metadata["room_transitions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| t["status"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(estados(&rutas[0]), ["restricted"]);
+        assert_eq!(estados(&rutas[1]), ["public", "offline"]);
+        let _ = std::fs::remove_dir_all(raiz);
+    }
+
+    #[tokio::test]
+    async fn several_qualities_record_siblings_sharing_a_session_id() {
+        let escenario = Escenario::nuevo("hermanas");
+        let client = Arc::new(RepoSala::default());
+        let config = config_de_prueba();
+        let calidades = [VideoQuality::Best, VideoQuality::P480];
+
+        let (segmentos, resultado) = grabar_calidades(
+            &client,
+            escenario.sesion(&config),
+            &calidades,
+            escenario.url.clone(),
+            |_| {},
+        )
+        .await;
+
+        resultado.expect("sesion sin errores");
+        let rutas = guardados(&segmentos);
+        let sidecars: Vec<serde_json::Value> = rutas.iter().map(|ruta| sidecar(ruta)).collect();
+        assert_eq!(rutas.len(), 2);
+        assert!(rutas[0]
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .ends_with("_best"));
+        assert!(rutas[1]
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .ends_with("_480p"));
+        assert_eq!(sidecars[1]["requested_quality"], "480p");
+        assert!(sidecars[0]["session_id"].as_str().is_some());
+        assert_eq!(sidecars[0]["session_id"], sidecars[1]["session_id"]);
+    }
+
+    #[tokio::test]
+    async fn staged_recording_is_moved_with_its_sidecar_to_the_archive() {
+        let escenario = Escenario::nuevo("archivo");
+        let client = Arc::new(RepoSala::default());
+        let staging = escenario.raiz.join("staging");
+        let archivo = escenario.raiz.join("nas");
+        let config = AppConfig {
+            staging_dir: Some(staging.clone()),
+            ..config_de_prueba()
+        };
+        let sesion = SesionGrabacion {
+            raiz_salida: Some(&archivo),
+            ..escenario.sesion(&config)
+        };
+        let iniciadas = std::sync::Mutex::new(Vec::new());
+
+        let (segmentos, resultado) =
+            grabar_sesion(&client, sesion, escenario.url.clone(), |ruta| {
+                iniciadas.lock().unwrap().push(ruta.to_path_buf())
+            })
+            .await;
+
+        resultado.expect("sesion sin errores");
+        assert!(iniciadas.lock().unwrap()[0].starts_with(&staging));
+        let [ruta] = guardados(&segmentos).try_into().unwrap();
+        assert!(ruta.starts_with(&archivo));
+        assert!(ruta.exists());
+        assert!(path_with_suffix(&ruta, ".json").exists());
+        assert!(!ruta_parcial(&ruta).exists());
+        assert_eq!(std::fs::read_dir(&staging).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn full_volume_closes_the_file_and_continues_on_the_next_one() {
+        let escenario = Escenario::nuevo("volumenes");
+        let client = Arc::new(RepoDiscoLleno::default());
+        let volumenes = [escenario.raiz.join("ssd"), escenario.raiz.join("nas")];
+        let config = AppConfig {
+            output_volumes: volumenes
+                .iter()
+                .map(|ruta| VolumenSalida {
+                    ruta: ruta.clone(),
+                    min_free_space: 0,
+                })
+                .collect(),
+            ..config_de_prueba()
+        };
+        let sesion = SesionGrabacion {
+            raiz_salida: None,
+            ..escenario.sesion(&config)
+        };
+
+        let (segmentos, resultado) =
+            grabar_sesion(&client, sesion, escenario.url.clone(), |_| {}).await;
+
+        resultado.expect("sesion sin errores");
+        let rutas = guardados(&segmentos);
+        assert_eq!(rutas.len(), 2);
+        assert!(rutas[0].starts_with(&volumenes[0]));
+        assert!(rutas[1].starts_with(&volumenes[1]));
+        assert!(rutas.iter().all(|ruta| ruta.exists()));
+    }
+
+    #[tokio::test]
+    async fn session_fails_with_low_disk_space_when_every_volume_is_full() {
+        let escenario = Escenario::nuevo("volumenes_llenos");
+        let client = Arc::new(RepoDiscoLleno::default());
+        let config = AppConfig {
+            output_volumes: vec![VolumenSalida {
+                ruta: escenario.raiz.clone(),
+                min_free_space: u64::MAX,
+            }],
+            ..config_de_prueba()
+        };
+        let sesion = SesionGrabacion {
+            raiz_salida: None,
+            ..escenario.sesion(&config)
+        };
+
+        let (segmentos, resultado) =
+            grabar_sesion(&client, sesion, escenario.url.clone(), |_| {}).await;
+
+        assert!(matches!(
+            resultado,
+            Err(InfrastructureError::LowDiskSpace {
+                required: u64::MAX,
+                ..
+            })
+        ));
+        assert!(segmentos.is_empty());
+        assert_eq!(
+            client.descargas.load(std::sync::atomic::Ordering::SeqCst),
+            0
+        );
+        assert!(!escenario.raiz.exists());
+    }
+
+    #[tokio::test]
+    async fn full_disk_removes_the_partial_without_another_volume() {
+        let escenario = Escenario::nuevo("sin_volumen");
+        let client = Arc::new(RepoDiscoLleno::default());
+        let config = config_de_prueba();
+        let rutas = std::sync::Mutex::new(Vec::new());
+
+        let (segmentos, resultado) = grabar_sesion(
+            &client,
+            escenario.sesion(&config),
+            escenario.url.clone(),
+            |ruta| rutas.lock().unwrap().push(ruta.to_path_buf()),
+        )
+        .await;
+
+        assert!(matches!(
+            resultado,
+            Err(InfrastructureError::LowDiskSpace { .. })
+        ));
+        assert!(segmentos.is_empty());
+        let rutas = rutas.into_inner().unwrap();
+        assert_eq!(rutas.len(), 1);
+        assert!(!rutas[0].exists());
+        assert!(!ruta_parcial(&rutas[0]).exists());
+    }
+
+    #[tokio::test]
+    async fn expired_url_is_refreshed_without_ending_the_session() {
+        let escenario = Escenario::nuevo("renovada");
+        let client = Arc::new(RepoUrlCaducada::default());
+        let config = config_de_prueba();
+
+        let (segmentos, resultado) = grabar_sesion(
+            &client,
+            escenario.sesion(&config),
+            escenario.url.clone(),
+            |_| {},
+        )
+        .await;
+
+        resultado.expect("la sesion sigue tras renovar la URL");
+        assert_eq!(client.urls.lock().unwrap().len(), 2);
+        assert!(client.urls.lock().unwrap()[1].contains("edge2"));
+        let sidecars: Vec<serde_json::Value> = guardados(&segmentos)
+            .iter()
+            .map(|ruta| sidecar(ruta))
+            .collect();
+        let campo = |nombre: &str| -> Vec<Option<u64>> {
+            sidecars.iter().map(|m| m[nombre].as_u64()).collect()
+        };
+        assert_eq!(campo("url_refreshes"), [None, Some(1)]);
+        assert_eq!(campo("recovered_secs"), [Some(42), None]);
+    }
+
+    #[tokio::test]
+    async fn subject_is_fetched_once_per_session_and_saved_in_every_sidecar() {
+        let escenario = Escenario::nuevo("subject");
+        let client = Arc::new(RepoUrlCaducada::default());
+        let config = AppConfig {
+            naming_template: "{model}_{subject}_{part}.mp4".to_string(),
+            ..config_de_prueba()
+        };
+
+        let (segmentos, resultado) = grabar_calidades(
+            &client,
+            escenario.sesion(&config),
+            &[VideoQuality::Best],
+            escenario.url.clone(),
+            |_| {},
+        )
+        .await;
+
+        resultado.expect("sesion sin errores");
+        let rutas = guardados(&segmentos);
+        assert_eq!(rutas.len(), 2);
+        assert_eq!(client.titulos.load(std::sync::atomic::Ordering::SeqCst), 1);
+        for (parte, ruta) in rutas.iter().enumerate() {
+            assert_eq!(
+                ruta.file_name().unwrap().to_string_lossy(),
+                format!("alice_hola_{}.mp4", parte + 1)
+            );
+            assert_eq!(sidecar(ruta)["subject"], "hola");
+        }
+    }
+
     #[tokio::test]
     async fn mp4_tiene_moov_detecta_mp4_finalizado() {
         let ruta = ruta_temporal("moov");
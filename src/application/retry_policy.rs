@@ -0,0 +1,131 @@
+//! Retry decisions after a recording ends.
+//!
+//! Each failure is classified (`ClaseFallo`) and answered with the matching
+//! `[retry.<class>]` policy. Consecutive failures are counted per model and
+//! class, so `max_attempts` and `backoff` only grow while the same problem
+//! repeats; a recording that ends cleanly resets the model.
+
+use crate::infrastructure::{ClaseFallo, EstrategiaReintento, RetryConfig, RetryPolicy};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Reintento {
+    Inmediato,
+    Esperar(Duration),
+    /// Start no recording for any model during this time.
+    PausarTodo(Duration),
+    /// Stop retrying the model after this many consecutive failures.
+    Abandonar(u64),
+}
+
+#[derive(Default)]
+pub(crate) struct HistorialFallos {
+    fallos: HashMap<(String, ClaseFallo), u64>,
+}
+
+impl HistorialFallos {
+    /// Counts a failure of `modelo` and returns how to react to it.
+    pub(crate) fn registrar(
+        &mut self,
+        modelo: &str,
+        clase: ClaseFallo,
+        config: &RetryConfig,
+    ) -> Reintento {
+        let intentos = self
+            .fallos
+            .entry((modelo.to_string(), clase))
+            .and_modify(|n| *n = n.saturating_add(1))
+            .or_insert(1);
+        decidir(config.politica(clase), *intentos)
+    }
+
+    pub(crate) fn limpiar(&mut self, modelo: &str) {
+        self.fallos.retain(|(nombre, _), _| nombre != modelo);
+    }
+}
+
+/// Wait before checking a model again after a clean end; `None` re-arms at once.
+pub(crate) fn espera_tras_fin_limpio(config: &RetryConfig) -> Option<Duration> {
+    match config.clean_end.estrategia() {
+        EstrategiaReintento::Inmediata => None,
+        _ => Some(Duration::from_secs(config.clean_end.delay_secs)),
+    }
+}
+
+fn decidir(politica: &RetryPolicy, intentos: u64) -> Reintento {
+    if politica.max_attempts > 0 && intentos >= politica.max_attempts {
+        return Reintento::Abandonar(intentos);
+    }
+    let espera = Duration::from_secs(politica.delay_secs);
+    match politica.estrategia() {
+        EstrategiaReintento::Inmediata => Reintento::Inmediato,
+        EstrategiaReintento::Espera => Reintento::Esperar(espera),
+        EstrategiaReintento::Exponencial => {
+            let exponente = u32::try_from(intentos.saturating_sub(1))
+                .unwrap_or(u32::MAX)
+                .min(20);
+            let maximo = Duration::from_secs(politica.max_delay_secs);
+            Reintento::Esperar(espera.saturating_mul(1 << exponente).min(maximo))
+        }
+        EstrategiaReintento::PausarTodo => Reintento::PausarTodo(espera),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_max_then_gives_up() {
+        let config = RetryConfig::default();
+        let mut historial = HistorialFallos::default();
+
+        let esperas: Vec<Reintento> = (0..5)
+            .map(|_| historial.registrar("alice", ClaseFallo::Arranque, &config))
+            .collect();
+
+        assert_eq!(
+            esperas,
+            [
+                Reintento::Esperar(Duration::from_secs(60)),
+                Reintento::Esperar(Duration::from_secs(120)),
+                Reintento::Esperar(Duration::from_secs(240)),
+                Reintento::Esperar(Duration::from_secs(480)),
+                Reintento::Abandonar(5),
+            ]
+        );
+        assert_eq!(
+            historial.registrar("bob", ClaseFallo::Arranque, &config),
+            Reintento::Esperar(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn classes_use_their_own_policy_and_clean_end_resets() {
+        let config = RetryConfig::default();
+        let mut historial = HistorialFallos::default();
+
+        assert_eq!(
+            historial.registrar("alice", ClaseFallo::Estancado, &config),
+            Reintento::Inmediato
+        );
+        assert_eq!(
+            historial.registrar("alice", ClaseFallo::DiscoLleno, &config),
+            Reintento::PausarTodo(Duration::from_secs(600))
+        );
+        assert_eq!(
+            historial.registrar("alice", ClaseFallo::Otro, &config),
+            Reintento::Esperar(Duration::from_secs(300))
+        );
+        for _ in 0..3 {
+            historial.registrar("alice", ClaseFallo::Estancado, &config);
+        }
+        historial.limpiar("alice");
+        assert_eq!(
+            historial.registrar("alice", ClaseFallo::Estancado, &config),
+            Reintento::Inmediato
+        );
+        assert_eq!(espera_tras_fin_limpio(&config), None);
+    }
+}
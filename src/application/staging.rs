@@ -0,0 +1,220 @@
+//! Moves recordings finished in `staging_dir` to their final location.
+//!
+//! FFmpeg writes and finalizes the `.part` file on the staging disk, so a slow
+//! archive never stalls a recording; only the finished file and its sidecar
+//! travel. A move is retried while the archive is unreachable or short on
+//! space, and the files stay in staging when every attempt fails. Moves run
+//! in a background task so the next part starts recording at once.
+
+use crate::application::recording::{
+    path_with_suffix, preparar_ruta_grabacion, ruta_parcial, ResultadoGrabacion,
+};
+use crate::application::utils::{espacio_disponible, esperar_reintento};
+use crate::infrastructure::InfrastructureError;
+use crate::presentation::Output;
+use std::ffi::OsStr;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Attempts to move one recording before leaving it in staging.
+const INTENTOS_ARCHIVO: u32 = 5;
+/// Wait after the first failed move; it grows with every attempt.
+const ESPERA_ARCHIVO: Duration = Duration::from_secs(30);
+
+/// Where a recording whose final path is `destino` is written in `staging`.
+pub(crate) fn ruta_en_staging(staging: &Path, destino: &Path) -> PathBuf {
+    staging.join(
+        destino
+            .file_name()
+            .unwrap_or_else(|| OsStr::new("cbrec.mp4")),
+    )
+}
+
+/// Finished file waiting to leave staging.
+struct Pendiente {
+    resultado: ResultadoGrabacion,
+    destino: PathBuf,
+    min_free_space: u64,
+}
+
+/// Background task archiving one session's finished files in order.
+pub(crate) struct Archivador {
+    tx: mpsc::UnboundedSender<Pendiente>,
+    tarea: JoinHandle<Vec<ResultadoGrabacion>>,
+}
+
+impl Archivador {
+    pub(crate) fn iniciar(
+        modelo: &str,
+        cancel_rx: Option<watch::Receiver<bool>>,
+        salida: Arc<dyn Output>,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Pendiente>();
+        let modelo = modelo.to_string();
+        let tarea = tokio::spawn(async move {
+            let mut archivados = Vec::new();
+            while let Some(pendiente) = rx.recv().await {
+                let archivado = archivar(
+                    pendiente.resultado,
+                    &pendiente.destino,
+                    pendiente.min_free_space,
+                    &modelo,
+                    cancel_rx.clone(),
+                    salida.as_ref(),
+                )
+                .await;
+                archivados.push(archivado);
+            }
+            archivados
+        });
+        Self { tx, tarea }
+    }
+
+    /// Queues `resultado` for `destino` without waiting for the move.
+    pub(crate) fn enviar(
+        &self,
+        resultado: ResultadoGrabacion,
+        destino: PathBuf,
+        min_free_space: u64,
+    ) {
+        let _ = self.tx.send(Pendiente {
+            resultado,
+            destino,
+            min_free_space,
+        });
+    }
+
+    /// Waits for the queued moves and returns the results at their final paths.
+    pub(crate) async fn terminar(self) -> Vec<ResultadoGrabacion> {
+        drop(self.tx);
+        self.tarea.await.unwrap_or_default()
+    }
+}
+
+/// Moves a finished recording and its sidecar from staging to `destino`.
+///
+/// `destino` gets the usual `_001` suffixes when taken, and small files go to
+/// its `small/` directory. The archive must keep `min_free_space` bytes free
+/// after the move. Returns the result at its new path, or unchanged when the
+/// files had to stay in staging.
+async fn archivar(
+    resultado: ResultadoGrabacion,
+    destino: &Path,
+    min_free_space: u64,
+    modelo: &str,
+    cancel_rx: Option<watch::Receiver<bool>>,
+    salida: &dyn Output,
+) -> ResultadoGrabacion {
+    let (origen, pequeno) = match &resultado {
+        ResultadoGrabacion::Guardado(ruta) => (ruta.clone(), None),
+        ResultadoGrabacion::Pequeno(ruta, bytes) => (ruta.clone(), Some(*bytes)),
+        ResultadoGrabacion::Cancelado => return resultado,
+    };
+
+    for intento in 1..=INTENTOS_ARCHIVO {
+        let error = match mover_a_archivo(&origen, destino, pequeno.is_some(), min_free_space).await
+        {
+            Ok(ruta) => {
+                return match pequeno {
+                    Some(bytes) => ResultadoGrabacion::Pequeno(ruta, bytes),
+                    None => ResultadoGrabacion::Guardado(ruta),
+                };
+            }
+            Err(error) => error,
+        };
+        let espera = ESPERA_ARCHIVO * intento;
+        if intento == INTENTOS_ARCHIVO || !esperar_reintento(espera, cancel_rx.clone()).await {
+            salida.grabacion_archivo_en_staging(modelo, &origen, &error.to_string());
+            break;
+        }
+        salida.grabacion_archivo_reintento(modelo, &error.to_string(), espera.as_secs());
+    }
+    resultado
+}
+
+async fn mover_a_archivo(
+    origen: &Path,
+    destino: &Path,
+    pequeno: bool,
+    min_free_space: u64,
+) -> Result<PathBuf, InfrastructureError> {
+    let sidecar = path_with_suffix(origen, ".json");
+    let tamano = tokio::fs::metadata(origen).await?.len()
+        + tokio::fs::metadata(&sidecar)
+            .await
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+
+    let reservada = preparar_ruta_grabacion(destino.to_path_buf()).await?;
+    let reserva = ruta_parcial(&reservada);
+    let resultado = async {
+        let dir = reservada.parent().unwrap_or_else(|| Path::new("."));
+        let requerido = tamano.saturating_add(min_free_space);
+        let disponible = espacio_disponible(dir).await?;
+        if disponible < requerido {
+            return Err(InfrastructureError::LowDiskSpace {
+                available: disponible,
+                required: requerido,
+            });
+        }
+
+        let ruta = if pequeno {
+            let small = dir.join("small");
+            tokio::fs::create_dir_all(&small).await?;
+            small.join(
+                reservada
+                    .file_name()
+                    .unwrap_or_else(|| OsStr::new("cbrec.mp4")),
+            )
+        } else {
+            reservada.clone()
+        };
+        mover_archivo(origen, &ruta, &reserva).await?;
+        if tokio::fs::try_exists(&sidecar).await? {
+            let destino_sidecar = path_with_suffix(&ruta, ".json");
+            let temporal = path_with_suffix(&destino_sidecar, ".part");
+            if let Err(error) = mover_archivo(&sidecar, &destino_sidecar, &temporal).await {
+                // Sin sidecar el archivo no se reconoce; se devuelve a staging.
+                let _ = mover_archivo(&ruta, origen, &ruta_parcial(origen)).await;
+                return Err(error.into());
+            }
+        }
+        Ok(ruta)
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&reserva).await;
+    resultado
+}
+
+/// Renames `origen`, or copies it through `temporal` when `destino` is on
+/// another file system.
+pub(crate) async fn mover_archivo(
+    origen: &Path,
+    destino: &Path,
+    temporal: &Path,
+) -> std::io::Result<()> {
+    match tokio::fs::rename(origen, destino).await {
+        Err(error) if error.kind() == ErrorKind::CrossesDevices => {}
+        resultado => return resultado,
+    }
+
+    let copia = async {
+        tokio::fs::copy(origen, temporal).await?;
+        tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(temporal)
+            .await?
+            .sync_all()
+            .await?;
+        tokio::fs::rename(temporal, destino).await
+    };
+    if let Err(error) = copia.await {
+        let _ = tokio::fs::remove_file(temporal).await;
+        return Err(error);
+    }
+    tokio::fs::remove_file(origen).await
+}
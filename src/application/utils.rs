@@ -1,20 +1,68 @@
 use crate::domain::errors::DomainError;
 use crate::domain::value_objects::ModelName;
-use crate::infrastructure::{expandir_tilde, ChaturbateClient};
+use crate::infrastructure::external::{
+    LimitePeticiones, OpcionesHttp, PoliticaReintentos, PreferenciaIp,
+};
+use crate::infrastructure::{
+    expandir_tilde, normalizar_proxy, ChaturbateClient, FfmpegConfig, NetworkConfig, PerfilFfmpeg,
+};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::watch;
 
 pub(crate) const FFMPEG_ENV: &str = "CBREC_FFMPEG";
 pub(crate) const SESSION_COOKIE_ENV: &str = "CBREC_SESSION_COOKIE";
+/// Standard proxy variables, most specific first; the API and streams use HTTPS.
+pub(crate) const PROXY_ENV: [&str; 6] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+];
 
 pub(crate) struct ParametrosGrabacion {
     pub raiz_salida: Option<PathBuf>,
-    pub quality: crate::domain::value_objects::VideoQuality,
+    /// Qualities recorded side by side; at least one.
+    pub calidades: Vec<crate::domain::value_objects::VideoQuality>,
     pub limite_concurrencia: usize,
     pub min_file_size: Option<u64>,
     pub cancel_rx: tokio::sync::watch::Receiver<bool>,
     pub salida: std::sync::Arc<dyn crate::presentation::Output>,
 }
 
+/// Free bytes on the disk that holds `ruta`, or will once it is created. Runs
+/// on the blocking pool because network mounts can stall the query.
+pub(crate) async fn espacio_disponible(ruta: &Path) -> std::io::Result<u64> {
+    let ruta = ruta.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let existente = ruta.ancestors().find(|r| r.exists()).unwrap_or(&ruta);
+        fs4::available_space(existente)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Waits before the next attempt; `false` when the recording was cancelled.
+pub(crate) async fn esperar_reintento(
+    espera: Duration,
+    cancel_rx: Option<watch::Receiver<bool>>,
+) -> bool {
+    let Some(mut cancel_rx) = cancel_rx else {
+        tokio::time::sleep(espera).await;
+        return true;
+    };
+    if *cancel_rx.borrow() {
+        return false;
+    }
+    tokio::select! {
+        _ = tokio::time::sleep(espera) => true,
+        _ = cancel_rx.wait_for(|v| *v) => false,
+    }
+}
+
 pub(crate) fn resolver_ruta_opcional(ruta: Option<String>) -> Option<PathBuf> {
     ruta.map(|r| expandir_tilde(&r))
 }
@@ -34,6 +82,111 @@ pub(crate) fn resolver_ffmpeg_path(ruta: Option<PathBuf>) -> PathBuf {
     buscar_ffmpeg_empaquetado().unwrap_or_else(|| PathBuf::from("ffmpeg"))
 }
 
+/// Resolves the proxy from `--proxy`, then `[network] proxy`, then the
+/// standard environment variables. `None` means a direct connection.
+pub(crate) fn resolver_proxy(
+    proxy_cli: Option<String>,
+    proxy_config: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    resolver_proxy_desde(proxy_cli, proxy_config, |nombre| std::env::var(nombre).ok())
+}
+
+fn resolver_proxy_desde(
+    proxy_cli: Option<String>,
+    proxy_config: Option<&str>,
+    entorno: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<Option<String>> {
+    if let Some(proxy) = proxy_cli.filter(|p| !p.trim().is_empty()) {
+        return normalizar_proxy(&proxy).map_err(|motivo| anyhow::anyhow!("--proxy: {motivo}"));
+    }
+    if let Some(proxy) = proxy_config {
+        return Ok(normalizar_proxy(proxy).ok().flatten());
+    }
+    for nombre in PROXY_ENV {
+        if let Some(proxy) = entorno(nombre).filter(|p| !p.trim().is_empty()) {
+            return normalizar_proxy(&proxy)
+                .map_err(|motivo| anyhow::anyhow!("{nombre}: {motivo}"));
+        }
+    }
+    Ok(None)
+}
+
+/// Translates the validated `[network]` section into client options.
+pub(crate) fn opciones_http(network: &NetworkConfig) -> OpcionesHttp {
+    OpcionesHttp {
+        timeout: Duration::from_secs(network.timeout_secs),
+        connect_timeout: Duration::from_secs(network.connect_timeout_secs),
+        user_agent: network.user_agent.clone(),
+        headers: network
+            .headers
+            .iter()
+            .map(|(nombre, valor)| (nombre.clone(), valor.clone()))
+            .collect(),
+        ip: match network.ip_version.as_str() {
+            "ipv4" => PreferenciaIp::Ipv4,
+            "ipv6" => PreferenciaIp::Ipv6,
+            _ => PreferenciaIp::Cualquiera,
+        },
+        reintentos: PoliticaReintentos {
+            max_reintentos: u32::try_from(network.retries).unwrap_or(u32::MAX),
+            base: Duration::from_millis(network.retry_base_ms),
+            max_intervalo: Duration::from_secs(network.retry_max_interval_secs),
+            presupuesto: Duration::from_secs(network.retry_budget_secs),
+        },
+        limite: LimitePeticiones {
+            por_minuto: u32::try_from(network.requests_per_minute).unwrap_or(u32::MAX),
+            rafaga: u32::try_from(network.burst).unwrap_or(u32::MAX),
+            umbral_circuito: u32::try_from(network.circuit_threshold).unwrap_or(u32::MAX),
+            pausa_base: Duration::from_secs(network.circuit_pause_secs),
+            pausa_max: Duration::from_secs(network.circuit_max_pause_secs),
+        },
+    }
+}
+
+/// Applies the `[network]` HTTP options and then the global and per-model proxies.
+pub(crate) fn aplicar_red(
+    client: ChaturbateClient,
+    proxy: Option<String>,
+    network: &NetworkConfig,
+) -> anyhow::Result<ChaturbateClient> {
+    let client = client.with_http_options(opciones_http(network))?;
+    let por_modelo: HashMap<String, Option<String>> = network
+        .model_proxies
+        .iter()
+        .map(|(modelo, proxy)| (modelo.clone(), normalizar_proxy(proxy).ok().flatten()))
+        .collect();
+    Ok(client.with_proxy(proxy)?.with_model_proxies(&por_modelo)?)
+}
+
+/// Applies the FFmpeg profile chosen for this run or, without one, the
+/// global and per-model profiles from `[ffmpeg]`.
+pub(crate) fn aplicar_perfil_ffmpeg(
+    client: ChaturbateClient,
+    perfil_cli: Option<&str>,
+    ffmpeg: &FfmpegConfig,
+) -> anyhow::Result<ChaturbateClient> {
+    if let Some(nombre) = perfil_cli {
+        let perfil = ffmpeg.profiles.get(nombre).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Perfil de FFmpeg desconocido: {nombre} (definelo en [ffmpeg.profiles])"
+            )
+        })?;
+        return Ok(client.with_perfil_ffmpeg(perfil.clone()));
+    }
+
+    let perfil_de = |nombre: &String| ffmpeg.profiles.get(nombre).cloned();
+    let client = match ffmpeg.profile.as_ref().and_then(perfil_de) {
+        Some(perfil) => client.with_perfil_ffmpeg(perfil),
+        None => client,
+    };
+    let por_modelo: HashMap<String, PerfilFfmpeg> = ffmpeg
+        .model_profiles
+        .iter()
+        .filter_map(|(modelo, nombre)| Some((modelo.clone(), perfil_de(nombre)?)))
+        .collect();
+    Ok(client.with_model_perfiles_ffmpeg(por_modelo))
+}
+
 pub(crate) fn aplicar_ffmpeg_path(client: ChaturbateClient, ruta: PathBuf) -> ChaturbateClient {
     client.with_ffmpeg_path(ruta)
 }
@@ -227,6 +380,26 @@ mod tests {
         assert!(resultado.is_err());
     }
 
+    #[test]
+    fn resolver_proxy_prefiere_cli_luego_config_luego_entorno() {
+        let entorno =
+            |nombre: &str| (nombre == "http_proxy").then(|| "http://env:3128".to_string());
+
+        assert_eq!(
+            resolver_proxy_desde(Some("socks5://cli:1080".into()), Some("none"), entorno).unwrap(),
+            Some("socks5://cli:1080".to_string())
+        );
+        assert_eq!(
+            resolver_proxy_desde(None, Some("none"), entorno).unwrap(),
+            None
+        );
+        assert_eq!(
+            resolver_proxy_desde(None, None, entorno).unwrap(),
+            Some("http://env:3128".to_string())
+        );
+        assert!(resolver_proxy_desde(Some("proxy:80".into()), None, entorno).is_err());
+    }
+
     #[test]
     fn resolver_ffmpeg_path_prefiere_ruta_explicita() {
         let ruta = resolver_ffmpeg_path(Some(PathBuf::from("/tmp/ffmpeg-custom")));
@@ -1,17 +1,24 @@
+use crate::application::dynamic_targets::ObjetivosDinamicos;
+use crate::application::model_tracker::{exportar_transiciones, SeguimientoModelos};
+use crate::application::poll_scheduler::PlanificadorConsultas;
 use crate::application::recording::{
-    descargar_grabacion, detener_tarea_progreso, preparar_ruta_grabacion, ruta_parcial,
-    write_recording_metadata, ResultadoGrabacion,
+    grabar_calidades, DescargaDetenible, ResultadoGrabacion, SesionGrabacion, VigilanciaSala,
 };
+use crate::application::retry_policy::{espera_tras_fin_limpio, HistorialFallos, Reintento};
 use crate::domain::errors::DomainError;
 use crate::domain::repositories::StreamRepository;
 use crate::domain::value_objects::{EstadoModelo, ModelName, StreamUrl, VideoQuality};
-use crate::infrastructure::{AppConfig, ChaturbateClient, InfrastructureError, WatchConfig};
+use crate::infrastructure::external::{DiscoveredRoom, EstadoCircuito};
+use crate::infrastructure::{
+    ActividadPrevista, AppConfig, ChaturbateClient, ClaseFallo, InfrastructureError,
+    ModelRuntimeState, OnlineHistory, WatchConfig, WatchState,
+};
 use crate::presentation::Output;
 use async_trait::async_trait;
-use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::io::AsyncBufReadExt;
 use tokio::sync::watch;
 use tokio::task::JoinSet;
@@ -22,12 +29,19 @@ pub(crate) struct WatchParams<R = ChaturbateClient> {
     pub modelos: Vec<ModelName>,
     pub ask: bool,
     pub raiz_salida: Option<PathBuf>,
-    pub quality: VideoQuality,
+    /// Qualities recorded side by side; together they take one slot.
+    pub calidades: Vec<VideoQuality>,
     pub limite_concurrencia: usize,
     pub min_file_size: Option<u64>,
     pub cancel_rx: watch::Receiver<bool>,
     pub salida: Arc<dyn Output>,
     pub prompter: Arc<dyn WatchPrompter>,
+    /// Learned online hours; `None` disables prediction and persistence.
+    pub historial: Option<OnlineHistory>,
+    /// Runtime state of the previous run; `None` disables persistence.
+    pub estado_guardado: Option<WatchState>,
+    /// JSON-lines file that receives every model state transition.
+    pub linea_temporal: Option<PathBuf>,
 }
 
 #[async_trait]
@@ -37,6 +51,55 @@ pub(crate) trait WatchPrompter: Send + Sync {
 
 pub(crate) struct ConsoleWatchPrompter;
 
+/// Lists public rooms by tag for `[[discovery.rules]]` evaluation.
+#[async_trait]
+pub(crate) trait RoomDiscovery: Send + Sync {
+    async fn rooms_by_tag(
+        &self,
+        tag: &str,
+        limit: usize,
+    ) -> Result<Vec<DiscoveredRoom>, InfrastructureError>;
+}
+
+#[async_trait]
+impl RoomDiscovery for ChaturbateClient {
+    async fn rooms_by_tag(
+        &self,
+        tag: &str,
+        limit: usize,
+    ) -> Result<Vec<DiscoveredRoom>, InfrastructureError> {
+        self.discover_rooms_by_tag(tag, limit, 0).await
+    }
+}
+
+const SALAS_POR_REGLA: usize = 50;
+
+/// Resolves the client one model should use, e.g. to honor its proxy override.
+pub(crate) trait ClientePorModelo {
+    fn para_modelo(self: &Arc<Self>, modelo: &str) -> Arc<Self>;
+}
+
+impl ClientePorModelo for ChaturbateClient {
+    fn para_modelo(self: &Arc<Self>, modelo: &str) -> Arc<Self> {
+        if self.has_model_proxy(modelo) {
+            Arc::new(self.for_model(modelo))
+        } else {
+            Arc::clone(self)
+        }
+    }
+}
+
+/// Exposes the shared rate-limit circuit so watch can pause all polling.
+pub(crate) trait PausaGlobal {
+    fn estado_circuito(&self) -> EstadoCircuito;
+}
+
+impl PausaGlobal for ChaturbateClient {
+    fn estado_circuito(&self) -> EstadoCircuito {
+        ChaturbateClient::estado_circuito(self)
+    }
+}
+
 #[async_trait]
 impl WatchPrompter for ConsoleWatchPrompter {
     async fn confirmar_grabacion(&self, modelo: &str, cfg: &WatchConfig) -> bool {
@@ -50,7 +113,12 @@ pub(crate) async fn ejecutar_watch(params: WatchParams) -> anyhow::Result<()> {
 
 async fn ejecutar_watch_con_repo<R>(params: WatchParams<R>) -> anyhow::Result<()>
 where
-    R: StreamRepository<Error = InfrastructureError> + 'static,
+    R: StreamRepository<Error = InfrastructureError>
+        + DescargaDetenible
+        + RoomDiscovery
+        + ClientePorModelo
+        + PausaGlobal
+        + 'static,
 {
     let WatchParams {
         client,
@@ -58,45 +126,84 @@ where
         modelos,
         ask,
         raiz_salida,
-        quality,
+        calidades,
         limite_concurrencia,
         min_file_size,
         cancel_rx,
         salida,
         prompter,
+        mut historial,
+        mut estado_guardado,
+        linea_temporal,
     } = params;
 
     let nombres: Vec<&str> = modelos.iter().map(|m| m.as_str()).collect();
     salida.watch_inicio(&nombres);
 
-    let mut estados: HashMap<String, EstadoModelo> = modelos
-        .iter()
-        .map(|m| (m.as_str().to_string(), EstadoModelo::Offline))
-        .collect();
+    let mut dinamicos = ObjetivosDinamicos::new(&config.discovery, &modelos);
+    if dinamicos.tiene_reglas() {
+        salida.watch_reglas_descubrimiento(&dinamicos.descripciones());
+    }
 
-    let mut omitidos: HashSet<String> = HashSet::new();
-    let mut invalidos: HashSet<String> = HashSet::new();
-    let mut bloqueados_hasta: HashMap<String, Instant> = HashMap::new();
-    let mut grabaciones: JoinSet<(String, Option<PathBuf>, bool)> = JoinSet::new();
+    let mut seguimiento = SeguimientoModelos::new(nombres.iter().copied());
+    let mut grabaciones: JoinSet<(String, Option<PathBuf>, Option<ClaseFallo>)> = JoinSet::new();
+    let mut fallos = HistorialFallos::default();
+    let mut grabaciones_pausadas_hasta: Option<Instant> = None;
 
-    let mut ultima_actividad = Instant::now()
-        .checked_sub(Duration::from_secs(
-            config.watch.idle_threshold_mins * 60 + 1,
-        ))
-        .unwrap_or_else(Instant::now);
+    let mut circuito_previo = EstadoCircuito::Cerrado;
+    let mut plan = PlanificadorConsultas::new(&config.watch);
+    plan.agregar_escalonados(&nombres, Instant::now());
+    if let Some(estado) = &estado_guardado {
+        restaurar_estado(estado, &mut plan, &mut seguimiento);
+    }
+    let mut proximo_descubrimiento = Instant::now();
 
     loop {
-        while let Some(Ok((modelo, ruta_final, hubo_error))) = grabaciones.try_join_next() {
+        while let Some(Ok((modelo, ruta_final, fallo))) = grabaciones.try_join_next() {
             if let Some(ruta) = ruta_final {
                 salida.watch_fin_grabacion(&modelo, &ruta);
             }
-            if hubo_error {
-                bloqueados_hasta.insert(
-                    modelo.clone(),
-                    instante_tras(Duration::from_secs(config.watch.cooldown_tras_fallo_secs)),
-                );
+            registrar_online(historial.as_mut(), &modelo, salida.as_ref());
+            plan.registrar(&modelo, true, ActividadPrevista::SinDatos, Instant::now());
+            let Some(clase) = fallo else {
+                fallos.limpiar(&modelo);
+                seguimiento.cambiar(&modelo, EstadoModelo::Offline, None);
+                let espera = espera_tras_fin_limpio(&config.retry).unwrap_or_default();
+                plan.adelantar_a(&modelo, instante_tras(espera));
+                continue;
+            };
+            let motivo = format!("fallo de grabacion ({clase})");
+            match fallos.registrar(&modelo, clase, &config.retry) {
+                Reintento::Inmediato => {
+                    seguimiento.cambiar(&modelo, EstadoModelo::Offline, Some(motivo));
+                    plan.adelantar_a(&modelo, Instant::now());
+                }
+                Reintento::Esperar(espera) => {
+                    let hasta = instante_tras(espera);
+                    seguimiento.bloquear(&modelo, hasta, motivo);
+                    plan.posponer_hasta(&modelo, hasta);
+                }
+                Reintento::PausarTodo(espera) => {
+                    let hasta = instante_tras(espera);
+                    salida.watch_grabaciones_pausadas(
+                        &modelo,
+                        &clase.to_string(),
+                        espera.as_secs(),
+                    );
+                    grabaciones_pausadas_hasta = Some(hasta);
+                    seguimiento.bloquear(&modelo, hasta, motivo);
+                    plan.posponer_hasta(&modelo, hasta);
+                }
+                Reintento::Abandonar(intentos) => {
+                    salida.watch_reintentos_agotados(&modelo, &clase.to_string(), intentos);
+                    plan.quitar(&modelo);
+                    seguimiento.cambiar(
+                        &modelo,
+                        EstadoModelo::Omitido,
+                        Some(format!("{intentos} fallos seguidos ({clase})")),
+                    );
+                }
             }
-            estados.insert(modelo, EstadoModelo::Offline);
         }
 
         if *cancel_rx.borrow() {
@@ -105,145 +212,282 @@ where
             break;
         }
 
-        let mut slots_disponibles = calcular_slots_disponibles(&estados, limite_concurrencia);
+        // Un circuito abierto por 429/bloqueos pausa todas las consultas;
+        // al vencer, el gobernador deja pasar una sola sonda.
+        let circuito = client.estado_circuito();
+        match circuito {
+            EstadoCircuito::Abierto { restante } => {
+                if !matches!(circuito_previo, EstadoCircuito::Abierto { .. }) {
+                    salida.watch_pausa_rate_limit(restante.as_secs().max(1));
+                    pausar_modelos(&mut seguimiento);
+                    volcar_transiciones(
+                        &mut seguimiento,
+                        linea_temporal.as_deref(),
+                        salida.as_ref(),
+                    );
+                }
+                circuito_previo = circuito;
+                tokio::select! {
+                    _ = tokio::time::sleep(restante) => {}
+                    _ = esperar_cancelacion(cancel_rx.clone()) => {}
+                }
+                continue;
+            }
+            EstadoCircuito::Cerrado if circuito_previo != EstadoCircuito::Cerrado => {
+                salida.watch_pausa_finalizada();
+                let pausados: Vec<String> = seguimiento
+                    .con_estado(EstadoModelo::Pausado)
+                    .map(str::to_string)
+                    .collect();
+                for modelo in pausados {
+                    seguimiento.cambiar(&modelo, EstadoModelo::Offline, None);
+                }
+            }
+            _ => {}
+        }
+        circuito_previo = circuito;
+
+        if dinamicos.tiene_reglas() {
+            if Instant::now() >= proximo_descubrimiento {
+                proximo_descubrimiento = instante_tras(plan.intervalo_base());
+                for (indice, tag) in dinamicos.tags().into_iter().enumerate() {
+                    match client.rooms_by_tag(&tag, SALAS_POR_REGLA).await {
+                        Ok(salas) => {
+                            for (modelo, regla) in dinamicos.evaluar(indice, &salas, Instant::now())
+                            {
+                                salida.watch_objetivo_dinamico(&modelo, &regla);
+                                plan.agregar(&modelo, Instant::now());
+                                seguimiento.agregar(&modelo);
+                            }
+                        }
+                        Err(InfrastructureError::CircuitOpen(_)) => {}
+                        Err(e) => salida
+                            .advertir_error_consulta_estado(&format!("#{tag}"), &e.to_string()),
+                    }
+                }
+            }
+            let expirados = dinamicos.expirar(Instant::now(), |nombre| {
+                seguimiento.estado(nombre) == Some(EstadoModelo::Grabando)
+            });
+            for modelo in expirados {
+                salida.watch_objetivo_expirado(&modelo);
+                plan.quitar(&modelo);
+                seguimiento.quitar(&modelo);
+            }
+        }
+
+        let mut slots_disponibles = calcular_slots_disponibles(&seguimiento, limite_concurrencia);
+        let mut slots_dinamicos = config.discovery.max_simultaneous.saturating_sub(
+            seguimiento
+                .con_estado(EstadoModelo::Grabando)
+                .filter(|nombre| dinamicos.contiene(nombre))
+                .count(),
+        );
         let cooldown = Duration::from_secs(config.watch.cooldown_tras_fallo_secs);
 
-        // Checks de estado en paralelo
+        // Solo se consultan los modelos cuyo plazo vencio, con concurrencia acotada.
         let mut checks: JoinSet<(String, Result<Option<StreamUrl>, InfrastructureError>)> =
             JoinSet::new();
 
-        for modelo in &modelos {
-            let nombre = modelo.as_str().to_string();
-            if !debe_consultar_modelo(&nombre, &estados, &omitidos, &invalidos, &bloqueados_hasta) {
+        for nombre in plan.vencidos(Instant::now(), config.watch.max_concurrent_checks) {
+            if seguimiento
+                .estado(&nombre)
+                .is_none_or(EstadoModelo::es_final)
+            {
+                plan.quitar(&nombre);
+                continue;
+            }
+            if !seguimiento.consultable(&nombre, Instant::now()) {
+                let hasta = seguimiento
+                    .bloqueado_hasta(&nombre)
+                    .unwrap_or_else(|| instante_tras(plan.intervalo_base()));
+                plan.posponer_hasta(&nombre, hasta);
                 continue;
             }
-            let client_c = Arc::clone(&client);
-            let m = modelo.clone();
-            checks
-                .spawn(async move { (m.as_str().to_string(), client_c.get_stream_url(&m).await) });
+            let Ok(m) = ModelName::try_from(nombre.as_str()) else {
+                plan.quitar(&nombre);
+                continue;
+            };
+            let client_c = client.para_modelo(&nombre);
+            checks.spawn(async move { (nombre, client_c.get_stream_url(&m).await) });
         }
+        let hubo_consultas = !checks.is_empty();
 
         let mut online: Vec<(String, StreamUrl)> = Vec::new();
         while let Some(Ok((nombre, resultado))) = checks.join_next().await {
             match resultado {
                 Ok(Some(url)) => {
-                    ultima_actividad = Instant::now();
+                    registrar_online(historial.as_mut(), &nombre, salida.as_ref());
+                    plan.registrar(&nombre, true, ActividadPrevista::SinDatos, Instant::now());
                     salida.watch_tick_online(&nombre);
+                    seguimiento.cambiar(&nombre, EstadoModelo::Online, None);
                     online.push((nombre, url));
                 }
-                Ok(None) => salida.watch_tick_offline(&nombre),
+                Ok(None) => {
+                    let prevision = historial.as_ref().map_or(ActividadPrevista::SinDatos, |h| {
+                        h.prevision(&nombre, chrono::Local::now())
+                    });
+                    plan.registrar(&nombre, false, prevision, Instant::now());
+                    salida.watch_tick_offline(&nombre);
+                    seguimiento.cambiar(&nombre, EstadoModelo::Offline, None);
+                }
+                Err(InfrastructureError::RoomRestricted(detalle)) => {
+                    // La sala sigue activa: se consulta al ritmo de un modelo online.
+                    registrar_online(historial.as_mut(), &nombre, salida.as_ref());
+                    plan.registrar(&nombre, true, ActividadPrevista::SinDatos, Instant::now());
+                    salida.watch_tick_privado(&nombre, &detalle);
+                    seguimiento.cambiar(&nombre, EstadoModelo::Privado, Some(detalle));
+                }
                 Err(InfrastructureError::Domain(DomainError::ModelNotFound(_))) => {
                     salida.error_fallo_grabacion(
                         &nombre,
                         "modelo no encontrado, eliminado de monitoreo",
                     );
-                    invalidos.insert(nombre);
+                    plan.quitar(&nombre);
+                    seguimiento.cambiar(
+                        &nombre,
+                        EstadoModelo::Invalido,
+                        Some("modelo no encontrado".to_string()),
+                    );
+                }
+                Err(InfrastructureError::CircuitOpen(secs)) => {
+                    plan.posponer_hasta(&nombre, instante_tras(Duration::from_secs(secs)));
+                    seguimiento.cambiar(
+                        &nombre,
+                        EstadoModelo::Pausado,
+                        Some(MOTIVO_PAUSA.to_string()),
+                    );
                 }
                 Err(e) => {
                     salida.advertir_error_consulta_estado(&nombre, &e.to_string());
-                    bloqueados_hasta.insert(
-                        nombre,
-                        instante_tras(cooldown_para_error_consulta(&e, cooldown)),
-                    );
+                    let hasta = instante_tras(cooldown_para_error_consulta(&e, cooldown));
+                    plan.posponer_hasta(&nombre, hasta);
+                    seguimiento.bloquear(&nombre, hasta, e.to_string());
                 }
             }
         }
 
+        // Tras un fallo por disco lleno no se inicia ninguna grabacion hasta que venza la pausa.
+        if grabaciones_pausadas_hasta.is_some_and(|hasta| Instant::now() < hasta) {
+            online.clear();
+        } else {
+            grabaciones_pausadas_hasta = None;
+        }
+
         // Decisiones de grabación (secuencial para manejar stdin/slots)
+        // Los modelos fijos tienen prioridad sobre los objetivos temporales.
+        online.sort_by_key(|(nombre, _)| dinamicos.contiene(nombre));
         for (nombre, stream_url) in online {
             if *cancel_rx.borrow() || slots_disponibles == 0 {
                 break;
             }
+            let es_dinamico = dinamicos.contiene(&nombre);
+            if es_dinamico && slots_dinamicos == 0 {
+                continue;
+            }
 
             if ask && !prompter.confirmar_grabacion(&nombre, &config.watch).await {
                 salida.watch_modelo_omitido(&nombre);
-                omitidos.insert(nombre);
+                plan.quitar(&nombre);
+                seguimiento.cambiar(
+                    &nombre,
+                    EstadoModelo::Omitido,
+                    Some("rechazado con --ask".to_string()),
+                );
                 continue;
             }
 
             salida.watch_inicio_grabacion(&nombre);
-            estados.insert(nombre.clone(), EstadoModelo::Grabando);
+            seguimiento.cambiar(&nombre, EstadoModelo::Grabando, None);
             slots_disponibles = slots_disponibles.saturating_sub(1);
+            if es_dinamico {
+                slots_dinamicos = slots_dinamicos.saturating_sub(1);
+            }
 
-            let client_clone = Arc::clone(&client);
+            let client_clone = client.para_modelo(&nombre);
             let config_clone = Arc::clone(&config);
             let salida_clone = Arc::clone(&salida);
             let raiz_clone = raiz_salida.clone();
             let cancel_clone = cancel_rx.clone();
             let nombre_clone = nombre.clone();
+            let calidades_clone = calidades.clone();
 
             grabaciones.spawn(async move {
                 if *cancel_clone.borrow() {
-                    return (nombre_clone, None, false);
+                    return (nombre_clone, None, None);
                 }
 
-                let ruta_base =
-                    config_clone.get_output_path(nombre_clone.as_str(), raiz_clone.as_deref());
-                let ruta = match preparar_ruta_grabacion(ruta_base).await {
-                    Ok(ruta) => ruta,
-                    Err(e) => {
-                        salida_clone.error_fallo_grabacion(&nombre_clone, &e.to_string());
-                        return (nombre_clone, None, true);
-                    }
+                let Ok(modelo) = ModelName::try_from(nombre_clone.as_str()) else {
+                    return (nombre_clone, None, None);
                 };
-
-                let salida_p = Arc::clone(&salida_clone);
-                let nombre_p = nombre_clone.clone();
-                let parcial_p = ruta_parcial(&ruta);
-                let progress_task = tokio::spawn(async move {
-                    loop {
-                        tokio::time::sleep(Duration::from_secs(5)).await;
-                        if let Ok(meta) = tokio::fs::metadata(&parcial_p).await {
-                            salida_p.mostrar_progreso_grabacion(&nombre_p, meta.len());
-                        }
-                    }
-                });
-
-                let started_at = chrono::Utc::now();
-                let result = descargar_grabacion(
-                    client_clone.as_ref(),
-                    &stream_url,
-                    ruta,
+                let Some(&quality) = calidades_clone.first() else {
+                    return (nombre_clone, None, None);
+                };
+                let sesion = SesionGrabacion {
+                    modelo: &modelo,
+                    config: config_clone.as_ref(),
+                    raiz_salida: raiz_clone.as_deref(),
                     quality,
+                    id_sesion: None,
+                    subject: None,
                     min_file_size,
-                )
-                .await;
-                detener_tarea_progreso(progress_task).await;
-
-                if let Ok(recording) = &result {
-                    if let Err(e) =
-                        write_recording_metadata(recording, &nombre_clone, quality, started_at)
-                            .await
-                    {
-                        salida_clone.error_fallo_grabacion(&nombre_clone, &e.to_string());
-                        return (nombre_clone, None, true);
-                    }
+                    vigilancia: VigilanciaSala::desde_config(&config_clone.recording),
+                    cancel_rx: Some(cancel_clone),
+                    salida: Arc::clone(&salida_clone),
+                };
+                let (segmentos, resultado) =
+                    grabar_calidades(&client_clone, sesion, &calidades_clone, stream_url, |_| {})
+                        .await;
+
+                let mut rutas: Vec<PathBuf> = segmentos
+                    .into_iter()
+                    .filter_map(|segmento| match segmento {
+                        ResultadoGrabacion::Guardado(p) | ResultadoGrabacion::Pequeno(p, _) => {
+                            Some(p)
+                        }
+                        ResultadoGrabacion::Cancelado => None,
+                    })
+                    .collect();
+                // Los cortes por show privado se informan al momento; el ultimo
+                // archivo lo informa el bucle principal.
+                let ultima = rutas.pop();
+                for ruta in &rutas {
+                    salida_clone.watch_fin_grabacion(&nombre_clone, ruta);
                 }
-
-                match result {
-                    Ok(ResultadoGrabacion::Guardado(p)) => (nombre_clone, Some(p), false),
-                    Ok(ResultadoGrabacion::Pequeno(p, _)) => (nombre_clone, Some(p), false),
-                    Ok(ResultadoGrabacion::Cancelado) => (nombre_clone, None, false),
+                match resultado {
+                    Ok(()) => (nombre_clone, ultima, None),
                     Err(e) => {
+                        if let Some(ruta) = &ultima {
+                            salida_clone.watch_fin_grabacion(&nombre_clone, ruta);
+                        }
                         salida_clone.error_fallo_grabacion(&nombre_clone, &e.to_string());
-                        (nombre_clone, None, true)
+                        (nombre_clone, None, Some(e.clase_fallo()))
                     }
                 }
             });
         }
 
-        let tiempo_idle = ultima_actividad.elapsed();
-        let umbral_idle = Duration::from_secs(config.watch.idle_threshold_mins * 60);
-        let intervalo_secs = if tiempo_idle >= umbral_idle {
-            config.watch.poll_interval_idle_secs
-        } else {
-            config.watch.poll_interval_secs
-        };
-
-        salida.watch_proximo_check(intervalo_secs);
+        let mut siguiente = plan
+            .proxima()
+            .unwrap_or_else(|| instante_tras(plan.intervalo_base()));
+        if dinamicos.tiene_reglas() {
+            siguiente = siguiente.min(proximo_descubrimiento);
+        }
+        let espera = siguiente.saturating_duration_since(Instant::now());
+        if hubo_consultas {
+            guardar_estado(
+                estado_guardado.as_mut(),
+                &modelos,
+                &plan,
+                &seguimiento,
+                salida.as_ref(),
+            );
+            salida.watch_proximo_check(espera.as_secs());
+        }
+        volcar_transiciones(&mut seguimiento, linea_temporal.as_deref(), salida.as_ref());
 
         tokio::select! {
-            _ = tokio::time::sleep(Duration::from_secs(intervalo_secs)) => {}
+            _ = tokio::time::sleep(espera) => {}
             _ = esperar_cancelacion(cancel_rx.clone()) => {
                 salida.watch_deteniendo();
                 cancelar_grabaciones(&mut grabaciones).await;
@@ -252,9 +496,196 @@ where
         }
     }
 
+    guardar_estado(
+        estado_guardado.as_mut(),
+        &modelos,
+        &plan,
+        &seguimiento,
+        salida.as_ref(),
+    );
+    volcar_transiciones(&mut seguimiento, linea_temporal.as_deref(), salida.as_ref());
     Ok(())
 }
 
+const MOTIVO_PAUSA: &str = "limite de peticiones";
+
+/// Marks every idle model as paused while the rate-limit circuit is open.
+fn pausar_modelos(seguimiento: &mut SeguimientoModelos) {
+    let activos: Vec<String> = [
+        EstadoModelo::Offline,
+        EstadoModelo::Online,
+        EstadoModelo::Privado,
+    ]
+    .into_iter()
+    .flat_map(|estado| {
+        seguimiento
+            .con_estado(estado)
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    })
+    .collect();
+    for modelo in activos {
+        seguimiento.cambiar(
+            &modelo,
+            EstadoModelo::Pausado,
+            Some(MOTIVO_PAUSA.to_string()),
+        );
+    }
+}
+
+/// Appends pending transitions to the `--timeline` file, or drops them without one.
+fn volcar_transiciones(
+    seguimiento: &mut SeguimientoModelos,
+    linea_temporal: Option<&std::path::Path>,
+    salida: &dyn Output,
+) {
+    let transiciones = seguimiento.drenar_transiciones();
+    let Some(ruta) = linea_temporal else {
+        return;
+    };
+    if let Err(e) = exportar_transiciones(ruta, &transiciones) {
+        salida.advertir_config(&format!(
+            "No se pudo escribir la linea temporal en {}: {e}",
+            ruta.display()
+        ));
+    }
+}
+
+fn segundos_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+        .unwrap_or_default()
+}
+
+/// Maps a saved Unix time onto the monotonic clock of this run.
+fn instante_desde_unix(segundos: i64, ahora: Instant, ahora_unix: i64) -> Instant {
+    let delta = Duration::from_secs(segundos.abs_diff(ahora_unix));
+    if segundos >= ahora_unix {
+        ahora.checked_add(delta).unwrap_or(ahora)
+    } else {
+        ahora.checked_sub(delta).unwrap_or(ahora)
+    }
+}
+
+fn unix_desde_instante(instante: Instant, ahora: Instant, ahora_unix: i64) -> i64 {
+    if instante >= ahora {
+        let delta = instante.duration_since(ahora).as_secs();
+        ahora_unix.saturating_add(i64::try_from(delta).unwrap_or(i64::MAX))
+    } else {
+        let delta = ahora.duration_since(instante).as_secs();
+        ahora_unix.saturating_sub(i64::try_from(delta).unwrap_or(i64::MAX))
+    }
+}
+
+/// Reapplies cooldowns, skipped/invalid models and poll cadence from the
+/// previous run to the models watched now.
+fn restaurar_estado(
+    estado: &WatchState,
+    plan: &mut PlanificadorConsultas,
+    seguimiento: &mut SeguimientoModelos,
+) {
+    let ahora = Instant::now();
+    let ahora_unix = segundos_unix();
+    for (nombre, guardado) in &estado.models {
+        let Some((_, intervalo_actual, actividad_actual)) = plan.instantanea(nombre) else {
+            continue;
+        };
+        let desde = DateTime::from_timestamp(guardado.updated_at, 0).unwrap_or_else(Utc::now);
+        if guardado.invalid || guardado.skipped {
+            let estado = if guardado.invalid {
+                EstadoModelo::Invalido
+            } else {
+                EstadoModelo::Omitido
+            };
+            plan.quitar(nombre);
+            seguimiento.restaurar(nombre, estado, desde, None);
+            continue;
+        }
+        if let Some(hasta) = guardado.blocked_until {
+            let hasta = instante_desde_unix(hasta, ahora, ahora_unix);
+            seguimiento.restaurar(nombre, EstadoModelo::Cooldown, desde, Some(hasta));
+            plan.posponer_hasta(nombre, hasta);
+        }
+        let proxima = guardado
+            .next_check
+            .map_or(ahora, |t| instante_desde_unix(t, ahora, ahora_unix));
+        let intervalo = guardado
+            .interval_secs
+            .map_or(intervalo_actual, Duration::from_secs);
+        let actividad = guardado.last_active.map_or(actividad_actual, |t| {
+            instante_desde_unix(t, ahora, ahora_unix)
+        });
+        plan.restaurar(nombre, proxima, intervalo, actividad);
+    }
+}
+
+/// Snapshots the runtime state of the fixed models and writes it to disk.
+fn guardar_estado(
+    estado: Option<&mut WatchState>,
+    modelos: &[ModelName],
+    plan: &PlanificadorConsultas,
+    seguimiento: &SeguimientoModelos,
+    salida: &dyn Output,
+) {
+    let Some(estado) = estado else {
+        return;
+    };
+    let ahora = Instant::now();
+    let ahora_unix = segundos_unix();
+    let mut nuevos = std::collections::BTreeMap::new();
+    for modelo in modelos {
+        let nombre = modelo.as_str();
+        let actual = seguimiento.estado(nombre);
+        let invalid = actual == Some(EstadoModelo::Invalido);
+        let skipped = actual == Some(EstadoModelo::Omitido);
+        // Las marcas conservan su fecha original para que expiren aunque
+        // `watch` se reinicie a menudo.
+        let updated_at = if invalid || skipped {
+            seguimiento
+                .desde(nombre)
+                .map_or(ahora_unix, |desde| desde.timestamp())
+        } else {
+            ahora_unix
+        };
+        let mut entrada = ModelRuntimeState {
+            updated_at,
+            blocked_until: seguimiento
+                .bloqueado_hasta(nombre)
+                .filter(|hasta| *hasta > ahora)
+                .map(|hasta| unix_desde_instante(hasta, ahora, ahora_unix)),
+            invalid,
+            skipped,
+            ..ModelRuntimeState::default()
+        };
+        if let Some((proxima, intervalo, actividad)) = plan.instantanea(nombre) {
+            entrada.next_check = Some(unix_desde_instante(proxima, ahora, ahora_unix));
+            entrada.interval_secs = Some(intervalo.as_secs());
+            entrada.last_active = Some(unix_desde_instante(actividad, ahora, ahora_unix));
+        }
+        nuevos.insert(nombre.to_string(), entrada);
+    }
+    if nuevos == estado.models {
+        return;
+    }
+    estado.models = nuevos;
+    if let Err(e) = estado.save() {
+        salida.advertir_config(&format!("No se pudo guardar watch_state.json: {e}"));
+    }
+}
+
+/// Counts the current hour as online for `modelo` and persists the change.
+fn registrar_online(historial: Option<&mut OnlineHistory>, modelo: &str, salida: &dyn Output) {
+    let Some(historial) = historial else {
+        return;
+    };
+    if historial.record_online(modelo, chrono::Local::now()) {
+        if let Err(e) = historial.save() {
+            salida.advertir_config(&format!("No se pudo guardar online_history.json: {e}"));
+        }
+    }
+}
+
 async fn preguntar_con_timeout(modelo: &str, cfg: &WatchConfig) -> bool {
     if cfg.desktop_notify {
         let cuerpo = cfg.notif_cuerpo.replace("{modelo}", modelo);
@@ -286,42 +717,18 @@ async fn esperar_cancelacion(mut rx: watch::Receiver<bool>) {
     let _ = rx.wait_for(|v| *v).await;
 }
 
-async fn cancelar_grabaciones(grabaciones: &mut JoinSet<(String, Option<PathBuf>, bool)>) {
+async fn cancelar_grabaciones(
+    grabaciones: &mut JoinSet<(String, Option<PathBuf>, Option<ClaseFallo>)>,
+) {
     grabaciones.abort_all();
     while grabaciones.join_next().await.is_some() {}
 }
 
 fn calcular_slots_disponibles(
-    estados: &HashMap<String, EstadoModelo>,
+    seguimiento: &SeguimientoModelos,
     limite_concurrencia: usize,
 ) -> usize {
-    let grabando_ahora = estados
-        .values()
-        .filter(|e| **e == EstadoModelo::Grabando)
-        .count();
-    limite_concurrencia.saturating_sub(grabando_ahora)
-}
-
-fn debe_consultar_modelo(
-    nombre: &str,
-    estados: &HashMap<String, EstadoModelo>,
-    omitidos: &HashSet<String>,
-    invalidos: &HashSet<String>,
-    bloqueados_hasta: &HashMap<String, Instant>,
-) -> bool {
-    if estados.get(nombre) == Some(&EstadoModelo::Grabando) {
-        return false;
-    }
-    if omitidos.contains(nombre) || invalidos.contains(nombre) {
-        return false;
-    }
-    if bloqueados_hasta
-        .get(nombre)
-        .is_some_and(|hasta| Instant::now() < *hasta)
-    {
-        return false;
-    }
-    true
+    limite_concurrencia.saturating_sub(seguimiento.grabando())
 }
 
 fn cooldown_para_error_consulta(error: &InfrastructureError, base: Duration) -> Duration {
@@ -349,6 +756,7 @@ fn instante_tras(duration: Duration) -> Instant {
 mod tests {
     use super::*;
     use crate::domain::repositories::StreamRepository;
+    use crate::domain::value_objects::VarianteDisponible;
     use crate::presentation::Output;
     use async_trait::async_trait;
     use std::path::Path;
@@ -364,6 +772,8 @@ mod tests {
     struct RepoFake {
         respuesta: RespuestaRepo,
         consultas: AtomicUsize,
+        salas: Vec<DiscoveredRoom>,
+        circuitos: Mutex<Vec<EstadoCircuito>>,
     }
 
     impl RepoFake {
@@ -371,6 +781,8 @@ mod tests {
             Self {
                 respuesta: RespuestaRepo::Online,
                 consultas: AtomicUsize::new(0),
+                salas: Vec::new(),
+                circuitos: Mutex::new(Vec::new()),
             }
         }
 
@@ -378,9 +790,30 @@ mod tests {
             Self {
                 respuesta: RespuestaRepo::NoEncontrado,
                 consultas: AtomicUsize::new(0),
+                salas: Vec::new(),
+                circuitos: Mutex::new(Vec::new()),
             }
         }
 
+        fn con_salas(mut self, salas: &[(&str, u64)]) -> Self {
+            self.salas = salas
+                .iter()
+                .map(|(username, viewers)| DiscoveredRoom {
+                    username: (*username).to_string(),
+                    subject: String::new(),
+                    viewers: *viewers,
+                    show: "public".to_string(),
+                })
+                .collect();
+            self
+        }
+
+        /// Circuit states returned by successive cycles, then `Cerrado`.
+        fn con_circuitos(self, circuitos: &[EstadoCircuito]) -> Self {
+            *self.circuitos.lock().unwrap() = circuitos.iter().rev().copied().collect();
+            self
+        }
+
         fn consultas(&self) -> usize {
             self.consultas.load(Ordering::SeqCst)
         }
@@ -416,6 +849,39 @@ mod tests {
         }
     }
 
+    #[async_trait]
+    impl RoomDiscovery for RepoFake {
+        async fn rooms_by_tag(
+            &self,
+            _tag: &str,
+            _limit: usize,
+        ) -> Result<Vec<DiscoveredRoom>, InfrastructureError> {
+            Ok(self.salas.clone())
+        }
+    }
+
+    impl ClientePorModelo for RepoFake {
+        fn para_modelo(self: &Arc<Self>, _modelo: &str) -> Arc<Self> {
+            Arc::clone(self)
+        }
+    }
+
+    impl DescargaDetenible for RepoFake {
+        fn con_parada(self: &Arc<Self>, _parada: watch::Receiver<bool>) -> Arc<Self> {
+            Arc::clone(self)
+        }
+    }
+
+    impl PausaGlobal for RepoFake {
+        fn estado_circuito(&self) -> EstadoCircuito {
+            self.circuitos
+                .lock()
+                .unwrap()
+                .pop()
+                .unwrap_or(EstadoCircuito::Cerrado)
+        }
+    }
+
     struct OutputFake {
         eventos: Mutex<Vec<String>>,
         cancel_tx: watch::Sender<bool>,
@@ -483,7 +949,7 @@ mod tests {
         fn mostrar_inicio_verificacion(&self, _modelo: &str) {}
         fn mostrar_estado_modelo(&self, _modelo: &str, _online: bool) {}
         fn mostrar_modelo_sin_variantes(&self, _modelo: &str) {}
-        fn mostrar_calidades(&self, _modelo: &str, _calidades: &[(Option<u32>, Option<u64>)]) {}
+        fn mostrar_calidades(&self, _modelo: &str, _calidades: &[VarianteDisponible]) {}
         fn watch_inicio(&self, _modelos: &[&str]) {
             self.evento("inicio");
         }
@@ -508,6 +974,15 @@ mod tests {
         fn watch_deteniendo(&self) {
             self.evento("deteniendo");
         }
+        fn watch_objetivo_dinamico(&self, modelo: &str, regla: &str) {
+            self.evento(format!("dinamico:{modelo}:{regla}"));
+        }
+        fn watch_pausa_rate_limit(&self, secs: u64) {
+            self.evento(format!("pausa:{secs}"));
+        }
+        fn watch_pausa_finalizada(&self) {
+            self.evento("reanudado");
+        }
     }
 
     struct PrompterFake {
@@ -588,12 +1063,15 @@ mod tests {
             modelos: vec![modelo("alice")],
             ask: false,
             raiz_salida: None,
-            quality: VideoQuality::Best,
+            calidades: vec![VideoQuality::Best],
             limite_concurrencia: 1,
             min_file_size: Some(1),
             cancel_rx,
             salida: salida_trait,
             prompter: Arc::new(PrompterFake::new(true)),
+            historial: None,
+            estado_guardado: None,
+            linea_temporal: None,
         })
         .await
         .unwrap();
@@ -619,12 +1097,15 @@ mod tests {
             modelos: vec![modelo("alice")],
             ask: false,
             raiz_salida: None,
-            quality: VideoQuality::Best,
+            calidades: vec![VideoQuality::Best],
             limite_concurrencia: 1,
             min_file_size: Some(1),
             cancel_rx,
             salida: salida_trait,
             prompter: Arc::new(PrompterFake::new(true)),
+            historial: None,
+            estado_guardado: None,
+            linea_temporal: None,
         })
         .await
         .unwrap();
@@ -650,12 +1131,15 @@ mod tests {
             modelos: vec![modelo("alice")],
             ask: true,
             raiz_salida: None,
-            quality: VideoQuality::Best,
+            calidades: vec![VideoQuality::Best],
             limite_concurrencia: 1,
             min_file_size: Some(1),
             cancel_rx,
             salida: salida_trait,
             prompter: prompter_trait,
+            historial: None,
+            estado_guardado: None,
+            linea_temporal: None,
         })
         .await
         .unwrap();
@@ -669,131 +1153,209 @@ mod tests {
         let _ = tokio::fs::remove_dir_all(output_root).await;
     }
 
-    fn estados(items: &[(&str, EstadoModelo)]) -> HashMap<String, EstadoModelo> {
-        items
-            .iter()
-            .map(|(nombre, estado)| ((*nombre).to_string(), estado.clone()))
-            .collect()
+    #[tokio::test]
+    async fn ejecutar_watch_pausa_consultas_con_circuito_abierto() {
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+        let abierto = EstadoCircuito::Abierto {
+            restante: Duration::from_millis(10),
+        };
+        let repo = Arc::new(RepoFake::no_encontrado().con_circuitos(&[
+            abierto,
+            abierto,
+            EstadoCircuito::SemiAbierto,
+        ]));
+        let salida = Arc::new(OutputFake::new(cancel_tx));
+        let salida_trait: Arc<dyn Output> = salida.clone();
+
+        ejecutar_watch_con_repo(WatchParams {
+            client: Arc::clone(&repo),
+            config: Arc::new(config_test()),
+            modelos: vec![modelo("alice")],
+            ask: false,
+            raiz_salida: None,
+            calidades: vec![VideoQuality::Best],
+            limite_concurrencia: 1,
+            min_file_size: Some(1),
+            cancel_rx,
+            salida: salida_trait,
+            prompter: Arc::new(PrompterFake::new(true)),
+            historial: None,
+            estado_guardado: None,
+            linea_temporal: None,
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            salida.eventos(),
+            ["inicio", "pausa:1", "error:alice", "deteniendo"]
+        );
+        assert_eq!(repo.consultas(), 1);
+    }
+
+    #[tokio::test]
+    async fn ejecutar_watch_graba_objetivo_de_regla_sin_persistirlo() {
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+        let repo = Arc::new(RepoFake::online().con_salas(&[("carol", 800), ("dave", 5)]));
+        let salida = Arc::new(OutputFake::new(cancel_tx));
+        let salida_trait: Arc<dyn Output> = salida.clone();
+        let mut config = config_test();
+        config.discovery.rules = vec![crate::infrastructure::DiscoveryRule {
+            tag: "gaming".to_string(),
+            min_viewers: 100,
+            new_rooms: false,
+        }];
+        let output_root = config.output_root.clone();
+
+        ejecutar_watch_con_repo(WatchParams {
+            client: Arc::clone(&repo),
+            config: Arc::new(config),
+            modelos: Vec::new(),
+            ask: false,
+            raiz_salida: None,
+            calidades: vec![VideoQuality::Best],
+            limite_concurrencia: 1,
+            min_file_size: Some(1),
+            cancel_rx,
+            salida: salida_trait,
+            prompter: Arc::new(PrompterFake::new(true)),
+            historial: None,
+            estado_guardado: None,
+            linea_temporal: None,
+        })
+        .await
+        .unwrap();
+
+        let eventos = salida.eventos();
+        assert!(eventos.contains(&"dinamico:carol:#gaming >=100 espectadores".to_string()));
+        assert!(eventos.contains(&"grabando:carol".to_string()));
+        assert!(eventos.contains(&"fin:carol".to_string()));
+        assert!(!eventos.iter().any(|e| e.contains("dave")));
+        let _ = tokio::fs::remove_dir_all(output_root).await;
+    }
+
+    fn seguimiento(items: &[(&str, EstadoModelo)]) -> SeguimientoModelos {
+        let mut seguimiento = SeguimientoModelos::new(items.iter().map(|(nombre, _)| *nombre));
+        for (nombre, estado) in items {
+            seguimiento.cambiar(nombre, *estado, None);
+        }
+        seguimiento
     }
 
     #[test]
     fn calcular_slots_disponibles_respeta_limite() {
-        let estados = estados(&[
+        let seguimiento = seguimiento(&[
             ("alice", EstadoModelo::Grabando),
             ("bob", EstadoModelo::Offline),
         ]);
 
-        assert_eq!(calcular_slots_disponibles(&estados, 2), 1);
+        assert_eq!(calcular_slots_disponibles(&seguimiento, 2), 1);
     }
 
     #[test]
     fn calcular_slots_disponibles_no_baja_de_cero() {
-        let estados = estados(&[
+        let seguimiento = seguimiento(&[
             ("alice", EstadoModelo::Grabando),
             ("bob", EstadoModelo::Grabando),
         ]);
 
-        assert_eq!(calcular_slots_disponibles(&estados, 1), 0);
+        assert_eq!(calcular_slots_disponibles(&seguimiento, 1), 0);
     }
 
     #[test]
-    fn debe_consultar_modelo_offline_sin_bloqueos() {
-        let estados = estados(&[("alice", EstadoModelo::Offline)]);
-        let omitidos = HashSet::new();
-        let invalidos = HashSet::new();
-        let bloqueados_hasta = HashMap::new();
+    fn consultable_offline_sin_bloqueos() {
+        let seguimiento = seguimiento(&[("alice", EstadoModelo::Offline)]);
 
-        assert!(debe_consultar_modelo(
-            "alice",
-            &estados,
-            &omitidos,
-            &invalidos,
-            &bloqueados_hasta,
-        ));
+        assert!(seguimiento.consultable("alice", Instant::now()));
     }
 
     #[test]
-    fn debe_consultar_modelo_ignora_si_esta_grabando() {
-        let estados = estados(&[("alice", EstadoModelo::Grabando)]);
-        let omitidos = HashSet::new();
-        let invalidos = HashSet::new();
-        let bloqueados_hasta = HashMap::new();
+    fn consultable_ignora_si_esta_grabando() {
+        let seguimiento = seguimiento(&[("alice", EstadoModelo::Grabando)]);
 
-        assert!(!debe_consultar_modelo(
-            "alice",
-            &estados,
-            &omitidos,
-            &invalidos,
-            &bloqueados_hasta,
-        ));
+        assert!(!seguimiento.consultable("alice", Instant::now()));
     }
 
     #[test]
-    fn debe_consultar_modelo_ignora_omitidos_e_invalidos() {
-        let estados = estados(&[
-            ("alice", EstadoModelo::Offline),
-            ("bob", EstadoModelo::Offline),
+    fn restaurar_estado_reaplica_cooldowns_omitidos_e_invalidos() {
+        let ahora_unix = segundos_unix();
+        let entrada = |cambios: fn(&mut ModelRuntimeState)| {
+            let mut estado = ModelRuntimeState {
+                updated_at: ahora_unix,
+                ..ModelRuntimeState::default()
+            };
+            cambios(&mut estado);
+            estado
+        };
+        let mut estado = WatchState::default();
+        estado
+            .models
+            .insert("alice".to_string(), entrada(|e| e.skipped = true));
+        estado
+            .models
+            .insert("bob".to_string(), entrada(|e| e.invalid = true));
+        estado.models.insert(
+            "carol".to_string(),
+            entrada(|e| e.blocked_until = Some(e.updated_at + 600)),
+        );
+        estado.models.insert(
+            "olvidada".to_string(),
+            entrada(|e| e.blocked_until = Some(e.updated_at + 600)),
+        );
+
+        let nombres = ["alice", "bob", "carol", "dave"];
+        let mut plan = PlanificadorConsultas::new(&WatchConfig::default());
+        plan.agregar_escalonados(&nombres, Instant::now());
+        let mut seguimiento = SeguimientoModelos::new(nombres);
+
+        restaurar_estado(&estado, &mut plan, &mut seguimiento);
+
+        assert_eq!(seguimiento.estado("alice"), Some(EstadoModelo::Omitido));
+        assert_eq!(seguimiento.estado("bob"), Some(EstadoModelo::Invalido));
+        assert_eq!(seguimiento.estado("carol"), Some(EstadoModelo::Cooldown));
+        assert!(seguimiento.bloqueado_hasta("carol").is_some());
+        assert_eq!(seguimiento.estado("olvidada"), None);
+        let tras_intervalo = Instant::now() + plan.intervalo_base();
+        assert_eq!(plan.vencidos(tras_intervalo, 10), ["dave"]);
+    }
+
+    #[test]
+    fn consultable_ignora_omitidos_e_invalidos() {
+        let seguimiento = seguimiento(&[
+            ("alice", EstadoModelo::Omitido),
+            ("bob", EstadoModelo::Invalido),
         ]);
-        let omitidos = HashSet::from(["alice".to_string()]);
-        let invalidos = HashSet::from(["bob".to_string()]);
-        let bloqueados_hasta = HashMap::new();
 
-        assert!(!debe_consultar_modelo(
-            "alice",
-            &estados,
-            &omitidos,
-            &invalidos,
-            &bloqueados_hasta,
-        ));
-        assert!(!debe_consultar_modelo(
-            "bob",
-            &estados,
-            &omitidos,
-            &invalidos,
-            &bloqueados_hasta,
-        ));
+        assert!(!seguimiento.consultable("alice", Instant::now()));
+        assert!(!seguimiento.consultable("bob", Instant::now()));
     }
 
     #[test]
-    fn debe_consultar_modelo_respeta_cooldown_de_fallo() {
-        let estados = estados(&[("alice", EstadoModelo::Offline)]);
-        let omitidos = HashSet::new();
-        let invalidos = HashSet::new();
-        let bloqueados_hasta = HashMap::from([(
-            "alice".to_string(),
+    fn consultable_respeta_cooldown_de_fallo() {
+        let mut seguimiento = seguimiento(&[("alice", EstadoModelo::Offline)]);
+        seguimiento.bloquear(
+            "alice",
             Instant::now()
                 .checked_add(Duration::from_secs(300))
                 .unwrap_or_else(Instant::now),
-        )]);
+            "HTTP 503".to_string(),
+        );
 
-        assert!(!debe_consultar_modelo(
-            "alice",
-            &estados,
-            &omitidos,
-            &invalidos,
-            &bloqueados_hasta,
-        ));
+        assert!(!seguimiento.consultable("alice", Instant::now()));
     }
 
     #[test]
-    fn debe_consultar_modelo_reintenta_despues_del_cooldown() {
-        let estados = estados(&[("alice", EstadoModelo::Offline)]);
-        let omitidos = HashSet::new();
-        let invalidos = HashSet::new();
-        let bloqueados_hasta = HashMap::from([(
-            "alice".to_string(),
+    fn consultable_reintenta_despues_del_cooldown() {
+        let mut seguimiento = seguimiento(&[("alice", EstadoModelo::Offline)]);
+        seguimiento.bloquear(
+            "alice",
             Instant::now()
                 .checked_sub(Duration::from_secs(1))
                 .unwrap_or_else(Instant::now),
-        )]);
+            "HTTP 503".to_string(),
+        );
 
-        assert!(debe_consultar_modelo(
-            "alice",
-            &estados,
-            &omitidos,
-            &invalidos,
-            &bloqueados_hasta,
-        ));
+        assert!(seguimiento.consultable("alice", Instant::now()));
     }
 
     #[test]
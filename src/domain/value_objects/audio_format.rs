@@ -0,0 +1,46 @@
+use std::str::FromStr;
+
+/// Container and codec of audio-only recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FormatoAudio {
+    /// The stream's AAC track copied into an MP4 audio container.
+    #[default]
+    M4a,
+    /// Transcoded to Opus in an Ogg container.
+    Opus,
+}
+
+impl FormatoAudio {
+    pub fn extension(self) -> &'static str {
+        match self {
+            FormatoAudio::M4a => "m4a",
+            FormatoAudio::Opus => "opus",
+        }
+    }
+
+    /// Codec written into the file.
+    pub fn codec(self) -> &'static str {
+        match self {
+            FormatoAudio::M4a => "aac",
+            FormatoAudio::Opus => "opus",
+        }
+    }
+}
+
+impl FromStr for FormatoAudio {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "m4a" | "aac" => Ok(FormatoAudio::M4a),
+            "opus" => Ok(FormatoAudio::Opus),
+            _ => Err(format!("Formato de audio invalido: {} (usa m4a u opus)", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for FormatoAudio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.extension())
+    }
+}
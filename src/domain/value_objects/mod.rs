@@ -1,9 +1,15 @@
+pub mod audio_format;
 pub mod model_name;
 pub mod model_state;
+pub mod recording_progress;
 pub mod stream_url;
+pub mod variant_policy;
 pub mod video_quality;
 
+pub use audio_format::FormatoAudio;
 pub use model_name::ModelName;
-pub use model_state::EstadoModelo;
+pub use model_state::{EstadoModelo, TransicionEstado};
+pub use recording_progress::ProgresoGrabacion;
 pub use stream_url::StreamUrl;
-pub use video_quality::VideoQuality;
+pub use variant_policy::{parsear_bitrate, CodecVideo, PoliticaVariante, VarianteDisponible};
+pub use video_quality::{parsear_calidades, VideoQuality};
@@ -1,18 +1,59 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle of a model while `watch` follows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum EstadoModelo {
     Offline,
+    /// Public show detected but not being recorded (no slot, declined...).
+    Online,
     Grabando,
+    /// Private, fan-club, password-protected or hidden show.
+    Privado,
+    /// Checks suspended after a failure until a deadline.
+    Cooldown,
+    /// Declined with `--ask` or given up after repeated failures; not checked again.
+    Omitido,
+    /// The room does not exist; not checked again.
+    Invalido,
+    /// Checks paused by the global rate-limit circuit.
+    Pausado,
+}
+
+impl EstadoModelo {
+    /// States in which the model is no longer polled.
+    pub fn es_final(self) -> bool {
+        matches!(self, EstadoModelo::Omitido | EstadoModelo::Invalido)
+    }
 }
 
 impl std::fmt::Display for EstadoModelo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             EstadoModelo::Offline => write!(f, "offline"),
+            EstadoModelo::Online => write!(f, "online"),
             EstadoModelo::Grabando => write!(f, "grabando"),
+            EstadoModelo::Privado => write!(f, "privado"),
+            EstadoModelo::Cooldown => write!(f, "cooldown"),
+            EstadoModelo::Omitido => write!(f, "omitido"),
+            EstadoModelo::Invalido => write!(f, "invalido"),
+            EstadoModelo::Pausado => write!(f, "pausado"),
         }
     }
 }
 
+/// One change of state, as exported to the `watch --timeline` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransicionEstado {
+    pub modelo: String,
+    pub desde: EstadoModelo,
+    pub hacia: EstadoModelo,
+    pub instante: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motivo: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -21,5 +62,25 @@ mod tests {
     fn displays_stable_state_names() {
         assert_eq!(EstadoModelo::Offline.to_string(), "offline");
         assert_eq!(EstadoModelo::Grabando.to_string(), "grabando");
+        assert_eq!(EstadoModelo::Privado.to_string(), "privado");
+    }
+
+    #[test]
+    fn serializes_states_with_display_names() {
+        for estado in [
+            EstadoModelo::Offline,
+            EstadoModelo::Online,
+            EstadoModelo::Grabando,
+            EstadoModelo::Privado,
+            EstadoModelo::Cooldown,
+            EstadoModelo::Omitido,
+            EstadoModelo::Invalido,
+            EstadoModelo::Pausado,
+        ] {
+            assert_eq!(
+                serde_json::to_string(&estado).unwrap(),
+                format!("\"{estado}\"")
+            );
+        }
     }
 }
@@ -0,0 +1,15 @@
+use std::time::Duration;
+
+/// Snapshot of a running recording as reported by FFmpeg.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProgresoGrabacion {
+    /// Bytes written so far.
+    pub bytes: u64,
+    /// Media time written so far; it only advances while the stream does.
+    pub tiempo_media: Duration,
+    pub bitrate_kbps: Option<f64>,
+    /// Media seconds written per wall-clock second.
+    pub velocidad: Option<f64>,
+    pub frames: u64,
+    pub frames_perdidos: u64,
+}
@@ -0,0 +1,140 @@
+use super::VideoQuality;
+use std::str::FromStr;
+
+/// Video codec family announced in a variant's `CODECS` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecVideo {
+    H264,
+    Hevc,
+}
+
+impl CodecVideo {
+    /// Detects the video codec of an HLS `CODECS` list such as `avc1.640028,mp4a.40.2`.
+    pub fn desde_codecs(codecs: &str) -> Option<Self> {
+        codecs.split(',').find_map(|codec| {
+            let familia = codec.trim().split('.').next().unwrap_or_default();
+            match familia.to_ascii_lowercase().as_str() {
+                "avc1" | "avc3" => Some(CodecVideo::H264),
+                "hvc1" | "hev1" => Some(CodecVideo::Hevc),
+                _ => None,
+            }
+        })
+    }
+}
+
+impl FromStr for CodecVideo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "h264" | "avc" => Ok(CodecVideo::H264),
+            "hevc" | "h265" => Ok(CodecVideo::Hevc),
+            _ => Err(format!("Codec invalido: {} (usa h264 o hevc)", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for CodecVideo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecVideo::H264 => write!(f, "h264"),
+            CodecVideo::Hevc => write!(f, "hevc"),
+        }
+    }
+}
+
+/// How one variant is chosen from a master playlist.
+///
+/// Preferences narrow the candidates in order: bitrate limit, codec, frame
+/// rate and finally height. Without `estricta`, a preference that no variant
+/// meets is dropped; with it, the selection fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoliticaVariante {
+    pub calidad: VideoQuality,
+    /// Maximum `BANDWIDTH` in bits per second.
+    pub max_bitrate: Option<u64>,
+    pub codec: Option<CodecVideo>,
+    pub fps: Option<u32>,
+    pub estricta: bool,
+}
+
+impl From<VideoQuality> for PoliticaVariante {
+    fn from(calidad: VideoQuality) -> Self {
+        Self {
+            calidad,
+            max_bitrate: None,
+            codec: None,
+            fps: None,
+            estricta: false,
+        }
+    }
+}
+
+impl PoliticaVariante {
+    /// Same preferences applied to another requested quality.
+    pub fn con_calidad(self, calidad: VideoQuality) -> Self {
+        Self { calidad, ..self }
+    }
+}
+
+/// Variant of a master playlist as shown by `--list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarianteDisponible {
+    pub height: Option<u32>,
+    pub bandwidth: Option<u64>,
+    pub codecs: Option<String>,
+    pub frame_rate: Option<f64>,
+    /// Name of the separate audio rendition recorded with this variant.
+    pub audio: Option<String>,
+    /// Whether the current selection policy would record this variant.
+    pub seleccionada: bool,
+}
+
+/// Parses a bitrate such as `3M`, `800k` or `2500000` into bits per second.
+pub fn parsear_bitrate(valor: &str) -> Result<u64, String> {
+    let texto = valor.trim();
+    let (numero, factor) = match texto.chars().last().map(|c| c.to_ascii_lowercase()) {
+        Some('k') => (&texto[..texto.len() - 1], 1_000.0),
+        Some('m') => (&texto[..texto.len() - 1], 1_000_000.0),
+        Some('g') => (&texto[..texto.len() - 1], 1_000_000_000.0),
+        _ => (texto, 1.0),
+    };
+    let bits = numero
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n > 0.0)
+        .map(|n| n * factor)
+        .filter(|bits| *bits >= 1.0 && *bits <= u64::MAX as f64)
+        .ok_or_else(|| format!("Bitrate invalido: {} (ejemplos: 3M, 800k)", valor))?;
+    Ok(bits.round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bitrate_suffixes() {
+        assert_eq!(parsear_bitrate("3M"), Ok(3_000_000));
+        assert_eq!(parsear_bitrate("1.5m"), Ok(1_500_000));
+        assert_eq!(parsear_bitrate("800k"), Ok(800_000));
+        assert_eq!(parsear_bitrate("2500000"), Ok(2_500_000));
+        assert!(parsear_bitrate("0").is_err());
+        assert!(parsear_bitrate("rapido").is_err());
+    }
+
+    #[test]
+    fn detects_video_codec_family() {
+        assert_eq!(
+            CodecVideo::desde_codecs("mp4a.40.2,avc1.640028"),
+            Some(CodecVideo::H264)
+        );
+        assert_eq!(
+            CodecVideo::desde_codecs("hvc1.1.6.L120.90"),
+            Some(CodecVideo::Hevc)
+        );
+        assert_eq!(CodecVideo::desde_codecs("mp4a.40.2"), None);
+        assert_eq!("H265".parse(), Ok(CodecVideo::Hevc));
+    }
+}
@@ -56,6 +56,18 @@ impl VideoQuality {
     }
 }
 
+/// Parses a comma-separated quality list such as `best,480p`, dropping repeats.
+pub fn parsear_calidades(s: &str) -> Result<Vec<VideoQuality>, String> {
+    let mut calidades = Vec::new();
+    for parte in s.split(',') {
+        let calidad = VideoQuality::from_str(parte.trim())?;
+        if !calidades.contains(&calidad) {
+            calidades.push(calidad);
+        }
+    }
+    Ok(calidades)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,4 +110,14 @@ mod tests {
             assert_eq!(quality.target_height(), height);
         }
     }
+
+    #[test]
+    fn parses_quality_lists_without_repeats() {
+        assert_eq!(
+            parsear_calidades("best, 480p,best"),
+            Ok(vec![VideoQuality::Best, VideoQuality::P480])
+        );
+        assert_eq!(parsear_calidades("720p"), Ok(vec![VideoQuality::P720]));
+        assert_eq!(parsear_calidades("best,"), Err("Calidad invalida: ".into()));
+    }
 }
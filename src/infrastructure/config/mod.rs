@@ -1,4 +1,14 @@
 pub mod app_config;
+pub mod naming;
+pub mod online_history;
+pub mod watch_state;
 
-pub(crate) use app_config::expandir_tilde;
-pub use app_config::{AppConfig, ConfigWarning, LoadedAppConfig, WatchConfig, WatchedModels};
+pub(crate) use app_config::{expandir_tilde, normalizar_proxy, ocultar_credenciales_proxy};
+pub use app_config::{
+    AppConfig, BufferConfig, ConfigWarning, DiscoveryConfig, DiscoveryRule, EstrategiaReintento,
+    FfmpegConfig, LoadedAppConfig, NetworkConfig, PerfilFfmpeg, RecordingConfig, RetryConfig,
+    RetryPolicy, VolumenSalida, WatchConfig, WatchedModels,
+};
+pub use naming::{DatosNombre, PlantillaNombre};
+pub use online_history::{ActividadPrevista, OnlineHistory};
+pub use watch_state::{ModelRuntimeState, WatchState};
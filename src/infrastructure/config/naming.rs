@@ -0,0 +1,353 @@
+//! Recording file names rendered from `naming.template`.
+//!
+//! A template is literal text with `{variable}` placeholders; `/` separates
+//! directories and `{{`/`}}` write literal braces. Variable values are
+//! sanitized for the current platform so they never add path components.
+
+use crate::domain::value_objects::VideoQuality;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, Utc};
+use std::path::{Path, PathBuf};
+
+/// Longest `{subject}` value kept in a file name, in characters.
+const MAX_SUBJECT: usize = 80;
+
+/// Values available to a naming template.
+#[derive(Debug, Clone, Copy)]
+pub struct DatosNombre<'a> {
+    pub modelo: &'a str,
+    pub inicio: DateTime<Utc>,
+    pub quality: VideoQuality,
+    pub sesion: &'a str,
+    pub parte: u32,
+    pub subject: Option<&'a str>,
+    pub ext: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pieza {
+    Literal(String),
+    /// strftime pattern rendered with the configured time zone.
+    Fecha(String),
+    Modelo,
+    Calidad,
+    Altura,
+    Sesion,
+    /// Part number zero-padded to this width.
+    Parte(usize),
+    Subject,
+    Ext,
+}
+
+/// Parsed `naming.template`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlantillaNombre {
+    piezas: Vec<Pieza>,
+}
+
+impl PlantillaNombre {
+    pub fn parsear(plantilla: &str) -> Result<Self, String> {
+        let plantilla = plantilla.trim();
+        if plantilla.is_empty() {
+            return Err("la plantilla esta vacia".to_string());
+        }
+        if Path::new(plantilla).is_absolute() || plantilla.starts_with(['/', '\\']) {
+            return Err("debe ser una ruta relativa".to_string());
+        }
+
+        let mut piezas = Vec::new();
+        let mut literal = String::new();
+        let mut chars = plantilla.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err("'}' sin '{' de apertura".to_string()),
+                '{' => {
+                    let mut variable = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => variable.push(c),
+                            None => return Err(format!("'{{{variable}' sin cerrar")),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        piezas.push(Pieza::Literal(std::mem::take(&mut literal)));
+                    }
+                    piezas.push(parsear_variable(&variable)?);
+                }
+                '\\' => literal.push('/'),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            piezas.push(Pieza::Literal(literal));
+        }
+
+        let literales = piezas.iter().filter_map(|pieza| match pieza {
+            Pieza::Literal(texto) => Some(texto.as_str()),
+            _ => None,
+        });
+        for texto in literales {
+            if texto.split('/').any(|componente| componente.trim() == "..") {
+                return Err("no puede contener '..'".to_string());
+            }
+        }
+        if matches!(piezas.last(), Some(Pieza::Literal(texto)) if texto.ends_with('/')) {
+            return Err("debe terminar en un nombre de archivo".to_string());
+        }
+        Ok(Self { piezas })
+    }
+
+    /// `true` when the template lays out directories itself.
+    pub fn define_directorios(&self) -> bool {
+        self.piezas
+            .iter()
+            .any(|pieza| matches!(pieza, Pieza::Literal(texto) if texto.contains('/')))
+    }
+
+    /// `true` when rendering needs the room subject.
+    pub fn usa_subject(&self) -> bool {
+        self.piezas.contains(&Pieza::Subject)
+    }
+
+    /// `true` when the template places the extension through `{ext}`.
+    pub fn usa_ext(&self) -> bool {
+        self.piezas.contains(&Pieza::Ext)
+    }
+
+    /// `true` when file names already tell qualities apart.
+    pub fn usa_calidad(&self) -> bool {
+        self.piezas
+            .iter()
+            .any(|pieza| matches!(pieza, Pieza::Calidad | Pieza::Altura))
+    }
+
+    /// Relative path for `datos`; dates use UTC when `utc` is set.
+    pub fn renderizar(&self, datos: &DatosNombre<'_>, utc: bool) -> PathBuf {
+        let windows = cfg!(windows);
+        let mut texto = String::new();
+        for pieza in &self.piezas {
+            let valor = match pieza {
+                Pieza::Literal(literal) => {
+                    texto.push_str(literal);
+                    continue;
+                }
+                Pieza::Fecha(formato) if utc => datos.inicio.format(formato).to_string(),
+                Pieza::Fecha(formato) => datos
+                    .inicio
+                    .with_timezone(&Local)
+                    .format(formato)
+                    .to_string(),
+                Pieza::Modelo => datos.modelo.to_string(),
+                Pieza::Calidad => datos.quality.to_string(),
+                Pieza::Altura => match datos.quality.target_height() {
+                    Some(0) | None => datos.quality.to_string(),
+                    Some(altura) => altura.to_string(),
+                },
+                Pieza::Sesion => datos.sesion.to_string(),
+                Pieza::Parte(ancho) => format!("{:0ancho$}", datos.parte),
+                Pieza::Subject => datos
+                    .subject
+                    .unwrap_or_default()
+                    .trim()
+                    .chars()
+                    .take(MAX_SUBJECT)
+                    .collect(),
+                Pieza::Ext => datos.ext.to_string(),
+            };
+            texto.push_str(&limpiar_valor(&valor, windows));
+        }
+
+        texto
+            .split('/')
+            .map(|componente| sanitizar_componente(componente, windows))
+            .collect()
+    }
+}
+
+fn parsear_variable(variable: &str) -> Result<Pieza, String> {
+    let (nombre, formato) = match variable.split_once(':') {
+        Some((nombre, formato)) => (nombre.trim(), Some(formato)),
+        None => (variable.trim(), None),
+    };
+    let fecha = |patron: &str| Pieza::Fecha(patron.to_string());
+    let pieza = match nombre {
+        "year" => fecha("%Y"),
+        "month" => fecha("%m"),
+        "day" => fecha("%d"),
+        "hour" => fecha("%H"),
+        "minute" => fecha("%M"),
+        "second" => fecha("%S"),
+        "date" => {
+            let Some(formato) = formato.filter(|f| !f.is_empty()) else {
+                return Err("{date} requiere un formato, ej: {date:%Y-%m-%d}".to_string());
+            };
+            if StrftimeItems::new(formato).any(|item| matches!(item, Item::Error)) {
+                return Err(format!("formato de fecha invalido: {formato}"));
+            }
+            return Ok(fecha(formato));
+        }
+        "part" => {
+            let ancho = match formato {
+                None => 1,
+                Some(ancho) => ancho
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|ancho| (1..=9).contains(ancho))
+                    .ok_or_else(|| format!("ancho invalido en {{part:{ancho}}} (usa 1-9)"))?,
+            };
+            return Ok(Pieza::Parte(ancho));
+        }
+        "model" => Pieza::Modelo,
+        "quality" => Pieza::Calidad,
+        "height" => Pieza::Altura,
+        "session" => Pieza::Sesion,
+        "subject" => Pieza::Subject,
+        "ext" => Pieza::Ext,
+        _ => return Err(format!("variable desconocida {{{nombre}}}")),
+    };
+    match formato {
+        Some(_) => Err(format!("{{{nombre}}} no admite formato")),
+        None => Ok(pieza),
+    }
+}
+
+/// Replaces characters that would add path components or are invalid on the platform.
+fn limpiar_valor(valor: &str, windows: bool) -> String {
+    valor
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' if windows => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Makes one rendered path component safe to create.
+fn sanitizar_componente(componente: &str, windows: bool) -> String {
+    let mut componente = componente.trim().to_string();
+    if windows {
+        componente.truncate(componente.trim_end_matches(['.', ' ']).len());
+        let base = componente.split('.').next().unwrap_or_default();
+        if nombre_reservado_windows(base) {
+            componente.insert(0, '_');
+        }
+    }
+    if componente.is_empty() || componente == "." || componente == ".." {
+        return "_".to_string();
+    }
+    componente
+}
+
+fn nombre_reservado_windows(base: &str) -> bool {
+    let base = base.trim().to_ascii_uppercase();
+    matches!(base.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((base.starts_with("COM") || base.starts_with("LPT"))
+            && base.len() == 4
+            && base.as_bytes()[3].is_ascii_digit()
+            && base.as_bytes()[3] != b'0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn datos(subject: Option<&str>) -> DatosNombre<'_> {
+        DatosNombre {
+            modelo: "alice",
+            inicio: Utc.with_ymd_and_hms(2026, 3, 4, 5, 6, 7).unwrap(),
+            quality: VideoQuality::P480,
+            sesion: "alice-20260304T050607",
+            parte: 2,
+            subject,
+            ext: "mp4",
+        }
+    }
+
+    #[test]
+    fn renders_every_variable_in_utc() {
+        let plantilla = PlantillaNombre::parsear(
+            "{model}/{date:%Y-%m}/{year}{month}{day}_{hour}{minute}{second}_{quality}_{height}_{session}_{part:03}_{subject}.{ext}",
+        )
+        .unwrap();
+
+        let ruta = plantilla.renderizar(&datos(Some("  hi / there ")), true);
+
+        assert_eq!(
+            ruta,
+            PathBuf::from(
+                "alice/2026-03/20260304_050607_480p_480_alice-20260304T050607_002_hi _ there.mp4"
+            )
+        );
+        assert!(plantilla.define_directorios());
+        assert!(plantilla.usa_subject());
+        assert!(plantilla.usa_calidad());
+    }
+
+    #[test]
+    fn values_cannot_escape_the_output_directory() {
+        let plantilla = PlantillaNombre::parsear("{subject}/{model}.mp4").unwrap();
+
+        assert_eq!(
+            plantilla.renderizar(&datos(Some("..")), true),
+            PathBuf::from("_/alice.mp4")
+        );
+        assert_eq!(
+            plantilla.renderizar(&datos(None), true),
+            PathBuf::from("_/alice.mp4")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for (plantilla, error) in [
+            ("", "la plantilla esta vacia"),
+            ("/abs/{model}.mp4", "debe ser una ruta relativa"),
+            ("../{model}.mp4", "no puede contener '..'"),
+            ("{model}/", "debe terminar en un nombre de archivo"),
+            ("{model", "'{model' sin cerrar"),
+            ("{model}}.mp4", "'}' sin '{' de apertura"),
+            ("{nombre}.mp4", "variable desconocida {nombre}"),
+            ("{model:x}.mp4", "{model} no admite formato"),
+            (
+                "{date}.mp4",
+                "{date} requiere un formato, ej: {date:%Y-%m-%d}",
+            ),
+            ("{date:%Q}.mp4", "formato de fecha invalido: %Q"),
+            ("{part:0}.mp4", "ancho invalido en {part:0} (usa 1-9)"),
+        ] {
+            assert_eq!(
+                PlantillaNombre::parsear(plantilla),
+                Err(error.to_string()),
+                "{plantilla}"
+            );
+        }
+        assert_eq!(
+            PlantillaNombre::parsear("{{{model}}}.mp4")
+                .unwrap()
+                .renderizar(&datos(None), true),
+            PathBuf::from("{alice}.mp4")
+        );
+    }
+
+    #[test]
+    fn windows_sanitization_drops_reserved_names_and_characters() {
+        assert_eq!(limpiar_valor("a:b?c", true), "a_b_c");
+        assert_eq!(limpiar_valor("a:b?c", false), "a:b?c");
+        assert_eq!(sanitizar_componente("con.mp4", true), "_con.mp4");
+        assert_eq!(sanitizar_componente("COM1", true), "_COM1");
+        assert_eq!(sanitizar_componente("show. ", true), "show");
+        assert_eq!(sanitizar_componente("con.mp4", false), "con.mp4");
+    }
+}
@@ -0,0 +1,193 @@
+//! Weekly record of when each watched model was seen online.
+//!
+//! Stored as `online_history.json` next to `watched.toml`. Every model keeps
+//! 168 counters (Monday 00h .. Sunday 23h, local time); each clock hour in
+//! which the model is seen online adds one to its slot.
+
+use super::app_config::{config_dir, ruta_temporal_para};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const HORAS_SEMANA: usize = 7 * 24;
+/// Online hours needed before the history influences polling.
+const MIN_OBSERVACIONES: u32 = 6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelHistory {
+    pub hours: Vec<u32>,
+    /// Hours since the Unix epoch of the last counted observation.
+    #[serde(default)]
+    pub last_hour: Option<i64>,
+}
+
+impl Default for ModelHistory {
+    fn default() -> Self {
+        Self {
+            hours: vec![0; HORAS_SEMANA],
+            last_hour: None,
+        }
+    }
+}
+
+/// What the history says about a model around a given time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActividadPrevista {
+    SinDatos,
+    Probable,
+    Improbable,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OnlineHistory {
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelHistory>,
+}
+
+impl OnlineHistory {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|d| d.join("online_history.json"))
+    }
+
+    /// Loads the history; a missing or unreadable file starts an empty one.
+    pub fn load() -> Self {
+        Self::path()
+            .map(|path| Self::load_from_path(&path))
+            .unwrap_or_default()
+    }
+
+    fn load_from_path(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contenido| serde_json::from_str::<Self>(&contenido).ok())
+            .map(|mut history| {
+                for model in history.models.values_mut() {
+                    model.hours.resize(HORAS_SEMANA, 0);
+                }
+                history
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "Sin directorio de config")
+        })?;
+        self.save_to_path(&path)
+    }
+
+    fn save_to_path(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contenido = serde_json::to_string(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let tmp_path = ruta_temporal_para(path);
+        fs::write(&tmp_path, contenido)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Counts `model` as online during the clock hour of `at`; returns
+    /// `false` when that hour was already counted.
+    pub fn record_online(&mut self, model: &str, at: DateTime<Local>) -> bool {
+        let hora_absoluta = at.timestamp().div_euclid(3600);
+        let history = self.models.entry(model.to_string()).or_default();
+        if history.last_hour == Some(hora_absoluta) {
+            return false;
+        }
+        history.last_hour = Some(hora_absoluta);
+        history.hours[slot(at)] = history.hours[slot(at)].saturating_add(1);
+        true
+    }
+
+    pub fn heat_map(&self, model: &str) -> Option<&[u32]> {
+        self.models.get(model).map(|h| h.hours.as_slice())
+    }
+
+    /// Compares the current and next hour against the model's busiest slot.
+    pub fn prevision(&self, model: &str, at: DateTime<Local>) -> ActividadPrevista {
+        let Some(horas) = self.heat_map(model) else {
+            return ActividadPrevista::SinDatos;
+        };
+        let total: u32 = horas.iter().sum();
+        let maximo = horas.iter().copied().max().unwrap_or_default();
+        if total < MIN_OBSERVACIONES || maximo == 0 {
+            return ActividadPrevista::SinDatos;
+        }
+        let cercano = horas[slot(at)].max(horas[slot(at + ChronoDuration::hours(1))]);
+        if cercano.saturating_mul(2) >= maximo {
+            ActividadPrevista::Probable
+        } else if cercano == 0 {
+            ActividadPrevista::Improbable
+        } else {
+            ActividadPrevista::SinDatos
+        }
+    }
+}
+
+fn slot(at: DateTime<Local>) -> usize {
+    at.weekday().num_days_from_monday() as usize * 24 + at.hour() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn lunes(hora: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, 1, hora, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn record_online_counts_each_hour_once() {
+        let mut history = OnlineHistory::default();
+
+        assert!(history.record_online("alice", lunes(20)));
+        assert!(!history.record_online("alice", lunes(20) + ChronoDuration::minutes(10)));
+        assert!(history.record_online("alice", lunes(21)));
+
+        let horas = history.heat_map("alice").unwrap();
+        assert_eq!(horas[20], 1);
+        assert_eq!(horas[21], 1);
+    }
+
+    #[test]
+    fn prevision_needs_data_and_marks_usual_hours() {
+        let mut history = OnlineHistory::default();
+        for semana in 0..6 {
+            history.record_online("alice", lunes(20) + ChronoDuration::weeks(semana));
+        }
+
+        assert_eq!(
+            history.prevision("bob", lunes(20)),
+            ActividadPrevista::SinDatos
+        );
+        assert_eq!(
+            history.prevision("alice", lunes(19)),
+            ActividadPrevista::Probable
+        );
+        assert_eq!(
+            history.prevision("alice", lunes(3)),
+            ActividadPrevista::Improbable
+        );
+    }
+
+    #[test]
+    fn history_survives_save_and_load() {
+        let path = std::env::temp_dir().join(format!(
+            "cbrec_online_history_{}_{}.json",
+            std::process::id(),
+            Local::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let mut history = OnlineHistory::default();
+        history.record_online("alice", lunes(20));
+
+        history.save_to_path(&path).unwrap();
+        let loaded = OnlineHistory::load_from_path(&path);
+
+        assert_eq!(loaded.heat_map("alice").unwrap()[20], 1);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,148 @@
+//! Runtime state of `watch` kept across restarts.
+//!
+//! Stored as `watch_state.json` next to `watched.toml`: cooldowns, models
+//! removed as invalid or skipped with `--ask`, and each model's poll cadence.
+//! Times are Unix seconds; entries not refreshed for a day are dropped.
+
+use super::app_config::{config_dir, ruta_temporal_para};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Entries untouched for longer than this are ignored on load.
+pub const VIGENCIA_ESTADO_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelRuntimeState {
+    pub updated_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_until: Option<i64>,
+    #[serde(default)]
+    pub invalid: bool,
+    #[serde(default)]
+    pub skipped: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_active: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_check: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchState {
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelRuntimeState>,
+}
+
+impl WatchState {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|d| d.join("watch_state.json"))
+    }
+
+    /// Loads the saved state without stale entries; a missing or unreadable
+    /// file yields an empty state.
+    pub fn load(ahora: i64) -> Self {
+        let mut estado = Self::path()
+            .map(|path| Self::load_from_path(&path))
+            .unwrap_or_default();
+        estado.purgar(ahora);
+        estado
+    }
+
+    fn load_from_path(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contenido| serde_json::from_str(&contenido).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "Sin directorio de config")
+        })?;
+        self.save_to_path(&path)
+    }
+
+    fn save_to_path(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contenido = serde_json::to_string(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let tmp_path = ruta_temporal_para(path);
+        fs::write(&tmp_path, contenido)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Drops entries older than `VIGENCIA_ESTADO_SECS` and cooldowns already over.
+    pub fn purgar(&mut self, ahora: i64) {
+        self.models
+            .retain(|_, modelo| ahora.saturating_sub(modelo.updated_at) <= VIGENCIA_ESTADO_SECS);
+        for modelo in self.models.values_mut() {
+            if modelo.blocked_until.is_some_and(|hasta| hasta <= ahora) {
+                modelo.blocked_until = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purgar_drops_stale_entries_and_finished_cooldowns() {
+        let ahora = 1_000_000;
+        let mut estado = WatchState::default();
+        estado.models.insert(
+            "alice".to_string(),
+            ModelRuntimeState {
+                updated_at: ahora - 60,
+                blocked_until: Some(ahora - 1),
+                skipped: true,
+                ..ModelRuntimeState::default()
+            },
+        );
+        estado.models.insert(
+            "bob".to_string(),
+            ModelRuntimeState {
+                updated_at: ahora - VIGENCIA_ESTADO_SECS - 1,
+                invalid: true,
+                ..ModelRuntimeState::default()
+            },
+        );
+
+        estado.purgar(ahora);
+
+        assert_eq!(estado.models.len(), 1);
+        let alice = &estado.models["alice"];
+        assert!(alice.skipped);
+        assert_eq!(alice.blocked_until, None);
+    }
+
+    #[test]
+    fn state_survives_save_and_load() {
+        let path = std::env::temp_dir().join(format!(
+            "cbrec_watch_state_{}_{}.json",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let mut estado = WatchState::default();
+        estado.models.insert(
+            "alice".to_string(),
+            ModelRuntimeState {
+                updated_at: 10,
+                next_check: Some(70),
+                interval_secs: Some(60),
+                ..ModelRuntimeState::default()
+            },
+        );
+
+        estado.save_to_path(&path).unwrap();
+
+        assert_eq!(WatchState::load_from_path(&path), estado);
+        let _ = fs::remove_file(path);
+    }
+}
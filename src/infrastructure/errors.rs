@@ -12,12 +12,112 @@ pub enum InfrastructureError {
     #[error("HTTP request failed with status: {0}")]
     HttpStatus(u16),
 
+    #[error("Room is not public: {0}")]
+    RoomRestricted(String),
+
+    #[error("API requests paused by the rate-limit circuit breaker for {0} s")]
+    CircuitOpen(u64),
+
     #[error("Recording error: {0}")]
     RecordingError(String),
 
+    #[error("Recording error: {0}")]
+    FfmpegStartup(String),
+
+    #[error("Recording error: el tiempo de video de FFmpeg no avanzo durante {0} segundos")]
+    StreamStalled(u64),
+
+    #[error(
+        "Recording error: espacio insuficiente: {available} bytes disponibles; se requieren {required}"
+    )]
+    LowDiskSpace { available: u64, required: u64 },
+
+    #[error("Recording error: {0}")]
+    PlaylistChanged(String),
+
+    #[error("Recording error: {0}")]
+    EdgeFailed(String),
+
+    #[error("No variant matches the selection policy: {0}")]
+    VariantUnavailable(String),
+
     #[error("Recording cancelled")]
     RecordingCancelled,
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
+
+/// Cause of a failed recording, used to pick its retry policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClaseFallo {
+    /// FFmpeg could not start or exited before writing anything.
+    Arranque,
+    /// The stream stopped delivering data.
+    Estancado,
+    DiscoLleno,
+    /// The playlist or its segments vanished (HTTP 403/404/410) or the CDN
+    /// edge serving them failed.
+    PlaylistCambiada,
+    Otro,
+}
+
+impl std::fmt::Display for ClaseFallo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClaseFallo::Arranque => write!(f, "startup"),
+            ClaseFallo::Estancado => write!(f, "stall"),
+            ClaseFallo::DiscoLleno => write!(f, "low_disk"),
+            ClaseFallo::PlaylistCambiada => write!(f, "playlist"),
+            ClaseFallo::Otro => write!(f, "other"),
+        }
+    }
+}
+
+impl InfrastructureError {
+    pub fn clase_fallo(&self) -> ClaseFallo {
+        match self {
+            Self::FfmpegStartup(_) => ClaseFallo::Arranque,
+            Self::StreamStalled(_) => ClaseFallo::Estancado,
+            Self::LowDiskSpace { .. } => ClaseFallo::DiscoLleno,
+            Self::Io(e) if e.kind() == std::io::ErrorKind::StorageFull => ClaseFallo::DiscoLleno,
+            Self::PlaylistChanged(_) | Self::EdgeFailed(_) => ClaseFallo::PlaylistCambiada,
+            _ => ClaseFallo::Otro,
+        }
+    }
+
+    /// Whether the stream URL expired or its edge failed, so resolving it
+    /// again may let the recording continue.
+    pub fn requiere_nueva_url(&self) -> bool {
+        matches!(self, Self::PlaylistChanged(_) | Self::EdgeFailed(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failure_classes_follow_error_variants() {
+        assert_eq!(
+            InfrastructureError::StreamStalled(120).clase_fallo(),
+            ClaseFallo::Estancado
+        );
+        assert_eq!(
+            InfrastructureError::LowDiskSpace {
+                available: 1,
+                required: 2
+            }
+            .clase_fallo(),
+            ClaseFallo::DiscoLleno
+        );
+        assert_eq!(
+            InfrastructureError::Io(std::io::ErrorKind::StorageFull.into()).clase_fallo(),
+            ClaseFallo::DiscoLleno
+        );
+        assert_eq!(
+            InfrastructureError::RecordingError("x".to_string()).clase_fallo(),
+            ClaseFallo::Otro
+        );
+    }
+}
@@ -1,25 +1,132 @@
 use crate::domain::repositories::StreamRepository;
-use crate::domain::value_objects::VideoQuality;
+use crate::domain::value_objects::{
+    CodecVideo, PoliticaVariante, ProgresoGrabacion, VarianteDisponible, VideoQuality,
+};
 use crate::domain::value_objects::{ModelName, StreamUrl};
-use crate::infrastructure::external::ffmpeg_process::run_ffmpeg;
-use crate::infrastructure::InfrastructureError;
+use crate::infrastructure::external::ffmpeg_process::{
+    combinar_senales, run_ffmpeg, ControlFfmpeg, DestinoFfmpeg, EntradaHttp, FuenteStream,
+};
+use crate::infrastructure::external::rate_governor::{
+    EstadoCircuito, Gobernador, LimitePeticiones,
+};
+use crate::infrastructure::{InfrastructureError, PerfilFfmpeg};
 use async_trait::async_trait;
 use quick_m3u8::config::ParsingOptionsBuilder;
 use quick_m3u8::tag::{hls, KnownTag};
 use quick_m3u8::{HlsLine, Reader};
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::watch;
 use tokio::time::{Duration, Instant};
 
-const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36";
+pub(crate) const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36";
 const HTTP_TIMEOUT_SECS: u64 = 10;
+const HTTP_CONNECT_TIMEOUT_SECS: u64 = 5;
+const HTTP_RETRY_MAX_RETRIES: u32 = 6;
 const HTTP_RETRY_BASE_MS: u64 = 200;
 const HTTP_RETRY_MAX_INTERVAL_SECS: u64 = 2;
 const HTTP_RETRY_MAX_ELAPSED_SECS: u64 = 10;
 
+/// IP family used for outgoing API connections.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PreferenciaIp {
+    #[default]
+    Cualquiera,
+    Ipv4,
+    Ipv6,
+}
+
+/// Bounds for retrying transient HTTP failures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoliticaReintentos {
+    pub max_reintentos: u32,
+    pub base: Duration,
+    pub max_intervalo: Duration,
+    pub presupuesto: Duration,
+}
+
+impl Default for PoliticaReintentos {
+    fn default() -> Self {
+        Self {
+            max_reintentos: HTTP_RETRY_MAX_RETRIES,
+            base: Duration::from_millis(HTTP_RETRY_BASE_MS),
+            max_intervalo: Duration::from_secs(HTTP_RETRY_MAX_INTERVAL_SECS),
+            presupuesto: Duration::from_secs(HTTP_RETRY_MAX_ELAPSED_SECS),
+        }
+    }
+}
+
+/// Settings shared by every HTTP client built for the API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpcionesHttp {
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    pub user_agent: String,
+    pub headers: Vec<(String, String)>,
+    pub ip: PreferenciaIp,
+    pub reintentos: PoliticaReintentos,
+    pub limite: LimitePeticiones,
+}
+
+impl Default for OpcionesHttp {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(HTTP_TIMEOUT_SECS),
+            connect_timeout: Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: Vec::new(),
+            ip: PreferenciaIp::Cualquiera,
+            reintentos: PoliticaReintentos::default(),
+            limite: LimitePeticiones::default(),
+        }
+    }
+}
+
+fn builder_http(opciones: &OpcionesHttp) -> Result<reqwest::ClientBuilder, InfrastructureError> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (nombre, valor) in &opciones.headers {
+        let nombre = reqwest::header::HeaderName::from_bytes(nombre.as_bytes()).map_err(|e| {
+            InfrastructureError::ExternalService(format!("Invalid header name {nombre}: {e}"))
+        })?;
+        let valor = reqwest::header::HeaderValue::from_str(valor).map_err(|e| {
+            InfrastructureError::ExternalService(format!("Invalid header value for {nombre}: {e}"))
+        })?;
+        headers.insert(nombre, valor);
+    }
+    let builder = Client::builder()
+        .user_agent(opciones.user_agent.as_str())
+        .timeout(opciones.timeout)
+        .connect_timeout(opciones.connect_timeout)
+        .default_headers(headers);
+    Ok(match opciones.ip {
+        PreferenciaIp::Cualquiera => builder,
+        PreferenciaIp::Ipv4 => builder.local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        PreferenciaIp::Ipv6 => builder.local_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+    })
+}
+
+fn construir_cliente_http(
+    proxy: Option<&str>,
+    opciones: &OpcionesHttp,
+) -> Result<Client, InfrastructureError> {
+    let builder = builder_http(opciones)?;
+    let builder =
+        match proxy {
+            Some(proxy) => builder.proxy(reqwest::Proxy::all(proxy).map_err(|e| {
+                InfrastructureError::ExternalService(format!("Invalid proxy: {}", e))
+            })?),
+            None => builder.no_proxy(),
+        };
+    builder.build().map_err(|e| {
+        InfrastructureError::ExternalService(format!("Failed to create HTTP client: {}", e))
+    })
+}
+
 enum RetryFailure<E> {
     Transient(E),
     Permanent(E),
@@ -32,26 +139,31 @@ fn retry_budget_exceeded(elapsed: Duration, delay: Duration, max_elapsed: Durati
 /// Retries transient HTTP failures with bounded deterministic exponential delays.
 ///
 /// ponytail: add jitter only if synchronized clients create measurable upstream load spikes.
-async fn retry_with_backoff<T, E, F, Fut>(mut operation: F) -> Result<T, E>
+async fn retry_with_backoff<T, E, F, Fut>(
+    politica: &PoliticaReintentos,
+    mut operation: F,
+) -> Result<T, E>
 where
     F: FnMut() -> Fut,
     Fut: Future<Output = Result<T, RetryFailure<E>>>,
 {
     let started = Instant::now();
-    let max_elapsed = Duration::from_secs(HTTP_RETRY_MAX_ELAPSED_SECS);
-    let max_interval = Duration::from_secs(HTTP_RETRY_MAX_INTERVAL_SECS);
-    let mut delay = Duration::from_millis(HTTP_RETRY_BASE_MS);
+    let mut delay = politica.base.min(politica.max_intervalo);
+    let mut reintentos = 0;
 
     loop {
         match operation().await {
             Ok(value) => return Ok(value),
             Err(RetryFailure::Permanent(error)) => return Err(error),
             Err(RetryFailure::Transient(error)) => {
-                if retry_budget_exceeded(started.elapsed(), delay, max_elapsed) {
+                if reintentos >= politica.max_reintentos
+                    || retry_budget_exceeded(started.elapsed(), delay, politica.presupuesto)
+                {
                     return Err(error);
                 }
+                reintentos += 1;
                 tokio::time::sleep(delay).await;
-                delay = delay.saturating_mul(2).min(max_interval);
+                delay = delay.saturating_mul(2).min(politica.max_intervalo);
             }
         }
     }
@@ -61,6 +173,7 @@ where
 struct ChatVideoContext {
     hls_source: Option<String>,
     room_status: Option<String>,
+    room_title: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -78,6 +191,12 @@ struct RoomListEntry {
     current_show: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct MoreLikeResponse {
+    #[serde(default, alias = "recommendations")]
+    rooms: Vec<RoomListEntry>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub(crate) struct DiscoveredRoom {
     pub(crate) username: String,
@@ -96,38 +215,204 @@ pub enum EstadoStream {
     RespuestaInesperada { detalle: String },
 }
 
+/// HTTP client bound to one per-model proxy override.
+#[derive(Clone)]
+struct ClienteConProxy {
+    client: Client,
+    proxy: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct ChaturbateClient {
     client: Client,
+    opciones_http: Arc<OpcionesHttp>,
+    /// Shared by every clone, including per-model proxy clients.
+    gobernador: Arc<Gobernador>,
+    proxy: Option<String>,
+    proxies_por_modelo: Arc<HashMap<String, ClienteConProxy>>,
     base_url: String,
     ffmpeg_path: Option<PathBuf>,
+    perfil_ffmpeg: Arc<PerfilFfmpeg>,
+    perfiles_por_modelo: Arc<HashMap<String, Arc<PerfilFfmpeg>>>,
     cancel_rx: Option<watch::Receiver<bool>>,
+    /// Stops only this client's recording, e.g. when the show turns private.
+    stop_rx: Option<watch::Receiver<bool>>,
+    /// Receives FFmpeg's progress reports for this client's recordings.
+    progreso: Option<Arc<watch::Sender<ProgresoGrabacion>>>,
     session_cookie: Option<String>,
     max_duration_secs: Option<u64>,
     min_free_space: u64,
+    /// Bitrate, codec, frame-rate and strictness preferences; the quality
+    /// comes from each `download_stream` call.
+    politica_variante: PoliticaVariante,
+    /// Start at the oldest segment the live playlist still lists.
+    inicio_ventana: bool,
 }
 
 impl ChaturbateClient {
     pub fn new() -> Result<Self, InfrastructureError> {
-        let client = Client::builder()
-            .user_agent(DEFAULT_USER_AGENT)
-            .timeout(std::time::Duration::from_secs(HTTP_TIMEOUT_SECS))
-            .build()
-            .map_err(|e| {
-                InfrastructureError::ExternalService(format!("Failed to create HTTP client: {}", e))
-            })?;
+        let opciones_http = OpcionesHttp::default();
+        let client = builder_http(&opciones_http)?.build().map_err(|e| {
+            InfrastructureError::ExternalService(format!("Failed to create HTTP client: {}", e))
+        })?;
 
         Ok(Self {
             client,
+            gobernador: Arc::new(Gobernador::new(opciones_http.limite)),
+            opciones_http: Arc::new(opciones_http),
+            proxy: None,
+            proxies_por_modelo: Arc::default(),
             base_url: "https://chaturbate.com".to_string(),
             ffmpeg_path: None,
+            perfil_ffmpeg: Arc::default(),
+            perfiles_por_modelo: Arc::default(),
             cancel_rx: None,
+            stop_rx: None,
+            progreso: None,
             session_cookie: None,
             max_duration_secs: None,
             min_free_space: 0,
+            politica_variante: VideoQuality::Best.into(),
+            inicio_ventana: false,
         })
     }
 
+    /// Replaces timeouts, headers, IP preference and retry policy. Call it
+    /// before `with_proxy`/`with_model_proxies`, which reuse these options.
+    pub fn with_http_options(
+        mut self,
+        opciones: OpcionesHttp,
+    ) -> Result<Self, InfrastructureError> {
+        self.client = builder_http(&opciones)?.build().map_err(|e| {
+            InfrastructureError::ExternalService(format!("Failed to create HTTP client: {}", e))
+        })?;
+        self.gobernador = Arc::new(Gobernador::new(opciones.limite));
+        self.opciones_http = Arc::new(opciones);
+        Ok(self)
+    }
+
+    /// Routes API requests and FFmpeg input through `proxy`, or connects
+    /// directly when it is `None`, ignoring proxy environment variables.
+    pub fn with_proxy(mut self, proxy: Option<String>) -> Result<Self, InfrastructureError> {
+        self.client = construir_cliente_http(proxy.as_deref(), &self.opciones_http)?;
+        self.proxy = proxy;
+        Ok(self)
+    }
+
+    /// Registers per-model proxy overrides; `None` forces a direct connection.
+    pub fn with_model_proxies(
+        mut self,
+        proxies: &HashMap<String, Option<String>>,
+    ) -> Result<Self, InfrastructureError> {
+        let mut clientes = HashMap::with_capacity(proxies.len());
+        for (modelo, proxy) in proxies {
+            clientes.insert(
+                modelo.to_ascii_lowercase(),
+                ClienteConProxy {
+                    client: construir_cliente_http(proxy.as_deref(), &self.opciones_http)?,
+                    proxy: proxy.clone(),
+                },
+            );
+        }
+        self.proxies_por_modelo = Arc::new(clientes);
+        Ok(self)
+    }
+
+    /// Returns the client to use for `model`, honoring its proxy and FFmpeg
+    /// profile overrides.
+    pub fn for_model(&self, model: &str) -> Self {
+        let mut client = self.clone();
+        let model = model.to_ascii_lowercase();
+        if let Some(override_) = self.proxies_por_modelo.get(&model) {
+            client.client = override_.client.clone();
+            client.proxy = override_.proxy.clone();
+        }
+        if let Some(perfil) = self.perfiles_por_modelo.get(&model) {
+            client.perfil_ffmpeg = Arc::clone(perfil);
+        }
+        client
+    }
+
+    /// Extra FFmpeg options for every recording of this client.
+    pub fn with_perfil_ffmpeg(mut self, perfil: PerfilFfmpeg) -> Self {
+        self.perfil_ffmpeg = Arc::new(perfil);
+        self
+    }
+
+    /// FFmpeg profiles that replace the client's one for some models.
+    pub fn with_model_perfiles_ffmpeg(mut self, perfiles: HashMap<String, PerfilFfmpeg>) -> Self {
+        self.perfiles_por_modelo = Arc::new(
+            perfiles
+                .into_iter()
+                .map(|(modelo, perfil)| (modelo.to_ascii_lowercase(), Arc::new(perfil)))
+                .collect(),
+        );
+        self
+    }
+
+    pub fn has_model_proxy(&self, model: &str) -> bool {
+        self.proxies_por_modelo
+            .contains_key(&model.to_ascii_lowercase())
+    }
+
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// Sends one request to the site and returns its HTTP status, proving
+    /// that the configured route (direct or proxied) reaches it.
+    pub async fn probar_conexion(&self) -> Result<u16, InfrastructureError> {
+        let response = self
+            .enviar(self.client.get(format!("{}/robots.txt", self.base_url)))
+            .await
+            .map_err(|(RetryFailure::Transient(e) | RetryFailure::Permanent(e))| e)?;
+        if response.status() != StatusCode::TOO_MANY_REQUESTS {
+            self.registrar_respuesta(false);
+        }
+        Ok(response.status().as_u16())
+    }
+
+    pub fn estado_circuito(&self) -> EstadoCircuito {
+        self.gobernador.estado(std::time::Instant::now())
+    }
+
+    pub fn limite_peticiones(&self) -> LimitePeticiones {
+        self.gobernador.limite()
+    }
+
+    /// Sends one API request through the shared governor. 429 responses are
+    /// recorded here; callers record the rest once they know whether the
+    /// body is a block page.
+    async fn enviar(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, RetryFailure<InfrastructureError>> {
+        self.gobernador.adquirir().await.map_err(|restante| {
+            RetryFailure::Permanent(InfrastructureError::CircuitOpen(restante.as_secs().max(1)))
+        })?;
+        let response = request.send().await.map_err(|e| {
+            self.gobernador.liberar_sonda();
+            RetryFailure::Transient(InfrastructureError::ExternalService(format!(
+                "HTTP request failed: {}",
+                e
+            )))
+        })?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            self.registrar_respuesta(true);
+        } else if response.status().is_server_error() {
+            self.gobernador.liberar_sonda();
+        }
+        Ok(response)
+    }
+
+    fn registrar_respuesta(&self, limitado: bool) {
+        if limitado {
+            self.gobernador.registrar_limite(std::time::Instant::now());
+        } else {
+            self.gobernador.registrar_exito();
+        }
+    }
+
     pub fn with_ffmpeg_path(mut self, path: PathBuf) -> Self {
         self.ffmpeg_path = Some(path);
         self
@@ -138,6 +423,23 @@ impl ChaturbateClient {
         self
     }
 
+    pub fn cancel_receiver(&self) -> Option<watch::Receiver<bool>> {
+        self.cancel_rx.clone()
+    }
+
+    /// Adds a signal that stops recordings made by this client without
+    /// cancelling the rest.
+    pub fn with_stop_receiver(mut self, stop_rx: watch::Receiver<bool>) -> Self {
+        self.stop_rx = Some(stop_rx);
+        self
+    }
+
+    /// Publishes the progress of recordings made by this client.
+    pub fn with_progreso(mut self, progreso: Arc<watch::Sender<ProgresoGrabacion>>) -> Self {
+        self.progreso = Some(progreso);
+        self
+    }
+
     pub fn with_session_cookie(mut self, cookie: String) -> Self {
         self.session_cookie = Some(cookie);
         self
@@ -153,6 +455,20 @@ impl ChaturbateClient {
         self
     }
 
+    pub fn with_politica_variante(mut self, politica: PoliticaVariante) -> Self {
+        self.politica_variante = politica;
+        self
+    }
+
+    pub fn with_inicio_ventana(mut self, desde_inicio: bool) -> Self {
+        self.inicio_ventana = desde_inicio;
+        self
+    }
+
+    pub fn inicio_ventana(&self) -> bool {
+        self.inicio_ventana
+    }
+
     fn get_request(&self, url: &str) -> reqwest::RequestBuilder {
         let mut req = self.client.get(url);
         if let Some(cookie) = &self.session_cookie {
@@ -171,17 +487,13 @@ impl ChaturbateClient {
             model_name.as_str()
         );
 
-        retry_with_backoff(|| async {
-            let response = self.get_request(&url).send().await.map_err(|e| {
-                RetryFailure::Transient(InfrastructureError::ExternalService(format!(
-                    "HTTP request failed: {}",
-                    e
-                )))
-            })?;
+        retry_with_backoff(&self.opciones_http.reintentos, || async {
+            let response = self.enviar(self.get_request(&url)).await?;
 
             match clasificar_status_http(response.status()) {
                 EstadoHttp::Ok => {}
                 EstadoHttp::NoEncontrado => {
+                    self.registrar_respuesta(false);
                     return Err(RetryFailure::Permanent(InfrastructureError::Domain(
                         crate::domain::errors::DomainError::ModelNotFound(
                             model_name.as_str().to_string(),
@@ -190,6 +502,7 @@ impl ChaturbateClient {
                 }
                 EstadoHttp::RateLimited => return Ok(EstadoStream::RateLimited),
                 EstadoHttp::RequiereSesion => {
+                    self.registrar_respuesta(false);
                     return Ok(EstadoStream::RequiereSesion {
                         detalle: format!("HTTP {}", response.status()),
                     });
@@ -200,6 +513,7 @@ impl ChaturbateClient {
                     )));
                 }
                 EstadoHttp::Permanente => {
+                    self.registrar_respuesta(true);
                     return Ok(EstadoStream::Bloqueado {
                         detalle: format!("HTTP {}", response.status()),
                     });
@@ -213,74 +527,125 @@ impl ChaturbateClient {
                 )))
             })?;
 
-            Ok(clasificar_chat_video_context(&contenido))
+            let estado = clasificar_chat_video_context(&contenido);
+            self.registrar_respuesta(matches!(estado, EstadoStream::Bloqueado { .. }));
+            Ok(estado)
         })
         .await
     }
 
+    /// Current room subject, or `None` when the room does not publish one.
+    pub async fn titulo_sala(
+        &self,
+        model_name: &ModelName,
+    ) -> Result<Option<String>, InfrastructureError> {
+        let url = format!(
+            "{}/api/chatvideocontext/{}/",
+            self.base_url,
+            model_name.as_str()
+        );
+        let response = self
+            .enviar(self.get_request(&url))
+            .await
+            .map_err(|(RetryFailure::Transient(e) | RetryFailure::Permanent(e))| e)?;
+        if !response.status().is_success() {
+            return Err(error_status_http(response.status()));
+        }
+        self.registrar_respuesta(false);
+        let contenido = response.text().await.map_err(|e| {
+            InfrastructureError::ExternalService(format!("Failed to read response: {}", e))
+        })?;
+        let context: ChatVideoContext = serde_json::from_str(&contenido).map_err(|e| {
+            InfrastructureError::ExternalService(format!("respuesta no JSON: {}", e))
+        })?;
+        Ok(context
+            .room_title
+            .filter(|titulo| !titulo.trim().is_empty()))
+    }
+
     pub(crate) async fn discover_rooms_by_tag(
         &self,
         tag: &str,
         limit: usize,
+        offset: usize,
     ) -> Result<Vec<DiscoveredRoom>, InfrastructureError> {
         let url = format!("{}/api/ts/roomlist/room-list/", self.base_url);
+        let mut query = vec![("hashtags", tag.to_string()), ("limit", limit.to_string())];
+        if offset > 0 {
+            query.push(("offset", offset.to_string()));
+        }
+        let content = self.obtener_json_publico(&url, &query).await?;
+        let response: RoomListResponse = serde_json::from_str(&content).map_err(|error| {
+            InfrastructureError::ExternalService(format!("Invalid room list response: {error}"))
+        })?;
 
-        retry_with_backoff(|| async {
-            let response = self
-                .client
-                .get(&url)
-                .query(&[("hashtags", tag), ("limit", &limit.to_string())])
-                .send()
-                .await
-                .map_err(|error| {
-                    RetryFailure::Transient(InfrastructureError::ExternalService(format!(
-                        "HTTP request failed: {error}"
-                    )))
-                })?;
+        Ok(response
+            .rooms
+            .into_iter()
+            .filter(|room| {
+                room.tags
+                    .as_deref()
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|value| value.eq_ignore_ascii_case(tag))
+            })
+            .filter_map(sala_descubierta)
+            .take(limit)
+            .collect())
+    }
+
+    /// Returns public rooms that the site recommends as similar to `model_name`.
+    pub(crate) async fn similar_rooms(
+        &self,
+        model_name: &ModelName,
+        limit: usize,
+    ) -> Result<Vec<DiscoveredRoom>, InfrastructureError> {
+        let url = format!("{}/api/more_like/{}/", self.base_url, model_name.as_str());
+        let content = self.obtener_json_publico(&url, &[]).await?;
+        let response: MoreLikeResponse = serde_json::from_str(&content).map_err(|error| {
+            InfrastructureError::ExternalService(format!("Invalid more_like response: {error}"))
+        })?;
+
+        let mut vistos = std::collections::HashSet::new();
+        Ok(response
+            .rooms
+            .into_iter()
+            .filter_map(sala_descubierta)
+            .filter(|room| room.username != model_name.as_str())
+            .filter(|room| vistos.insert(room.username.clone()))
+            .take(limit)
+            .collect())
+    }
+
+    /// Fetches a read-only public endpoint without the session cookie.
+    async fn obtener_json_publico(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+    ) -> Result<String, InfrastructureError> {
+        retry_with_backoff(&self.opciones_http.reintentos, || async {
+            let response = self.enviar(self.client.get(url).query(query)).await?;
 
             match clasificar_status_http(response.status()) {
-                EstadoHttp::Ok => {}
+                EstadoHttp::Ok => self.registrar_respuesta(false),
                 EstadoHttp::RateLimited | EstadoHttp::Reintentable => {
                     return Err(RetryFailure::Transient(error_status_http(
                         response.status(),
                     )));
                 }
                 EstadoHttp::NoEncontrado | EstadoHttp::RequiereSesion | EstadoHttp::Permanente => {
+                    self.registrar_respuesta(false);
                     return Err(RetryFailure::Permanent(error_status_http(
                         response.status(),
                     )));
                 }
             }
 
-            let content = response.text().await.map_err(|error| {
+            response.text().await.map_err(|error| {
                 RetryFailure::Permanent(InfrastructureError::ExternalService(format!(
                     "Failed to read response: {error}"
                 )))
-            })?;
-            let response: RoomListResponse = serde_json::from_str(&content).map_err(|error| {
-                RetryFailure::Permanent(InfrastructureError::ExternalService(format!(
-                    "Invalid room list response: {error}"
-                )))
-            })?;
-
-            Ok(response
-                .rooms
-                .into_iter()
-                .filter_map(|room| {
-                    let tags = room.tags.unwrap_or_default();
-                    if !tags.iter().any(|value| value.eq_ignore_ascii_case(tag)) {
-                        return None;
-                    }
-                    let username = ModelName::try_from(room.username?).ok()?;
-                    Some(DiscoveredRoom {
-                        username: username.as_str().to_string(),
-                        subject: room.room_subject.unwrap_or_default(),
-                        viewers: room.num_users.unwrap_or_default(),
-                        show: room.current_show.unwrap_or_else(|| "unknown".to_string()),
-                    })
-                })
-                .take(limit)
-                .collect())
+            })
         })
         .await
     }
@@ -298,9 +663,9 @@ impl StreamRepository for ChaturbateClient {
             EstadoStream::Online { stream_url } => Ok(Some(stream_url)),
             EstadoStream::Offline => Ok(None),
             EstadoStream::RateLimited => Err(InfrastructureError::HttpStatus(429)),
-            EstadoStream::RequiereSesion { detalle } => Err(InfrastructureError::ExternalService(
-                format!("stream requiere sesion o acceso privado: {detalle}"),
-            )),
+            EstadoStream::RequiereSesion { detalle } => {
+                Err(InfrastructureError::RoomRestricted(detalle))
+            }
             EstadoStream::Bloqueado { detalle } => Err(InfrastructureError::ExternalService(
                 format!("respuesta bloqueada por Chaturbate: {detalle}"),
             )),
@@ -318,57 +683,173 @@ impl StreamRepository for ChaturbateClient {
         output_path: &Path,
         quality: VideoQuality,
     ) -> Result<(), InfrastructureError> {
-        let stream_url = match self.resolver_variant_url(stream_url, quality).await {
-            Ok(url) => url,
-            Err(_) => stream_url.clone(),
+        self.grabar_stream(stream_url, output_path.into(), quality)
+            .await
+    }
+}
+
+impl ChaturbateClient {
+    /// Records into a rolling HLS window that keeps about `ventana_secs` of
+    /// stream in `playlist`'s directory until the show ends.
+    pub async fn grabar_ventana(
+        &self,
+        stream_url: &StreamUrl,
+        playlist: &Path,
+        quality: VideoQuality,
+        ventana_secs: u64,
+        segundos_segmento: u64,
+    ) -> Result<(), InfrastructureError> {
+        let destino = DestinoFfmpeg::Ventana {
+            playlist,
+            segundos_segmento,
+            segmentos: ventana_secs.div_ceil(segundos_segmento.max(1)).max(1),
         };
+        self.grabar_stream(stream_url, destino, quality).await
+    }
 
-        let ffmpeg_path = self
-            .ffmpeg_path
-            .as_deref()
-            .unwrap_or_else(|| Path::new("ffmpeg"));
+    /// Copies a local rolling window, from its oldest segment on, plus
+    /// whatever arrives during the rest of `duracion_secs` into `output_path`.
+    pub async fn extraer_clip(
+        &self,
+        playlist: &Path,
+        output_path: &Path,
+        duracion_secs: u64,
+    ) -> Result<(), InfrastructureError> {
+        let playlist = playlist.to_string_lossy();
         run_ffmpeg(
-            ffmpeg_path,
-            stream_url.as_str(),
-            output_path,
-            self.session_cookie.as_deref(),
-            self.max_duration_secs,
-            self.min_free_space,
-            self.cancel_rx.clone(),
+            self.ruta_ffmpeg(),
+            FuenteStream {
+                video: &playlist,
+                audio: None,
+                desde_inicio: true,
+            },
+            output_path.into(),
+            EntradaHttp::default(),
+            // La entrada es un archivo local: las opciones HTTP no aplican.
+            &PerfilFfmpeg {
+                input: Vec::new(),
+                output: self.perfil_ffmpeg.output.clone(),
+            },
+            ControlFfmpeg {
+                max_duration_secs: Some(duracion_secs),
+                min_free_space: self.min_free_space,
+                cancel_rx: self.cancel_rx.clone(),
+                progreso: self.progreso.clone(),
+            },
         )
         .await
     }
-}
 
-impl ChaturbateClient {
+    async fn grabar_stream(
+        &self,
+        stream_url: &StreamUrl,
+        destino: DestinoFfmpeg<'_>,
+        quality: VideoQuality,
+    ) -> Result<(), InfrastructureError> {
+        let resuelta = match self.resolver_variant_url(stream_url, quality).await {
+            Ok(resuelta) => resuelta,
+            Err(e) if self.politica_variante.estricta => return Err(e),
+            Err(_) => VarianteResuelta {
+                video: stream_url.clone(),
+                audio: None,
+            },
+        };
+        // Solo audio: si la playlist publica el audio aparte, no hace falta bajar video.
+        let resuelta = match resuelta {
+            VarianteResuelta {
+                audio: Some(audio), ..
+            } if quality == VideoQuality::AudioOnly => VarianteResuelta {
+                video: audio,
+                audio: None,
+            },
+            resuelta => resuelta,
+        };
+
+        run_ffmpeg(
+            self.ruta_ffmpeg(),
+            FuenteStream {
+                video: resuelta.video.as_str(),
+                audio: resuelta.audio.as_ref().map(StreamUrl::as_str),
+                desde_inicio: self.inicio_ventana,
+            },
+            destino,
+            EntradaHttp {
+                session_cookie: self.session_cookie.as_deref(),
+                proxy: self.proxy.as_deref(),
+            },
+            &self.perfil_ffmpeg,
+            ControlFfmpeg {
+                max_duration_secs: self.max_duration_secs,
+                min_free_space: self.min_free_space,
+                cancel_rx: combinar_senales(self.cancel_rx.clone(), self.stop_rx.clone()),
+                progreso: self.progreso.clone(),
+            },
+        )
+        .await
+    }
+
+    fn ruta_ffmpeg(&self) -> &Path {
+        self.ffmpeg_path
+            .as_deref()
+            .unwrap_or_else(|| Path::new("ffmpeg"))
+    }
+
+    /// Lists every variant, marking the one `quality` and the client's
+    /// selection policy would record.
     pub async fn listar_calidades(
         &self,
         master_url: &StreamUrl,
-    ) -> Result<Vec<CalidadDisponible>, InfrastructureError> {
+        quality: VideoQuality,
+    ) -> Result<Vec<VarianteDisponible>, InfrastructureError> {
         let variantes = self.obtener_variantes(master_url).await?;
-        let mut calidades: Vec<CalidadDisponible> = variantes
+        let politica = self.politica_variante.con_calidad(quality);
+        let elegida = seleccionar_variante(&variantes, &politica).map(|v| v.url.clone());
+        let mut calidades: Vec<VarianteDisponible> = variantes
             .into_iter()
-            .map(|v| CalidadDisponible {
+            .map(|v| VarianteDisponible {
+                seleccionada: elegida.as_deref() == Some(v.url.as_str()),
                 height: v.height,
                 bandwidth: v.bandwidth,
+                codecs: v.codecs,
+                frame_rate: v.frame_rate,
+                audio: v.audio.map(|rendition| rendition.nombre),
             })
             .collect();
         calidades.sort_by_key(|c| (c.height.unwrap_or(0), c.bandwidth.unwrap_or(0)));
         Ok(calidades)
     }
 
+    /// Seconds the oldest listed segment recovers compared with FFmpeg's
+    /// default start near the live edge.
+    pub async fn segundos_recuperables(
+        &self,
+        stream_url: &StreamUrl,
+        quality: VideoQuality,
+    ) -> Result<f64, InfrastructureError> {
+        let resuelta = self.resolver_variant_url(stream_url, quality).await?;
+        let contenido = self.obtener_playlist(resuelta.video.as_str()).await?;
+        parsear_ventana_recuperable(&contenido)
+    }
+
     async fn resolver_variant_url(
         &self,
         master_url: &StreamUrl,
         quality: VideoQuality,
-    ) -> Result<StreamUrl, InfrastructureError> {
+    ) -> Result<VarianteResuelta, InfrastructureError> {
         let variantes = self.obtener_variantes(master_url).await?;
 
         if variantes.is_empty() {
-            return Ok(master_url.clone());
+            return Ok(VarianteResuelta {
+                video: master_url.clone(),
+                audio: None,
+            });
         }
 
-        resolver_url_variante(master_url, &variantes, quality)
+        resolver_url_variante(
+            master_url,
+            &variantes,
+            &self.politica_variante.con_calidad(quality),
+        )
     }
 
     async fn obtener_variantes(
@@ -380,22 +861,18 @@ impl ChaturbateClient {
     }
 
     async fn obtener_playlist(&self, url: &str) -> Result<String, InfrastructureError> {
-        retry_with_backoff(|| async {
-            let response = self.get_request(url).send().await.map_err(|e| {
-                RetryFailure::Transient(InfrastructureError::ExternalService(format!(
-                    "HTTP request failed: {}",
-                    e
-                )))
-            })?;
+        retry_with_backoff(&self.opciones_http.reintentos, || async {
+            let response = self.enviar(self.get_request(url)).await?;
 
             match clasificar_status_http(response.status()) {
-                EstadoHttp::Ok => {}
+                EstadoHttp::Ok => self.registrar_respuesta(false),
                 EstadoHttp::RateLimited => {
                     return Err(RetryFailure::Transient(InfrastructureError::HttpStatus(
                         429,
                     )));
                 }
                 EstadoHttp::RequiereSesion => {
+                    self.registrar_respuesta(false);
                     return Err(RetryFailure::Permanent(error_status_http(
                         response.status(),
                     )));
@@ -406,6 +883,7 @@ impl ChaturbateClient {
                     )));
                 }
                 EstadoHttp::NoEncontrado | EstadoHttp::Permanente => {
+                    self.registrar_respuesta(false);
                     return Err(RetryFailure::Permanent(error_status_http(
                         response.status(),
                     )));
@@ -423,17 +901,41 @@ impl ChaturbateClient {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 struct VarianteStream {
     url: String,
     bandwidth: Option<u64>,
     height: Option<u32>,
+    codecs: Option<String>,
+    frame_rate: Option<f64>,
+    /// Separate audio rendition from the variant's `AUDIO` group, if any.
+    audio: Option<RenditionAudio>,
+}
+
+/// `EXT-X-MEDIA` audio rendition that carries its own playlist.
+#[derive(Debug, Clone, PartialEq)]
+struct RenditionAudio {
+    nombre: String,
+    url: String,
 }
 
-#[derive(Clone, Debug)]
-pub struct CalidadDisponible {
-    pub height: Option<u32>,
-    pub bandwidth: Option<u64>,
+/// Variant URLs handed to FFmpeg, resolved against the master playlist.
+#[derive(Debug, Clone, PartialEq)]
+struct VarianteResuelta {
+    video: StreamUrl,
+    audio: Option<StreamUrl>,
+}
+
+impl VarianteStream {
+    fn codec_video(&self) -> Option<CodecVideo> {
+        self.codecs.as_deref().and_then(CodecVideo::desde_codecs)
+    }
+
+    fn fps_redondeados(&self) -> Option<u32> {
+        self.frame_rate
+            .filter(|fps| fps.is_finite() && *fps > 0.0)
+            .map(|fps| fps.round() as u32)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -465,6 +967,16 @@ fn clasificar_status_http(status: StatusCode) -> EstadoHttp {
     EstadoHttp::Permanente
 }
 
+fn sala_descubierta(room: RoomListEntry) -> Option<DiscoveredRoom> {
+    let username = ModelName::try_from(room.username?).ok()?;
+    Some(DiscoveredRoom {
+        username: username.as_str().to_string(),
+        subject: room.room_subject.unwrap_or_default(),
+        viewers: room.num_users.unwrap_or_default(),
+        show: room.current_show.unwrap_or_else(|| "unknown".to_string()),
+    })
+}
+
 fn error_status_http(status: StatusCode) -> InfrastructureError {
     InfrastructureError::HttpStatus(status.as_u16())
 }
@@ -502,6 +1014,7 @@ fn clasificar_chat_video_context(contenido: &str) -> EstadoStream {
         Some(status) if status == "offline" => EstadoStream::Offline,
         Some(status)
             if status.contains("private")
+                || status.contains("away")
                 || status.contains("fan")
                 || status.contains("password")
                 || status.contains("hidden") =>
@@ -533,10 +1046,13 @@ fn parsear_variantes(contenido: &str) -> Result<Vec<VarianteStream>, Infrastruct
 
     let opciones = ParsingOptionsBuilder::new()
         .with_parsing_for_stream_inf()
+        .with_parsing_for_media()
         .build();
     let mut reader = Reader::from_str(contenido, opciones);
     let mut variantes = Vec::new();
-    let mut pendiente: Option<VarianteStream> = None;
+    let mut grupos_audio: Vec<Option<String>> = Vec::new();
+    let mut renditions = Vec::new();
+    let mut pendiente: Option<(VarianteStream, Option<String>)> = None;
 
     loop {
         match reader.read_line() {
@@ -545,18 +1061,33 @@ fn parsear_variantes(contenido: &str) -> Result<Vec<VarianteStream>, Infrastruct
                     let height = tag
                         .resolution()
                         .and_then(|res| u32::try_from(res.height).ok());
-                    pendiente = Some(VarianteStream {
+                    let variante = VarianteStream {
                         url: String::new(),
                         bandwidth: Some(tag.bandwidth()),
                         height,
+                        codecs: tag.codecs().map(str::to_string),
+                        frame_rate: tag.frame_rate(),
+                        audio: None,
+                    };
+                    pendiente = Some((variante, tag.audio().map(str::to_string)));
+                }
+                HlsLine::KnownTag(KnownTag::Hls(hls::Tag::Media(tag)))
+                    if tag.media_type().to_string().eq_ignore_ascii_case("AUDIO") =>
+                {
+                    renditions.push(MediaAudio {
+                        grupo: tag.group_id().to_string(),
+                        nombre: tag.name().to_string(),
+                        url: tag.uri().map(|uri| uri.trim().to_string()),
+                        prioridad: u8::from(tag.default()) * 2 + u8::from(tag.autoselect()),
                     });
                 }
                 HlsLine::Uri(uri) => {
-                    if let Some(mut variante) = pendiente.take() {
+                    if let Some((mut variante, grupo)) = pendiente.take() {
                         let url = uri.trim();
                         if !url.is_empty() {
                             variante.url = url.to_string();
                             variantes.push(variante);
+                            grupos_audio.push(grupo);
                         }
                     }
                 }
@@ -572,39 +1103,172 @@ fn parsear_variantes(contenido: &str) -> Result<Vec<VarianteStream>, Infrastruct
         }
     }
 
+    // EXT-X-MEDIA may appear after the variants that reference its group.
+    for (variante, grupo) in variantes.iter_mut().zip(grupos_audio) {
+        variante.audio = grupo.and_then(|grupo| rendition_de_grupo(&renditions, &grupo));
+    }
+
     Ok(variantes)
 }
 
-fn seleccionar_variante(
-    variantes: &[VarianteStream],
-    quality: VideoQuality,
-) -> Option<&VarianteStream> {
+struct MediaAudio {
+    grupo: String,
+    nombre: String,
+    url: Option<String>,
+    prioridad: u8,
+}
+
+/// Picks the DEFAULT (then AUTOSELECT, then first) rendition of a group. A
+/// rendition without URI is muxed into the variant, so nothing is added.
+fn rendition_de_grupo(renditions: &[MediaAudio], grupo: &str) -> Option<RenditionAudio> {
+    let mut elegida: Option<&MediaAudio> = None;
+    for rendition in renditions.iter().filter(|r| r.grupo == grupo) {
+        if elegida.is_none_or(|actual| rendition.prioridad > actual.prioridad) {
+            elegida = Some(rendition);
+        }
+    }
+    let elegida = elegida?;
+    let url = elegida.url.as_ref().filter(|url| !url.is_empty())?;
+    Some(RenditionAudio {
+        nombre: elegida.nombre.clone(),
+        url: url.clone(),
+    })
+}
+
+/// Segments FFmpeg skips by default when joining a live HLS playlist.
+const SEGMENTOS_BORDE_VIVO: usize = 3;
+
+/// Duration of a media playlist's segments older than the live edge.
+fn parsear_ventana_recuperable(contenido: &str) -> Result<f64, InfrastructureError> {
+    let duraciones = duraciones_segmentos(contenido)?;
+    let antiguos = duraciones.len().saturating_sub(SEGMENTOS_BORDE_VIVO);
+    Ok(duraciones[..antiguos].iter().sum())
+}
+
+/// Total duration of the segments a media playlist lists.
+pub(crate) fn segundos_en_playlist(contenido: &str) -> Result<f64, InfrastructureError> {
+    Ok(duraciones_segmentos(contenido)?.iter().sum())
+}
+
+fn duraciones_segmentos(contenido: &str) -> Result<Vec<f64>, InfrastructureError> {
+    if !contenido.trim_start().starts_with("#EXTM3U") {
+        return Err(InfrastructureError::ExternalService(
+            "Invalid playlist: missing #EXTM3U header".to_string(),
+        ));
+    }
+
+    let opciones = ParsingOptionsBuilder::new().with_parsing_for_inf().build();
+    let mut reader = Reader::from_str(contenido, opciones);
+    let mut duraciones = Vec::new();
+    loop {
+        match reader.read_line() {
+            Ok(Some(HlsLine::KnownTag(KnownTag::Hls(hls::Tag::Inf(tag))))) => {
+                duraciones.push(tag.duration());
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => break,
+            Err(err) => {
+                return Err(InfrastructureError::ExternalService(format!(
+                    "Invalid playlist: {}",
+                    err
+                )))
+            }
+        }
+    }
+    Ok(duraciones)
+}
+
+fn seleccionar_variante<'a>(
+    variantes: &'a [VarianteStream],
+    politica: &PoliticaVariante,
+) -> Option<&'a VarianteStream> {
     if variantes.is_empty() {
         return None;
     }
+    let estricta = politica.estricta;
+    let mut candidatas: Vec<&VarianteStream> = variantes.iter().collect();
 
+    if let Some(maximo) = politica.max_bitrate {
+        let dentro: Vec<&VarianteStream> = candidatas
+            .iter()
+            .copied()
+            .filter(|v| v.bandwidth.is_none_or(|bw| bw <= maximo))
+            .collect();
+        candidatas = match (dentro.is_empty(), estricta) {
+            (false, _) => dentro,
+            (true, true) => return None,
+            // Ninguna cabe: la de menor bitrate es la mas cercana al limite.
+            (true, false) => candidatas
+                .into_iter()
+                .min_by_key(|v| v.bandwidth.unwrap_or(0))
+                .into_iter()
+                .collect(),
+        };
+    }
+
+    if politica.calidad != VideoQuality::AudioOnly {
+        if let Some(codec) = politica.codec {
+            candidatas = preferir(candidatas, estricta, |v| v.codec_video() == Some(codec))?;
+        }
+        if let Some(fps) = politica.fps {
+            candidatas = preferir(candidatas, estricta, |v| v.fps_redondeados() == Some(fps))?;
+        }
+    }
+
+    seleccionar_por_altura(&candidatas, politica.calidad, estricta)
+}
+
+/// Keeps the candidates meeting a preference; without matches the preference
+/// is ignored, or the selection fails in strict mode.
+fn preferir(
+    candidatas: Vec<&VarianteStream>,
+    estricta: bool,
+    cumple: impl Fn(&VarianteStream) -> bool,
+) -> Option<Vec<&VarianteStream>> {
+    let filtradas: Vec<&VarianteStream> =
+        candidatas.iter().copied().filter(|v| cumple(v)).collect();
+    match (filtradas.is_empty(), estricta) {
+        (false, _) => Some(filtradas),
+        (true, true) => None,
+        (true, false) => Some(candidatas),
+    }
+}
+
+fn seleccionar_por_altura<'a>(
+    variantes: &[&'a VarianteStream],
+    quality: VideoQuality,
+    estricta: bool,
+) -> Option<&'a VarianteStream> {
     if quality == VideoQuality::AudioOnly {
         // streams sin RESOLUTION son pistas de audio puro
         let audio = variantes
             .iter()
+            .copied()
             .filter(|v| v.height.is_none())
             .min_by_key(|v| v.bandwidth.unwrap_or(0));
-        if let Some(a) = audio {
-            return Some(a);
+        if audio.is_some() || estricta {
+            return audio;
         }
         // fallback: video de menor calidad
         return variantes
             .iter()
+            .copied()
             .filter(|v| v.height.is_some())
             .min_by_key(|v| (v.height.unwrap_or(u32::MAX), v.bandwidth.unwrap_or(0)));
     }
 
-    let objetivo = quality.target_height();
-
-    if let Some(objetivo) = objetivo {
+    if let Some(objetivo) = quality.target_height() {
+        if estricta {
+            return variantes
+                .iter()
+                .copied()
+                .filter(|v| v.height == Some(objetivo))
+                .max_by_key(|v| v.bandwidth.unwrap_or(0));
+        }
         // mejor variante en o por debajo del objetivo
         let bajo = variantes
             .iter()
+            .copied()
             .filter(|v| v.height.is_some_and(|h| h <= objetivo))
             .max_by_key(|v| (v.height.unwrap_or(0), v.bandwidth.unwrap_or(0)));
         if let Some(sel) = bajo {
@@ -613,6 +1277,7 @@ fn seleccionar_variante(
         // fallback: la más cercana por encima
         let sobre = variantes
             .iter()
+            .copied()
             .filter(|v| v.height.is_some_and(|h| h > objetivo))
             .min_by_key(|v| (v.height.unwrap_or(u32::MAX), v.bandwidth.unwrap_or(0)));
         if let Some(sel) = sobre {
@@ -623,26 +1288,63 @@ fn seleccionar_variante(
     // Best o sin coincidencia: mayor resolución, luego mayor bandwidth
     variantes
         .iter()
+        .copied()
         .filter(|v| v.height.is_some())
         .max_by_key(|v| (v.height.unwrap_or(0), v.bandwidth.unwrap_or(0)))
-        .or_else(|| variantes.iter().max_by_key(|v| v.bandwidth.unwrap_or(0)))
+        .or_else(|| {
+            variantes
+                .iter()
+                .copied()
+                .max_by_key(|v| v.bandwidth.unwrap_or(0))
+        })
 }
 
 fn resolver_url_variante(
     master_url: &StreamUrl,
     variantes: &[VarianteStream],
-    quality: VideoQuality,
-) -> Result<StreamUrl, InfrastructureError> {
-    let seleccion = seleccionar_variante(variantes, quality)
-        .or_else(|| seleccionar_variante(variantes, VideoQuality::Best))
-        .ok_or_else(|| {
-            InfrastructureError::ExternalService(
-                "No se pudo seleccionar variante de calidad".to_string(),
-            )
-        })?;
+    politica: &PoliticaVariante,
+) -> Result<VarianteResuelta, InfrastructureError> {
+    let seleccion = match seleccionar_variante(variantes, politica) {
+        Some(seleccion) => Some(seleccion),
+        None if politica.estricta => {
+            return Err(InfrastructureError::VariantUnavailable(describir_politica(
+                politica,
+            )))
+        }
+        None => seleccionar_variante(variantes, &VideoQuality::Best.into()),
+    }
+    .ok_or_else(|| {
+        InfrastructureError::ExternalService(
+            "No se pudo seleccionar variante de calidad".to_string(),
+        )
+    })?;
+
+    let video = resolver_url(master_url.as_str(), &seleccion.url)?;
+    let audio = match &seleccion.audio {
+        Some(rendition) => Some(StreamUrl::try_from(resolver_url(
+            master_url.as_str(),
+            &rendition.url,
+        )?)?),
+        None => None,
+    };
+    Ok(VarianteResuelta {
+        video: StreamUrl::try_from(video)?,
+        audio,
+    })
+}
 
-    let url_final = resolver_url(master_url.as_str(), &seleccion.url)?;
-    Ok(StreamUrl::try_from(url_final)?)
+fn describir_politica(politica: &PoliticaVariante) -> String {
+    let mut partes = vec![politica.calidad.to_string()];
+    if let Some(maximo) = politica.max_bitrate {
+        partes.push(format!("<= {} kbps", maximo / 1000));
+    }
+    if let Some(codec) = politica.codec {
+        partes.push(codec.to_string());
+    }
+    if let Some(fps) = politica.fps {
+        partes.push(format!("{fps} fps"));
+    }
+    partes.join(", ")
 }
 
 fn resolver_url(base: &str, relativa: &str) -> Result<String, InfrastructureError> {
@@ -677,10 +1379,10 @@ hi.m3u8
         let variantes = parsear_variantes(playlist).expect("parse failed");
         assert_eq!(variantes.len(), 3);
 
-        let sel_720 = seleccionar_variante(&variantes, VideoQuality::P720).unwrap();
+        let sel_720 = seleccionar_variante(&variantes, &VideoQuality::P720.into()).unwrap();
         assert_eq!(sel_720.url, "mid.m3u8");
 
-        let sel_best = seleccionar_variante(&variantes, VideoQuality::Best).unwrap();
+        let sel_best = seleccionar_variante(&variantes, &VideoQuality::Best.into()).unwrap();
         assert_eq!(sel_best.url, "hi.m3u8");
     }
 
@@ -692,18 +1394,23 @@ hi.m3u8
                 url: "360/index.m3u8".to_string(),
                 bandwidth: Some(800_000),
                 height: Some(360),
+                ..Default::default()
             },
             VarianteStream {
                 url: "1080/index.m3u8".to_string(),
                 bandwidth: Some(5_128_000),
                 height: Some(1080),
+                ..Default::default()
             },
         ];
 
-        let url =
-            resolver_url_variante(&master, &variantes, VideoQuality::Best).expect("resuelve best");
+        let url = resolver_url_variante(&master, &variantes, &VideoQuality::Best.into())
+            .expect("resuelve best");
 
-        assert_eq!(url.as_str(), "https://example.com/hls/1080/index.m3u8");
+        assert_eq!(
+            url.video.as_str(),
+            "https://example.com/hls/1080/index.m3u8"
+        );
     }
 
     #[test]
@@ -718,7 +1425,7 @@ low.m3u8
 hi.m3u8
 ";
         let variantes = parsear_variantes(playlist).expect("parse failed");
-        let sel = seleccionar_variante(&variantes, VideoQuality::AudioOnly).unwrap();
+        let sel = seleccionar_variante(&variantes, &VideoQuality::AudioOnly.into()).unwrap();
         assert_eq!(sel.url, "audio.m3u8");
     }
 
@@ -732,7 +1439,7 @@ low.m3u8
 hi.m3u8
 ";
         let variantes = parsear_variantes(playlist).expect("parse failed");
-        let sel = seleccionar_variante(&variantes, VideoQuality::AudioOnly).unwrap();
+        let sel = seleccionar_variante(&variantes, &VideoQuality::AudioOnly.into()).unwrap();
         assert_eq!(sel.url, "low.m3u8");
     }
 
@@ -753,11 +1460,162 @@ hi.m3u8
 hi.m3u8
 ";
         let variantes = parsear_variantes(playlist).expect("parse failed");
-        let sel = seleccionar_variante(&variantes, VideoQuality::P720).unwrap();
+        let sel = seleccionar_variante(&variantes, &VideoQuality::P720.into()).unwrap();
 
         assert_eq!(sel.url, "hi.m3u8");
     }
 
+    const PLAYLIST_CODECS: &str = "\
+#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=1400000,RESOLUTION=1280x720,CODECS=\"avc1.4d401f,mp4a.40.2\",FRAME-RATE=30.000
+h264-720p30.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=2500000,RESOLUTION=1280x720,CODECS=\"avc1.4d401f,mp4a.40.2\",FRAME-RATE=60.000
+h264-720p60.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=2000000,RESOLUTION=1920x1080,CODECS=\"hvc1.1.6.L120.90,mp4a.40.2\",FRAME-RATE=30.000
+hevc-1080p30.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,CODECS=\"avc1.640028,mp4a.40.2\",FRAME-RATE=60.000
+h264-1080p60.m3u8
+";
+
+    #[test]
+    fn parsea_codecs_y_frame_rate() {
+        let variantes = parsear_variantes(PLAYLIST_CODECS).expect("parse failed");
+
+        assert_eq!(variantes[2].codec_video(), Some(CodecVideo::Hevc));
+        assert_eq!(variantes[1].fps_redondeados(), Some(60));
+        assert_eq!(
+            variantes[0].codecs.as_deref(),
+            Some("avc1.4d401f,mp4a.40.2")
+        );
+    }
+
+    #[test]
+    fn politica_limita_bitrate_y_prefiere_codec_y_fps() {
+        let variantes = parsear_variantes(PLAYLIST_CODECS).expect("parse failed");
+        let base = PoliticaVariante::from(VideoQuality::Best);
+
+        let limitada = PoliticaVariante {
+            max_bitrate: Some(3_000_000),
+            ..base
+        };
+        let sel = seleccionar_variante(&variantes, &limitada).unwrap();
+        assert_eq!(sel.url, "hevc-1080p30.m3u8");
+
+        let h264_60 = PoliticaVariante {
+            max_bitrate: Some(3_000_000),
+            codec: Some(CodecVideo::H264),
+            fps: Some(60),
+            ..base
+        };
+        let sel = seleccionar_variante(&variantes, &h264_60).unwrap();
+        assert_eq!(sel.url, "h264-720p60.m3u8");
+
+        let fps_inexistente = PoliticaVariante {
+            fps: Some(25),
+            ..base
+        };
+        let sel = seleccionar_variante(&variantes, &fps_inexistente).unwrap();
+        assert_eq!(sel.url, "h264-1080p60.m3u8");
+    }
+
+    #[test]
+    fn politica_estricta_falla_sin_variante_compatible() {
+        let master = StreamUrl::try_from("https://example.com/hls/master.m3u8").unwrap();
+        let variantes = parsear_variantes(PLAYLIST_CODECS).expect("parse failed");
+        let politica = PoliticaVariante {
+            codec: Some(CodecVideo::Hevc),
+            fps: Some(60),
+            estricta: true,
+            ..PoliticaVariante::from(VideoQuality::Best)
+        };
+
+        let err = resolver_url_variante(&master, &variantes, &politica)
+            .expect_err("sin variante hevc a 60 fps");
+        assert!(matches!(err, InfrastructureError::VariantUnavailable(_)));
+
+        let relajada = PoliticaVariante {
+            estricta: false,
+            ..politica
+        };
+        let url = resolver_url_variante(&master, &variantes, &relajada).expect("fallback");
+        assert_eq!(
+            url.video.as_str(),
+            "https://example.com/hls/hevc-1080p30.m3u8"
+        );
+
+        let altura_exacta = PoliticaVariante {
+            estricta: true,
+            ..PoliticaVariante::from(VideoQuality::P480)
+        };
+        assert!(seleccionar_variante(&variantes, &altura_exacta).is_none());
+    }
+
+    #[test]
+    fn variante_con_grupo_audio_resuelve_rendition_por_defecto() {
+        let playlist = "\
+#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=2800000,RESOLUTION=1920x1080,AUDIO=\"aud\"
+video/1080.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,AUDIO=\"muxed\"
+video/360.m3u8
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"Backup\",URI=\"audio/backup.m3u8\"
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"Main\",DEFAULT=YES,AUTOSELECT=YES,URI=\"audio/main.m3u8\"
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"muxed\",NAME=\"Muxed\",DEFAULT=YES
+";
+        let master = StreamUrl::try_from("https://example.com/hls/master.m3u8").unwrap();
+        let variantes = parsear_variantes(playlist).expect("parse failed");
+
+        assert_eq!(
+            variantes[0].audio,
+            Some(RenditionAudio {
+                nombre: "Main".to_string(),
+                url: "audio/main.m3u8".to_string(),
+            })
+        );
+        assert_eq!(variantes[1].audio, None);
+
+        let best = resolver_url_variante(&master, &variantes, &VideoQuality::Best.into())
+            .expect("resuelve best");
+        assert_eq!(
+            best.video.as_str(),
+            "https://example.com/hls/video/1080.m3u8"
+        );
+        assert_eq!(
+            best.audio.as_ref().map(StreamUrl::as_str),
+            Some("https://example.com/hls/audio/main.m3u8")
+        );
+
+        let baja = resolver_url_variante(&master, &variantes, &VideoQuality::P360.into())
+            .expect("resuelve 360p");
+        assert_eq!(baja.audio, None);
+    }
+
+    #[test]
+    fn ventana_recuperable_descuenta_borde_vivo() {
+        let playlist = "\
+#EXTM3U
+#EXT-X-TARGETDURATION:2
+#EXT-X-MEDIA-SEQUENCE:100
+#EXTINF:2.000,
+seg100.ts
+#EXTINF:2.000,
+seg101.ts
+#EXTINF:1.500,
+seg102.ts
+#EXTINF:2.000,
+seg103.ts
+#EXTINF:2.000,
+seg104.ts
+";
+        let segundos = parsear_ventana_recuperable(playlist).expect("parse failed");
+        assert!((segundos - 4.0).abs() < f64::EPSILON);
+
+        let corta = "#EXTM3U\n#EXTINF:2.0,\nseg1.ts\n";
+        assert_eq!(parsear_ventana_recuperable(corta).unwrap(), 0.0);
+        assert!((segundos_en_playlist(playlist).unwrap() - 9.5).abs() < f64::EPSILON);
+        assert!(parsear_ventana_recuperable("<html>").is_err());
+    }
+
     #[test]
     fn clasificar_status_http_distingue_respuestas() {
         assert_eq!(clasificar_status_http(StatusCode::OK), EstadoHttp::Ok);
@@ -788,7 +1646,7 @@ hi.m3u8
         let attempts = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
         let operation_attempts = attempts.clone();
 
-        let result = retry_with_backoff(|| {
+        let result = retry_with_backoff(&PoliticaReintentos::default(), || {
             let attempt = operation_attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
             async move {
                 if attempt == 0 {
@@ -809,7 +1667,7 @@ hi.m3u8
         let attempts = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
         let operation_attempts = attempts.clone();
 
-        let result: Result<(), &str> = retry_with_backoff(|| {
+        let result: Result<(), &str> = retry_with_backoff(&PoliticaReintentos::default(), || {
             operation_attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
             async { Err(RetryFailure::Permanent("stop")) }
         })
@@ -819,6 +1677,52 @@ hi.m3u8
         assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
     }
 
+    #[tokio::test]
+    async fn retry_with_backoff_stops_after_configured_retries() {
+        let attempts = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let operation_attempts = attempts.clone();
+        let politica = PoliticaReintentos {
+            max_reintentos: 2,
+            base: Duration::from_millis(1),
+            ..PoliticaReintentos::default()
+        };
+
+        let result: Result<(), &str> = retry_with_backoff(&politica, || {
+            operation_attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async { Err(RetryFailure::Transient("retry")) }
+        })
+        .await;
+
+        assert_eq!(result, Err("retry"));
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn http_options_send_user_agent_and_custom_headers() {
+        let Some((base_url, request_task)) = servidor_http_falso(200, "").await else {
+            return;
+        };
+        let mut client = ChaturbateClient::new()
+            .expect("crea cliente")
+            .with_http_options(OpcionesHttp {
+                user_agent: "cbrec-test".to_string(),
+                headers: vec![("X-Prueba".to_string(), "si".to_string())],
+                ..OpcionesHttp::default()
+            })
+            .expect("aplica opciones")
+            .with_proxy(None)
+            .expect("conexion directa");
+        client.base_url = base_url;
+
+        assert_eq!(client.probar_conexion().await.expect("responde"), 200);
+        let request = request_task
+            .await
+            .expect("request task")
+            .to_ascii_lowercase();
+        assert!(request.contains("user-agent: cbrec-test"));
+        assert!(request.contains("x-prueba: si"));
+    }
+
     #[test]
     fn retry_budget_distinguishes_limit_boundary() {
         let max_elapsed = Duration::from_secs(10);
@@ -979,6 +1883,44 @@ hi.m3u8
         let _ = request_task.await.expect("request task");
     }
 
+    #[tokio::test]
+    async fn rate_limit_opens_shared_circuit_for_cloned_clients() {
+        let Some((base_url, request_task)) = servidor_http_falso(429, "{}").await else {
+            return;
+        };
+        let mut client = ChaturbateClient::new()
+            .expect("crea cliente")
+            .with_http_options(OpcionesHttp {
+                limite: LimitePeticiones {
+                    umbral_circuito: 1,
+                    ..LimitePeticiones::default()
+                },
+                ..OpcionesHttp::default()
+            })
+            .expect("aplica opciones");
+        client.base_url = base_url;
+        let otro = client.for_model("bob");
+
+        let estado = client
+            .consultar_estado(&ModelName::try_from("alice").unwrap())
+            .await
+            .expect("consulta");
+        let bloqueado = otro
+            .consultar_estado(&ModelName::try_from("bob").unwrap())
+            .await;
+
+        assert_eq!(estado, EstadoStream::RateLimited);
+        assert!(matches!(
+            bloqueado,
+            Err(InfrastructureError::CircuitOpen(_))
+        ));
+        assert!(matches!(
+            otro.estado_circuito(),
+            EstadoCircuito::Abierto { .. }
+        ));
+        let _ = request_task.await.expect("request task");
+    }
+
     #[tokio::test]
     async fn listar_calidades_parsea_playlist_contrato_http() {
         let playlist = "\
@@ -995,7 +1937,7 @@ hi.m3u8
         let master = StreamUrl::try_from(format!("{base_url}/hls/master.m3u8")).unwrap();
 
         let calidades = client
-            .listar_calidades(&master)
+            .listar_calidades(&master, VideoQuality::Best)
             .await
             .expect("lista calidades");
 
@@ -1017,7 +1959,7 @@ hi.m3u8
         let master = StreamUrl::try_from(format!("{base_url}/hls/master.m3u8")).unwrap();
 
         let err = client
-            .listar_calidades(&master)
+            .listar_calidades(&master, VideoQuality::Best)
             .await
             .expect_err("playlist html debe fallar");
 
@@ -1061,7 +2003,7 @@ hi.m3u8
         client.base_url = base_url;
 
         let rooms = client
-            .discover_rooms_by_tag("gaming", 2)
+            .discover_rooms_by_tag("gaming", 2, 0)
             .await
             .expect("discover rooms");
 
@@ -1081,6 +2023,85 @@ hi.m3u8
         assert!(!request.to_ascii_lowercase().contains("cookie:"));
     }
 
+    #[test]
+    fn model_proxy_override_replaces_global_proxy() {
+        let client = ChaturbateClient::new()
+            .expect("crea cliente")
+            .with_proxy(Some("socks5h://127.0.0.1:1080".to_string()))
+            .expect("proxy global")
+            .with_model_proxies(&HashMap::from([
+                (
+                    "Alice".to_string(),
+                    Some("http://10.0.0.1:3128".to_string()),
+                ),
+                ("bob".to_string(), None),
+            ]))
+            .expect("proxies por modelo");
+
+        assert_eq!(
+            client.for_model("alice").proxy(),
+            Some("http://10.0.0.1:3128")
+        );
+        assert_eq!(client.for_model("bob").proxy(), None);
+        assert_eq!(
+            client.for_model("carol").proxy(),
+            Some("socks5h://127.0.0.1:1080")
+        );
+        assert!(client.has_model_proxy("ALICE"));
+    }
+
+    #[tokio::test]
+    async fn discover_rooms_sends_offset_for_later_pages() {
+        let Some((base_url, request_task)) = servidor_http_falso(200, r#"{"rooms":[]}"#).await
+        else {
+            return;
+        };
+        let mut client = ChaturbateClient::new().expect("crea cliente");
+        client.base_url = base_url;
+
+        let rooms = client
+            .discover_rooms_by_tag("gaming", 20, 40)
+            .await
+            .expect("discover rooms");
+
+        assert!(rooms.is_empty());
+        let request = request_task.await.expect("request task");
+        assert!(request.starts_with(
+            "GET /api/ts/roomlist/room-list/?hashtags=gaming&limit=20&offset=40 HTTP/1.1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn similar_rooms_skips_source_model_and_duplicates() {
+        let body = r#"{"rooms":[{"username":"alice","num_users":9},{"username":"Bob","room_subject":"hi","num_users":7,"current_show":"public"},{"username":"bob"},{"username":"bad name"}]}"#;
+        let Some((base_url, request_task)) = servidor_http_falso(200, body).await else {
+            return;
+        };
+        let mut client = ChaturbateClient::new()
+            .expect("crea cliente")
+            .with_session_cookie("PHPSESSID=secret".to_string());
+        client.base_url = base_url;
+        let model = ModelName::try_from("alice").unwrap();
+
+        let rooms = client
+            .similar_rooms(&model, 10)
+            .await
+            .expect("similar rooms");
+
+        assert_eq!(
+            rooms,
+            vec![DiscoveredRoom {
+                username: "bob".to_string(),
+                subject: "hi".to_string(),
+                viewers: 7,
+                show: "public".to_string(),
+            }]
+        );
+        let request = request_task.await.expect("request task");
+        assert!(request.starts_with("GET /api/more_like/alice/ HTTP/1.1"));
+        assert!(!request.to_ascii_lowercase().contains("cookie:"));
+    }
+
     async fn servidor_http_falso(
         status: u16,
         body: &'static str,
@@ -1,13 +1,16 @@
 //! Runs and supervises the FFmpeg child process used for stream recording.
 //!
-//! This module owns process construction, cancellation, stall detection, and
-//! stderr sanitization. It does not resolve stream URLs or know about HTTP.
+//! This module owns process construction, cancellation, progress reporting,
+//! stall detection, and stderr sanitization. It does not resolve stream URLs
+//! or know about HTTP.
 
-use crate::infrastructure::InfrastructureError;
+use crate::domain::value_objects::ProgresoGrabacion;
+use crate::infrastructure::{InfrastructureError, PerfilFfmpeg};
 use std::future;
 use std::path::Path;
 use std::process::{ExitStatus, Stdio};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Child;
 use tokio::sync::watch;
 use tokio::time::{Duration, Instant};
@@ -17,15 +20,202 @@ const STALL_TIMEOUT_SECS: u64 = 120;
 const STALL_CHECK_SECS: u64 = 5;
 const DISK_CHECK_SECS: u64 = 30;
 
+/// Options a profile cannot set: they choose inputs, outputs or the
+/// container, or make FFmpeg log request headers and with them the cookie.
+const OPCIONES_RESERVADAS: &[&str] = &[
+    "i",
+    "y",
+    "n",
+    "f",
+    "headers",
+    "cookies",
+    "http_proxy",
+    "progress",
+    "stats",
+    "nostats",
+    "loglevel",
+    "v",
+    "report",
+    "hls_segment_filename",
+];
+
+/// Options a profile may set and how many values each one takes. Anything
+/// else is rejected, since an unknown flag without a value would turn the
+/// next argument into another output file.
+const OPCIONES_PERFIL: &[(&str, usize)] = &[
+    // Entrada HTTP/HLS.
+    ("reconnect", 1),
+    ("reconnect_streamed", 1),
+    ("reconnect_at_eof", 1),
+    ("reconnect_on_network_error", 1),
+    ("reconnect_on_http_error", 1),
+    ("reconnect_delay_max", 1),
+    ("rw_timeout", 1),
+    ("timeout", 1),
+    ("user_agent", 1),
+    ("referer", 1),
+    ("multiple_requests", 1),
+    ("http_persistent", 1),
+    ("seekable", 1),
+    ("live_start_index", 1),
+    ("max_reload", 1),
+    ("m3u8_hold_counters", 1),
+    ("stream_loop", 1),
+    ("thread_queue_size", 1),
+    ("analyzeduration", 1),
+    ("probesize", 1),
+    ("fflags", 1),
+    ("re", 0),
+    // Salida.
+    ("bsf", 1),
+    ("movflags", 1),
+    ("metadata", 1),
+    ("map", 1),
+    ("map_metadata", 1),
+    ("map_chapters", 1),
+    ("disposition", 1),
+    ("tag", 1),
+    ("avoid_negative_ts", 1),
+    ("max_muxing_queue_size", 1),
+    ("max_interleave_delta", 1),
+    ("frag_duration", 1),
+    ("min_frag_duration", 1),
+    ("copyts", 0),
+    ("start_at_zero", 0),
+    ("shortest", 0),
+    ("an", 0),
+    ("vn", 0),
+    ("sn", 0),
+    ("dn", 0),
+];
+
+/// HTTP input options applied to every request FFmpeg makes for one recording.
+#[derive(Clone, Copy, Default)]
+pub(super) struct EntradaHttp<'a> {
+    pub(super) session_cookie: Option<&'a str>,
+    pub(super) proxy: Option<&'a str>,
+}
+
+/// Limits and signals that supervise one FFmpeg run.
+#[derive(Clone, Default)]
+pub(super) struct ControlFfmpeg {
+    pub(super) max_duration_secs: Option<u64>,
+    pub(super) min_free_space: u64,
+    pub(super) cancel_rx: Option<watch::Receiver<bool>>,
+    /// Receives every `-progress` report FFmpeg writes.
+    pub(super) progreso: Option<Arc<watch::Sender<ProgresoGrabacion>>>,
+}
+
+/// Inputs FFmpeg records: a video variant and, when the master playlist
+/// announces it separately, the audio rendition that goes with it.
+#[derive(Clone, Copy)]
+pub(super) struct FuenteStream<'a> {
+    pub(super) video: &'a str,
+    pub(super) audio: Option<&'a str>,
+    /// Read from the oldest listed segment instead of near the live edge.
+    pub(super) desde_inicio: bool,
+}
+
+/// Where FFmpeg writes: one file, or a rolling HLS window that keeps only the
+/// newest `segmentos` segments next to its playlist.
+#[derive(Clone, Copy)]
+pub(super) enum DestinoFfmpeg<'a> {
+    Archivo(&'a Path),
+    Ventana {
+        playlist: &'a Path,
+        segundos_segmento: u64,
+        segmentos: u64,
+    },
+}
+
+impl<'a> From<&'a Path> for DestinoFfmpeg<'a> {
+    fn from(ruta: &'a Path) -> Self {
+        DestinoFfmpeg::Archivo(ruta)
+    }
+}
+
+impl DestinoFfmpeg<'_> {
+    fn ruta(&self) -> &Path {
+        match self {
+            DestinoFfmpeg::Archivo(ruta) => ruta,
+            DestinoFfmpeg::Ventana { playlist, .. } => playlist,
+        }
+    }
+
+    /// Profile output options only apply to single files; the rolling window
+    /// is always plain HLS.
+    fn args(&self, opciones_salida: &[String]) -> Vec<String> {
+        let mut args = Vec::new();
+        if let DestinoFfmpeg::Archivo(ruta) = self {
+            args.extend(codec_args(ruta).iter().map(|arg| arg.to_string()));
+            args.extend(opciones_salida.iter().cloned());
+        } else {
+            args.extend(["-c", "copy"].map(String::from));
+        }
+        if let DestinoFfmpeg::Ventana {
+            playlist,
+            segundos_segmento,
+            segmentos,
+        } = self
+        {
+            let patron = playlist.with_file_name("seg_%06d.ts");
+            args.extend([
+                "-f".to_string(),
+                "hls".to_string(),
+                "-hls_time".to_string(),
+                segundos_segmento.to_string(),
+                "-hls_list_size".to_string(),
+                segmentos.to_string(),
+                "-hls_flags".to_string(),
+                "delete_segments".to_string(),
+                "-hls_segment_filename".to_string(),
+                patron.to_string_lossy().into_owned(),
+            ]);
+        }
+        args.push("-y".to_string());
+        args.push(self.ruta().to_string_lossy().into_owned());
+        args
+    }
+}
+
+/// Audio files drop the video and keep the AAC track as is, or transcode it
+/// for Opus; anything else is copied untouched.
+fn codec_args(ruta: &Path) -> &'static [&'static str] {
+    let extension = ruta
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("m4a") => &["-vn", "-c:a", "copy"],
+        Some("opus") => &["-vn", "-c:a", "libopus", "-b:a", "96k"],
+        _ => &["-c", "copy"],
+    }
+}
+
+impl<'a> From<&'a str> for FuenteStream<'a> {
+    fn from(video: &'a str) -> Self {
+        Self {
+            video,
+            audio: None,
+            desde_inicio: false,
+        }
+    }
+}
+
 pub(super) async fn run_ffmpeg(
     ffmpeg_path: &Path,
-    stream_url: &str,
-    output_path: &Path,
-    session_cookie: Option<&str>,
-    max_duration_secs: Option<u64>,
-    min_free_space: u64,
-    cancel_rx: Option<watch::Receiver<bool>>,
+    fuente: FuenteStream<'_>,
+    destino: DestinoFfmpeg<'_>,
+    entrada: EntradaHttp<'_>,
+    perfil: &PerfilFfmpeg,
+    control: ControlFfmpeg,
 ) -> Result<(), InfrastructureError> {
+    let ControlFfmpeg {
+        max_duration_secs,
+        min_free_space,
+        cancel_rx,
+        progreso,
+    } = control;
     if cancel_rx
         .as_ref()
         .is_some_and(|receiver| *receiver.borrow())
@@ -33,7 +223,9 @@ pub(super) async fn run_ffmpeg(
         return Err(InfrastructureError::RecordingCancelled);
     }
 
+    let output_path = destino.ruta();
     ensure_disk_space(output_path, min_free_space)?;
+    let proxy_args = proxy_args(entrada.proxy)?;
 
     let mut command = tokio::process::Command::new(ffmpeg_path);
     command.kill_on_drop(true);
@@ -42,27 +234,26 @@ pub(super) async fn run_ffmpeg(
         .arg("-hide_banner")
         .arg("-loglevel")
         .arg("error")
+        .args(["-nostats", "-progress", "pipe:1"])
         .stdin(Stdio::piped())
-        .stdout(Stdio::null())
+        .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
-    if let Some(cookie) = session_cookie {
-        command
-            .arg("-headers")
-            .arg(format!("Cookie: {}\r\n", cookie));
+    let mut opciones_entrada = Vec::new();
+    if let Some(cookie) = entrada.session_cookie {
+        opciones_entrada.push("-headers".to_string());
+        opciones_entrada.push(format!("Cookie: {}\r\n", cookie));
     }
+    opciones_entrada.extend(proxy_args);
+    opciones_entrada.extend(perfil.input.iter().cloned());
+    opciones_entrada.extend(duration_args(max_duration_secs));
 
     let mut child = command
-        .args(duration_args(max_duration_secs))
-        .arg("-i")
-        .arg(stream_url)
-        .arg("-c")
-        .arg("copy")
-        .arg("-y")
-        .arg(output_path)
+        .args(input_args(fuente, &opciones_entrada))
+        .args(destino.args(&perfil.output))
         .spawn()
         .map_err(|e| {
-            InfrastructureError::RecordingError(format!("Failed to start ffmpeg: {}", e))
+            InfrastructureError::FfmpegStartup(format!("Failed to start ffmpeg: {}", e))
         })?;
     let mut stderr_task = child.stderr.take().map(|mut stderr| {
         tokio::spawn(async move {
@@ -71,6 +262,10 @@ pub(super) async fn run_ffmpeg(
             buffer
         })
     });
+    let (avance_tx, avance_rx) = watch::channel(ProgresoGrabacion::default());
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(leer_progreso(stdout, avance_tx, progreso));
+    }
 
     if let Some(mut cancel_rx) = cancel_rx {
         if *cancel_rx.borrow() {
@@ -80,7 +275,7 @@ pub(super) async fn run_ffmpeg(
 
         tokio::select! {
             status = child.wait() => {
-                check_exit_status(status, stderr_task.take()).await?;
+                check_exit_status(status, stderr_task.take(), output_path).await?;
             }
             _ = async { cancel_rx.wait_for(|value| *value).await.ok(); } => {
                 cancel_ffmpeg(&mut child, stderr_task.take()).await;
@@ -91,15 +286,12 @@ pub(super) async fn run_ffmpeg(
                 return Err(InfrastructureError::RecordingCancelled);
             }
             _ = wait_for_stall(
-                output_path,
+                avance_rx.clone(),
                 Duration::from_secs(STALL_TIMEOUT_SECS),
                 Duration::from_secs(STALL_CHECK_SECS),
             ) => {
                 cancel_ffmpeg(&mut child, stderr_task.take()).await;
-                return Err(InfrastructureError::RecordingError(format!(
-                    "FFmpeg no escribio datos nuevos durante {} segundos",
-                    STALL_TIMEOUT_SECS
-                )));
+                return Err(InfrastructureError::StreamStalled(STALL_TIMEOUT_SECS));
             }
             available = wait_for_low_disk(output_path, min_free_space) => {
                 cancel_ffmpeg(&mut child, stderr_task.take()).await;
@@ -109,22 +301,19 @@ pub(super) async fn run_ffmpeg(
     } else {
         tokio::select! {
             status = child.wait() => {
-                check_exit_status(status, stderr_task.take()).await?;
+                check_exit_status(status, stderr_task.take(), output_path).await?;
             }
             _ = wait_for_duration_limit(max_duration_secs) => {
                 cancel_ffmpeg(&mut child, stderr_task.take()).await;
                 return Err(InfrastructureError::RecordingCancelled);
             }
             _ = wait_for_stall(
-                output_path,
+                avance_rx.clone(),
                 Duration::from_secs(STALL_TIMEOUT_SECS),
                 Duration::from_secs(STALL_CHECK_SECS),
             ) => {
                 cancel_ffmpeg(&mut child, stderr_task.take()).await;
-                return Err(InfrastructureError::RecordingError(format!(
-                    "FFmpeg no escribio datos nuevos durante {} segundos",
-                    STALL_TIMEOUT_SECS
-                )));
+                return Err(InfrastructureError::StreamStalled(STALL_TIMEOUT_SECS));
             }
             available = wait_for_low_disk(output_path, min_free_space) => {
                 cancel_ffmpeg(&mut child, stderr_task.take()).await;
@@ -136,6 +325,28 @@ pub(super) async fn run_ffmpeg(
     Ok(())
 }
 
+/// Combines the global cancel signal with a per-recording stop signal; either
+/// one stops FFmpeg gracefully.
+pub(super) fn combinar_senales(
+    cancel_rx: Option<watch::Receiver<bool>>,
+    stop_rx: Option<watch::Receiver<bool>>,
+) -> Option<watch::Receiver<bool>> {
+    let (mut cancel_rx, mut stop_rx) = match (cancel_rx, stop_rx) {
+        (Some(cancel_rx), Some(stop_rx)) => (cancel_rx, stop_rx),
+        (cancel_rx, stop_rx) => return cancel_rx.or(stop_rx),
+    };
+    let (tx, rx) = watch::channel(*cancel_rx.borrow() || *stop_rx.borrow());
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = cancel_rx.wait_for(|value| *value) => {}
+            _ = stop_rx.wait_for(|value| *value) => {}
+            _ = tx.closed() => return,
+        }
+        let _ = tx.send(true);
+    });
+    Some(rx)
+}
+
 fn ensure_disk_space(path: &Path, required: u64) -> Result<(), InfrastructureError> {
     if required == 0 {
         return Ok(());
@@ -166,27 +377,59 @@ fn available_disk_space(path: &Path) -> Result<u64, std::io::Error> {
 }
 
 fn low_disk_error(available: u64, required: u64) -> InfrastructureError {
-    InfrastructureError::RecordingError(format!(
-        "espacio insuficiente: {available} bytes disponibles; se requieren {required}"
-    ))
+    InfrastructureError::LowDiskSpace {
+        available,
+        required,
+    }
 }
 
 async fn check_exit_status(
     status: std::io::Result<ExitStatus>,
     stderr_task: Option<tokio::task::JoinHandle<Vec<u8>>>,
+    output_path: &Path,
 ) -> Result<(), InfrastructureError> {
     let status = status.map_err(|e| {
         InfrastructureError::RecordingError(format!("Failed to wait for ffmpeg: {}", e))
     })?;
     if !status.success() {
         let stderr = read_stderr(stderr_task).await;
-        return Err(InfrastructureError::RecordingError(format_ffmpeg_error(
-            status, &stderr,
-        )));
+        let mensaje = format_ffmpeg_error(status, &stderr);
+        return Err(clasificar_salida_ffmpeg(
+            mensaje,
+            &stderr,
+            file_size(output_path).await,
+        ));
     }
     Ok(())
 }
 
+/// Tells apart a vanished playlist and a process that died before writing
+/// anything from other FFmpeg failures.
+fn clasificar_salida_ffmpeg(mensaje: String, stderr: &[u8], bytes: u64) -> InfrastructureError {
+    let texto = String::from_utf8_lossy(stderr).to_ascii_lowercase();
+    let playlist_perdida = ["403 forbidden", "404 not found", "410 gone", "http error 4"]
+        .iter()
+        .any(|patron| texto.contains(patron));
+    let edge_caido = [
+        "server returned 5",
+        "http error 5",
+        "connection refused",
+        "connection reset",
+        "connection timed out",
+    ]
+    .iter()
+    .any(|patron| texto.contains(patron));
+    if playlist_perdida {
+        InfrastructureError::PlaylistChanged(mensaje)
+    } else if edge_caido {
+        InfrastructureError::EdgeFailed(mensaje)
+    } else if bytes == 0 {
+        InfrastructureError::FfmpegStartup(mensaje)
+    } else {
+        InfrastructureError::RecordingError(mensaje)
+    }
+}
+
 #[cfg(unix)]
 fn configure_process_isolation(command: &mut tokio::process::Command) {
     command.process_group(0);
@@ -195,6 +438,74 @@ fn configure_process_isolation(command: &mut tokio::process::Command) {
 #[cfg(not(unix))]
 fn configure_process_isolation(_command: &mut tokio::process::Command) {}
 
+/// FFmpeg's HTTP protocol only understands HTTP proxies; SOCKS is rejected
+/// instead of silently bypassing the proxy for media segments.
+fn proxy_args(proxy: Option<&str>) -> Result<Vec<String>, InfrastructureError> {
+    let Some(proxy) = proxy else {
+        return Ok(Vec::new());
+    };
+    let scheme = proxy
+        .split_once("://")
+        .map(|(scheme, _)| scheme.to_ascii_lowercase())
+        .unwrap_or_default();
+    if scheme != "http" {
+        return Err(InfrastructureError::FfmpegStartup(format!(
+            "FFmpeg solo admite proxies http://; el proxy {scheme}:// no sirve para grabar"
+        )));
+    }
+    Ok(vec!["-http_proxy".to_string(), proxy.to_string()])
+}
+
+/// Checks that a profile only holds known options, each with the values it
+/// takes, so it cannot add outputs, replace the output path or leak the
+/// session cookie.
+pub(crate) fn validar_args_perfil(args: &[String]) -> Result<(), &'static str> {
+    if args
+        .iter()
+        .any(|arg| arg.to_ascii_lowercase().contains("cookie"))
+    {
+        return Err("no puede incluir cookies");
+    }
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(opcion) = arg
+            .strip_prefix('-')
+            .filter(|o| o.starts_with(|c: char| c.is_ascii_alphabetic()))
+        else {
+            return Err("solo admite pares -opcion valor; la ruta de salida la fija cbrec");
+        };
+        let nombre = opcion.split(':').next().unwrap_or(opcion);
+        if OPCIONES_RESERVADAS.contains(&nombre) {
+            return Err("no puede cambiar entradas, salida, formato ni registro de FFmpeg");
+        }
+        let Some(&(_, valores)) = OPCIONES_PERFIL.iter().find(|(o, _)| *o == nombre) else {
+            return Err("opcion de FFmpeg no admitida en perfiles");
+        };
+        if args.by_ref().take(valores).count() < valores {
+            return Err("falta el valor de una opcion");
+        }
+    }
+    Ok(())
+}
+
+/// Per-input options must precede every `-i`, so they are repeated for the
+/// audio rendition; with two inputs only its video and its audio are kept.
+fn input_args(fuente: FuenteStream<'_>, opciones: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+    for url in std::iter::once(fuente.video).chain(fuente.audio) {
+        args.extend(opciones.iter().cloned());
+        if fuente.desde_inicio {
+            args.extend(["-live_start_index", "0"].map(String::from));
+        }
+        args.push("-i".to_string());
+        args.push(url.to_string());
+    }
+    if fuente.audio.is_some() {
+        args.extend(["-map", "0:v:0", "-map", "1:a:0"].map(String::from));
+    }
+    args
+}
+
 fn duration_args(max_duration_secs: Option<u64>) -> Vec<String> {
     match max_duration_secs {
         Some(seconds) => vec!["-t".to_string(), seconds.to_string()],
@@ -238,17 +549,23 @@ async fn request_stdin_shutdown(child: &mut Child) -> bool {
     stdin.write_all(b"q\n").await.is_ok()
 }
 
-async fn wait_for_stall(path: &Path, timeout: Duration, check_interval: Duration) {
-    let mut last_size = file_size(path).await;
-    let mut last_change = Instant::now();
+/// Returns once FFmpeg's media time has not advanced for `timeout`; bytes
+/// alone are not enough since muxers keep flushing after the input stops.
+async fn wait_for_stall(
+    mut avance: watch::Receiver<ProgresoGrabacion>,
+    timeout: Duration,
+    check_interval: Duration,
+) {
+    let mut ultimo = avance.borrow_and_update().tiempo_media;
+    let mut ultimo_cambio = Instant::now();
 
     loop {
         tokio::time::sleep(check_interval).await;
-        let size = file_size(path).await;
-        if file_has_stalled(
-            &mut last_size,
-            &mut last_change,
-            size,
+        let actual = avance.borrow_and_update().tiempo_media;
+        if media_estancada(
+            &mut ultimo,
+            &mut ultimo_cambio,
+            actual,
             Instant::now(),
             timeout,
         ) {
@@ -257,20 +574,84 @@ async fn wait_for_stall(path: &Path, timeout: Duration, check_interval: Duration
     }
 }
 
-fn file_has_stalled(
-    last_size: &mut u64,
-    last_change: &mut Instant,
-    size: u64,
+fn media_estancada(
+    ultimo: &mut Duration,
+    ultimo_cambio: &mut Instant,
+    actual: Duration,
     now: Instant,
     timeout: Duration,
 ) -> bool {
-    if size > *last_size {
-        *last_size = size;
-        *last_change = now;
+    if actual > *ultimo {
+        *ultimo = actual;
+        *ultimo_cambio = now;
         return false;
     }
 
-    now.duration_since(*last_change) >= timeout
+    now.duration_since(*ultimo_cambio) >= timeout
+}
+
+/// Reads FFmpeg's `-progress` blocks and publishes each complete one.
+async fn leer_progreso(
+    stdout: impl AsyncRead + Unpin,
+    avance: watch::Sender<ProgresoGrabacion>,
+    externo: Option<Arc<watch::Sender<ProgresoGrabacion>>>,
+) {
+    let mut lineas = BufReader::new(stdout).lines();
+    let mut actual = ProgresoGrabacion::default();
+    while let Ok(Some(linea)) = lineas.next_line().await {
+        if aplicar_linea_progreso(&mut actual, &linea) {
+            avance.send_replace(actual);
+            if let Some(externo) = &externo {
+                externo.send_replace(actual);
+            }
+        }
+    }
+}
+
+/// Folds one `key=value` line into `progreso`; true on the `progress=` line
+/// that closes a block. `N/A` values leave the previous reading.
+fn aplicar_linea_progreso(progreso: &mut ProgresoGrabacion, linea: &str) -> bool {
+    let Some((clave, valor)) = linea.trim().split_once('=') else {
+        return false;
+    };
+    let valor = valor.trim();
+    match clave {
+        "total_size" => {
+            if let Ok(bytes) = valor.parse() {
+                progreso.bytes = bytes;
+            }
+        }
+        "out_time_us" => {
+            if let Ok(micros) = valor.parse::<i64>() {
+                progreso.tiempo_media = Duration::from_micros(micros.max(0) as u64);
+            }
+        }
+        "bitrate" => {
+            progreso.bitrate_kbps = valor
+                .strip_suffix("kbits/s")
+                .and_then(|v| v.trim().parse().ok())
+                .or(progreso.bitrate_kbps);
+        }
+        "speed" => {
+            progreso.velocidad = valor
+                .strip_suffix('x')
+                .and_then(|v| v.trim().parse().ok())
+                .or(progreso.velocidad);
+        }
+        "frame" => {
+            if let Ok(frames) = valor.parse() {
+                progreso.frames = frames;
+            }
+        }
+        "drop_frames" => {
+            if let Ok(perdidos) = valor.parse() {
+                progreso.frames_perdidos = perdidos;
+            }
+        }
+        "progress" => return true,
+        _ => {}
+    }
+    false
 }
 
 async fn file_size(path: &Path) -> u64 {
@@ -343,6 +724,7 @@ fn redact_sensitive_line(line: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::infrastructure::ClaseFallo;
 
     #[test]
     fn duration_arguments_include_ffmpeg_limit() {
@@ -353,6 +735,175 @@ mod tests {
         assert!(duration_args(None).is_empty());
     }
 
+    #[test]
+    fn audio_rendition_is_added_as_second_mapped_input() {
+        let opciones = duration_args(Some(20));
+        assert_eq!(
+            input_args("https://example.com/v.m3u8".into(), &opciones),
+            ["-t", "20", "-i", "https://example.com/v.m3u8"].map(String::from)
+        );
+
+        let fuente = FuenteStream {
+            video: "https://example.com/v.m3u8",
+            audio: Some("https://example.com/a.m3u8"),
+            desde_inicio: false,
+        };
+        assert_eq!(
+            input_args(fuente, &opciones),
+            [
+                "-t",
+                "20",
+                "-i",
+                "https://example.com/v.m3u8",
+                "-t",
+                "20",
+                "-i",
+                "https://example.com/a.m3u8",
+                "-map",
+                "0:v:0",
+                "-map",
+                "1:a:0",
+            ]
+            .map(String::from)
+        );
+
+        let desde_inicio = FuenteStream {
+            desde_inicio: true,
+            ..fuente
+        };
+        assert_eq!(
+            input_args(desde_inicio, &[])[..6],
+            [
+                "-live_start_index",
+                "0",
+                "-i",
+                "https://example.com/v.m3u8",
+                "-live_start_index",
+                "0"
+            ]
+            .map(String::from)
+        );
+    }
+
+    #[test]
+    fn proxy_arguments_accept_only_http_proxies() {
+        assert!(proxy_args(None).unwrap().is_empty());
+        assert_eq!(
+            proxy_args(Some("http://10.0.0.1:3128")).unwrap(),
+            vec![
+                "-http_proxy".to_string(),
+                "http://10.0.0.1:3128".to_string()
+            ]
+        );
+        assert!(proxy_args(Some("socks5://10.0.0.1:1080"))
+            .unwrap_err()
+            .to_string()
+            .contains("socks5://"));
+    }
+
+    #[test]
+    fn rolling_window_writes_hls_segments_next_to_playlist() {
+        let playlist = Path::new("/tmp/buffer/buffer.m3u8");
+        let destino = DestinoFfmpeg::Ventana {
+            playlist,
+            segundos_segmento: 6,
+            segmentos: 50,
+        };
+
+        let opciones_salida = ["-movflags", "+faststart"].map(String::from);
+
+        assert_eq!(
+            destino.args(&opciones_salida),
+            [
+                "-c",
+                "copy",
+                "-f",
+                "hls",
+                "-hls_time",
+                "6",
+                "-hls_list_size",
+                "50",
+                "-hls_flags",
+                "delete_segments",
+                "-hls_segment_filename",
+                "/tmp/buffer/seg_%06d.ts",
+                "-y",
+                "/tmp/buffer/buffer.m3u8"
+            ]
+            .map(String::from)
+        );
+        assert_eq!(
+            DestinoFfmpeg::from(Path::new("/tmp/a.mp4")).args(&opciones_salida),
+            ["-c", "copy", "-movflags", "+faststart", "-y", "/tmp/a.mp4"].map(String::from)
+        );
+    }
+
+    #[test]
+    fn audio_files_drop_video_and_pick_codec_by_extension() {
+        assert_eq!(
+            DestinoFfmpeg::from(Path::new("/tmp/a.part.m4a")).args(&[]),
+            ["-vn", "-c:a", "copy", "-y", "/tmp/a.part.m4a"].map(String::from)
+        );
+        assert_eq!(
+            DestinoFfmpeg::from(Path::new("/tmp/a.part.opus")).args(&[]),
+            [
+                "-vn",
+                "-c:a",
+                "libopus",
+                "-b:a",
+                "96k",
+                "-y",
+                "/tmp/a.part.opus"
+            ]
+            .map(String::from)
+        );
+    }
+
+    #[test]
+    fn profiles_accept_option_pairs_but_not_outputs_or_cookies() {
+        let args = |valores: &[&str]| valores.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        for valido in [
+            args(&[
+                "-reconnect",
+                "1",
+                "-reconnect_streamed",
+                "1",
+                "-rw_timeout",
+                "15000000",
+            ]),
+            args(&["-bsf:a", "aac_adtstoasc", "-movflags", "+faststart"]),
+            args(&[
+                "-metadata",
+                "title=show",
+                "-map",
+                "0",
+                "-stream_loop",
+                "-1",
+                "-an",
+            ]),
+        ] {
+            assert_eq!(validar_args_perfil(&valido), Ok(()), "{valido:?}");
+        }
+        for invalido in [
+            args(&["/tmp/otra.mp4"]),
+            args(&["-an", "/tmp/otra.mp4"]),
+            args(&["-an", "copia.mkv"]),
+            args(&["-dn", "-vn", "copia.mkv"]),
+            args(&["-desconocida", "copia.mkv"]),
+            args(&["-movflags"]),
+            args(&["-movflags", "+faststart", "copia.mp4"]),
+            args(&["-y"]),
+            args(&["-f", "flv"]),
+            args(&["-i", "https://example.com/otro.m3u8"]),
+            args(&["-loglevel", "debug"]),
+            args(&["-headers", "X-Test: 1"]),
+            args(&["-metadata", "comment=Cookie: x"]),
+        ] {
+            assert!(validar_args_perfil(&invalido).is_err(), "{invalido:?}");
+        }
+    }
+
     #[test]
     fn empty_stderr_has_no_summary() {
         assert_eq!(summarize_stderr(b"\n  \n"), None);
@@ -385,58 +936,86 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn stalled_file_is_detected() {
-        let path = std::env::temp_dir().join(format!(
-            "cbrec_stall_{}.part.mp4",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|duration| duration.as_nanos())
-                .unwrap_or_default()
-        ));
-        tokio::fs::write(&path, b"data")
-            .await
-            .expect("create partial file");
+    async fn stalled_media_time_is_detected() {
+        let (_avance_tx, avance_rx) = watch::channel(ProgresoGrabacion {
+            bytes: 4096,
+            tiempo_media: Duration::from_secs(3),
+            ..Default::default()
+        });
 
         tokio::time::timeout(
             Duration::from_millis(100),
-            wait_for_stall(&path, Duration::from_millis(20), Duration::from_millis(5)),
+            wait_for_stall(
+                avance_rx,
+                Duration::from_millis(20),
+                Duration::from_millis(5),
+            ),
         )
         .await
-        .expect("stalled file must be detected within the test deadline");
-
-        let _ = tokio::fs::remove_file(path).await;
+        .expect("stalled media time must be detected within the test deadline");
     }
 
     #[test]
-    fn file_growth_resets_stall_deadline() {
+    fn media_time_progress_resets_stall_deadline() {
         let started = Instant::now();
         let timeout = Duration::from_millis(40);
-        let mut last_size = 4;
-        let mut last_change = started;
+        let mut ultimo = Duration::from_secs(4);
+        let mut ultimo_cambio = started;
 
-        assert!(!file_has_stalled(
-            &mut last_size,
-            &mut last_change,
-            9,
+        assert!(!media_estancada(
+            &mut ultimo,
+            &mut ultimo_cambio,
+            Duration::from_secs(9),
             started + Duration::from_millis(25),
             timeout,
         ));
-        assert!(!file_has_stalled(
-            &mut last_size,
-            &mut last_change,
-            9,
+        assert!(!media_estancada(
+            &mut ultimo,
+            &mut ultimo_cambio,
+            Duration::from_secs(9),
             started + Duration::from_millis(64),
             timeout,
         ));
-        assert!(file_has_stalled(
-            &mut last_size,
-            &mut last_change,
-            9,
+        assert!(media_estancada(
+            &mut ultimo,
+            &mut ultimo_cambio,
+            Duration::from_secs(9),
             started + Duration::from_millis(65),
             timeout,
         ));
     }
 
+    #[tokio::test]
+    async fn progress_blocks_are_parsed_and_published() {
+        let salida: &[u8] = b"frame=250\nfps=25.00\nbitrate=2500.5kbits/s\n\
+total_size=3145728\nout_time_us=10000000\ndrop_frames=2\nspeed=1.01x\n\
+progress=continue\nframe=260\nbitrate=N/A\nout_time_us=N/A\nspeed=N/A\n";
+        let (avance_tx, avance_rx) = watch::channel(ProgresoGrabacion::default());
+        let (externo_tx, externo_rx) = watch::channel(ProgresoGrabacion::default());
+
+        leer_progreso(salida, avance_tx, Some(Arc::new(externo_tx))).await;
+
+        let esperado = ProgresoGrabacion {
+            bytes: 3_145_728,
+            tiempo_media: Duration::from_secs(10),
+            bitrate_kbps: Some(2500.5),
+            velocidad: Some(1.01),
+            frames: 250,
+            frames_perdidos: 2,
+        };
+        assert_eq!(*avance_rx.borrow(), esperado);
+        assert_eq!(*externo_rx.borrow(), esperado);
+
+        let mut siguiente = esperado;
+        for linea in ["frame=260", "bitrate=N/A", "out_time_us=N/A", "speed=N/A"] {
+            assert!(!aplicar_linea_progreso(&mut siguiente, linea));
+        }
+        assert!(aplicar_linea_progreso(&mut siguiente, "progress=end"));
+        assert_eq!(siguiente.frames, 260);
+        assert_eq!(siguiente.bitrate_kbps, Some(2500.5));
+        assert_eq!(siguiente.tiempo_media, Duration::from_secs(10));
+    }
+
     #[tokio::test]
     async fn file_size_distinguishes_existing_and_missing_files() {
         let path = std::env::temp_dir().join(format!(
@@ -542,12 +1121,11 @@ mod tests {
 
         let result = run_ffmpeg(
             Path::new("true"),
-            "https://example.com/live.m3u8",
-            &output,
-            None,
-            None,
-            0,
-            None,
+            "https://example.com/live.m3u8".into(),
+            output.as_path().into(),
+            EntradaHttp::default(),
+            &PerfilFfmpeg::default(),
+            ControlFfmpeg::default(),
         )
         .await;
 
@@ -561,12 +1139,11 @@ mod tests {
 
         let error = run_ffmpeg(
             Path::new("false"),
-            "https://example.com/live.m3u8",
-            &output,
-            None,
-            None,
-            0,
-            None,
+            "https://example.com/live.m3u8".into(),
+            output.as_path().into(),
+            EntradaHttp::default(),
+            &PerfilFfmpeg::default(),
+            ControlFfmpeg::default(),
         )
         .await
         .expect_err("non-zero process must fail")
@@ -584,12 +1161,14 @@ mod tests {
 
         let result = run_ffmpeg(
             &executable,
-            "https://example.com/live.m3u8",
-            &output,
-            None,
-            None,
-            0,
-            Some(cancel_rx),
+            "https://example.com/live.m3u8".into(),
+            output.as_path().into(),
+            EntradaHttp::default(),
+            &PerfilFfmpeg::default(),
+            ControlFfmpeg {
+                cancel_rx: Some(cancel_rx),
+                ..Default::default()
+            },
         )
         .await;
 
@@ -600,6 +1179,20 @@ mod tests {
         drop(cancel_tx);
     }
 
+    #[tokio::test]
+    async fn stop_signal_triggers_combined_receiver() {
+        let (_cancel_tx, cancel_rx) = watch::channel(false);
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let mut combinada = combinar_senales(Some(cancel_rx), Some(stop_rx)).expect("senal");
+
+        stop_tx.send(true).expect("envia parada");
+
+        tokio::time::timeout(Duration::from_secs(1), combinada.wait_for(|v| *v))
+            .await
+            .expect("la senal combinada se activa")
+            .expect("canal abierto");
+    }
+
     #[tokio::test]
     async fn missing_executable_reports_start_failure() {
         let executable = std::env::temp_dir().join("cbrec_missing_ffmpeg_executable");
@@ -607,12 +1200,11 @@ mod tests {
 
         let error = run_ffmpeg(
             &executable,
-            "https://example.com/live.m3u8",
-            &output,
-            None,
-            None,
-            0,
-            None,
+            "https://example.com/live.m3u8".into(),
+            output.as_path().into(),
+            EntradaHttp::default(),
+            &PerfilFfmpeg::default(),
+            ControlFfmpeg::default(),
         )
         .await
         .expect_err("missing executable must fail")
@@ -628,12 +1220,14 @@ mod tests {
 
         let error = run_ffmpeg(
             &executable,
-            "https://example.com/live.m3u8",
-            &output,
-            None,
-            None,
-            u64::MAX,
-            None,
+            "https://example.com/live.m3u8".into(),
+            output.as_path().into(),
+            EntradaHttp::default(),
+            &PerfilFfmpeg::default(),
+            ControlFfmpeg {
+                min_free_space: u64::MAX,
+                ..Default::default()
+            },
         )
         .await
         .expect_err("low disk must fail before process start")
@@ -642,4 +1236,28 @@ mod tests {
         assert!(error.contains("espacio insuficiente"));
         assert!(!error.contains("Failed to start ffmpeg"));
     }
+
+    #[test]
+    fn ffmpeg_exit_is_classified_by_stderr_and_output() {
+        let playlist = clasificar_salida_ffmpeg(
+            "salida".to_string(),
+            b"[https @ 0x1] HTTP error 404 Not Found",
+            1024,
+        );
+        assert_eq!(playlist.clase_fallo(), ClaseFallo::PlaylistCambiada);
+
+        let edge = clasificar_salida_ffmpeg(
+            "salida".to_string(),
+            b"Server returned 5XX Server Error reply",
+            1024,
+        );
+        assert!(matches!(edge, InfrastructureError::EdgeFailed(_)));
+        assert!(edge.requiere_nueva_url());
+
+        let arranque = clasificar_salida_ffmpeg("salida".to_string(), b"Invalid argument", 0);
+        assert_eq!(arranque.clase_fallo(), ClaseFallo::Arranque);
+
+        let otro = clasificar_salida_ffmpeg("salida".to_string(), b"Invalid data", 1024);
+        assert_eq!(otro.clase_fallo(), ClaseFallo::Otro);
+    }
 }
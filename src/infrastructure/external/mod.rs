@@ -1,5 +1,11 @@
 pub mod chaturbate_client;
 mod ffmpeg_process;
+mod rate_governor;
 
-pub(crate) use chaturbate_client::DiscoveredRoom;
-pub use chaturbate_client::{CalidadDisponible, ChaturbateClient, EstadoStream};
+pub(crate) use chaturbate_client::DEFAULT_USER_AGENT;
+pub(crate) use chaturbate_client::{segundos_en_playlist, DiscoveredRoom};
+pub use chaturbate_client::{
+    ChaturbateClient, EstadoStream, OpcionesHttp, PoliticaReintentos, PreferenciaIp,
+};
+pub(crate) use ffmpeg_process::validar_args_perfil;
+pub use rate_governor::{EstadoCircuito, LimitePeticiones};
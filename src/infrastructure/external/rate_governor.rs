@@ -0,0 +1,275 @@
+//! Shared request governor for every clone of `ChaturbateClient`.
+//!
+//! A token bucket spaces outgoing API requests and a circuit breaker stops
+//! all of them after repeated 429 or block responses. Once the pause ends a
+//! single probe request is let through: success closes the circuit, another
+//! rate limit reopens it with a doubled pause.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Limits applied by the governor; see `[network]` in the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LimitePeticiones {
+    pub por_minuto: u32,
+    pub rafaga: u32,
+    /// Consecutive 429/block responses that open the circuit.
+    pub umbral_circuito: u32,
+    pub pausa_base: Duration,
+    pub pausa_max: Duration,
+}
+
+impl Default for LimitePeticiones {
+    fn default() -> Self {
+        Self {
+            por_minuto: 120,
+            rafaga: 10,
+            umbral_circuito: 3,
+            pausa_base: Duration::from_secs(60),
+            pausa_max: Duration::from_secs(900),
+        }
+    }
+}
+
+/// Snapshot of the circuit breaker for watch and doctor output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EstadoCircuito {
+    Cerrado,
+    Abierto { restante: Duration },
+    SemiAbierto,
+}
+
+#[derive(Debug)]
+enum Circuito {
+    Cerrado { fallos: u32 },
+    Abierto { hasta: Instant },
+    SemiAbierto { sonda_en_curso: bool },
+}
+
+#[derive(Debug)]
+struct Estado {
+    tokens: f64,
+    repuesto: Instant,
+    circuito: Circuito,
+    /// Times the circuit opened since it was last closed; drives the backoff.
+    aperturas: u32,
+}
+
+/// Outcome of asking the governor for permission to send one request.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Permiso {
+    Enviar,
+    Esperar(Duration),
+    Rechazar(Duration),
+}
+
+#[derive(Debug)]
+pub(crate) struct Gobernador {
+    limite: LimitePeticiones,
+    estado: Mutex<Estado>,
+}
+
+impl Gobernador {
+    pub(crate) fn new(limite: LimitePeticiones) -> Self {
+        Self {
+            limite,
+            estado: Mutex::new(Estado {
+                tokens: f64::from(limite.rafaga.max(1)),
+                repuesto: Instant::now(),
+                circuito: Circuito::Cerrado { fallos: 0 },
+                aperturas: 0,
+            }),
+        }
+    }
+
+    pub(crate) fn limite(&self) -> LimitePeticiones {
+        self.limite
+    }
+
+    /// Waits for a token; fails with the remaining pause while the circuit is open.
+    pub(crate) async fn adquirir(&self) -> Result<(), Duration> {
+        loop {
+            match self.solicitar(Instant::now()) {
+                Permiso::Enviar => return Ok(()),
+                Permiso::Esperar(espera) => tokio::time::sleep(espera).await,
+                Permiso::Rechazar(restante) => return Err(restante),
+            }
+        }
+    }
+
+    pub(crate) fn solicitar(&self, ahora: Instant) -> Permiso {
+        let mut estado = self.bloquear();
+        match estado.circuito {
+            Circuito::Abierto { hasta } if ahora < hasta => {
+                return Permiso::Rechazar(hasta - ahora);
+            }
+            Circuito::Abierto { .. } => {
+                estado.circuito = Circuito::SemiAbierto {
+                    sonda_en_curso: true,
+                };
+                return Permiso::Enviar;
+            }
+            Circuito::SemiAbierto {
+                sonda_en_curso: true,
+            } => return Permiso::Rechazar(Duration::from_secs(1)),
+            Circuito::SemiAbierto {
+                sonda_en_curso: false,
+            } => {
+                estado.circuito = Circuito::SemiAbierto {
+                    sonda_en_curso: true,
+                };
+                return Permiso::Enviar;
+            }
+            Circuito::Cerrado { .. } => {}
+        }
+
+        let capacidad = f64::from(self.limite.rafaga.max(1));
+        let por_segundo = f64::from(self.limite.por_minuto.max(1)) / 60.0;
+        let transcurrido = ahora.saturating_duration_since(estado.repuesto);
+        estado.tokens = (estado.tokens + transcurrido.as_secs_f64() * por_segundo).min(capacidad);
+        estado.repuesto = ahora;
+        if estado.tokens >= 1.0 {
+            estado.tokens -= 1.0;
+            Permiso::Enviar
+        } else {
+            Permiso::Esperar(Duration::from_secs_f64((1.0 - estado.tokens) / por_segundo))
+        }
+    }
+
+    /// Any non-throttled response closes a half-open circuit and resets the count.
+    /// Late responses to requests sent before the circuit opened are ignored.
+    pub(crate) fn registrar_exito(&self) {
+        let mut estado = self.bloquear();
+        if let Circuito::Abierto { .. } = estado.circuito {
+            return;
+        }
+        estado.circuito = Circuito::Cerrado { fallos: 0 };
+        estado.aperturas = 0;
+    }
+
+    pub(crate) fn registrar_limite(&self, ahora: Instant) {
+        let mut estado = self.bloquear();
+        let abrir = match estado.circuito {
+            Circuito::Cerrado { fallos } => {
+                let fallos = fallos.saturating_add(1);
+                estado.circuito = Circuito::Cerrado { fallos };
+                fallos >= self.limite.umbral_circuito.max(1)
+            }
+            Circuito::SemiAbierto { .. } => true,
+            Circuito::Abierto { .. } => false,
+        };
+        if abrir {
+            let pausa = self
+                .limite
+                .pausa_base
+                .saturating_mul(2u32.saturating_pow(estado.aperturas.min(16)))
+                .min(self.limite.pausa_max);
+            estado.aperturas = estado.aperturas.saturating_add(1);
+            estado.circuito = Circuito::Abierto {
+                hasta: ahora.checked_add(pausa).unwrap_or(ahora),
+            };
+        }
+    }
+
+    /// Lets another probe through when the previous one failed without a response.
+    pub(crate) fn liberar_sonda(&self) {
+        let mut estado = self.bloquear();
+        if let Circuito::SemiAbierto { .. } = estado.circuito {
+            estado.circuito = Circuito::SemiAbierto {
+                sonda_en_curso: false,
+            };
+        }
+    }
+
+    pub(crate) fn estado(&self, ahora: Instant) -> EstadoCircuito {
+        match self.bloquear().circuito {
+            Circuito::Cerrado { .. } => EstadoCircuito::Cerrado,
+            Circuito::Abierto { hasta } if ahora < hasta => EstadoCircuito::Abierto {
+                restante: hasta - ahora,
+            },
+            Circuito::Abierto { .. } | Circuito::SemiAbierto { .. } => EstadoCircuito::SemiAbierto,
+        }
+    }
+
+    fn bloquear(&self) -> std::sync::MutexGuard<'_, Estado> {
+        self.estado.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gobernador(por_minuto: u32, rafaga: u32) -> Gobernador {
+        Gobernador::new(LimitePeticiones {
+            por_minuto,
+            rafaga,
+            umbral_circuito: 2,
+            pausa_base: Duration::from_secs(10),
+            pausa_max: Duration::from_secs(25),
+        })
+    }
+
+    #[test]
+    fn token_bucket_spaces_requests_after_burst() {
+        let gobernador = gobernador(60, 2);
+        let ahora = Instant::now();
+
+        assert_eq!(gobernador.solicitar(ahora), Permiso::Enviar);
+        assert_eq!(gobernador.solicitar(ahora), Permiso::Enviar);
+        assert_eq!(
+            gobernador.solicitar(ahora),
+            Permiso::Esperar(Duration::from_secs(1))
+        );
+        assert_eq!(
+            gobernador.solicitar(ahora + Duration::from_secs(1)),
+            Permiso::Enviar
+        );
+    }
+
+    #[test]
+    fn circuit_opens_after_threshold_and_probes_once() {
+        let gobernador = gobernador(600, 10);
+        let ahora = Instant::now();
+
+        gobernador.registrar_limite(ahora);
+        assert_eq!(gobernador.estado(ahora), EstadoCircuito::Cerrado);
+        gobernador.registrar_limite(ahora);
+        assert_eq!(
+            gobernador.solicitar(ahora),
+            Permiso::Rechazar(Duration::from_secs(10))
+        );
+
+        let despues = ahora + Duration::from_secs(10);
+        assert_eq!(gobernador.solicitar(despues), Permiso::Enviar);
+        assert!(matches!(
+            gobernador.solicitar(despues),
+            Permiso::Rechazar(_)
+        ));
+        assert_eq!(gobernador.estado(despues), EstadoCircuito::SemiAbierto);
+
+        gobernador.registrar_exito();
+        assert_eq!(gobernador.estado(despues), EstadoCircuito::Cerrado);
+        assert_eq!(gobernador.solicitar(despues), Permiso::Enviar);
+    }
+
+    #[test]
+    fn failed_probe_reopens_with_capped_backoff() {
+        let gobernador = gobernador(600, 10);
+        let mut ahora = Instant::now();
+        gobernador.registrar_limite(ahora);
+        gobernador.registrar_limite(ahora);
+
+        for esperado in [20, 25] {
+            ahora += Duration::from_secs(30);
+            assert_eq!(gobernador.solicitar(ahora), Permiso::Enviar);
+            gobernador.registrar_limite(ahora);
+            assert_eq!(
+                gobernador.estado(ahora),
+                EstadoCircuito::Abierto {
+                    restante: Duration::from_secs(esperado)
+                }
+            );
+        }
+    }
+}
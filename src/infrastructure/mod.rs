@@ -2,7 +2,12 @@ pub mod config;
 pub mod errors;
 pub mod external;
 
-pub(crate) use config::expandir_tilde;
-pub use config::{AppConfig, ConfigWarning, LoadedAppConfig, WatchConfig, WatchedModels};
-pub use errors::InfrastructureError;
+pub(crate) use config::{expandir_tilde, normalizar_proxy, ocultar_credenciales_proxy};
+pub use config::{
+    ActividadPrevista, AppConfig, BufferConfig, ConfigWarning, DatosNombre, DiscoveryConfig,
+    DiscoveryRule, EstrategiaReintento, FfmpegConfig, LoadedAppConfig, ModelRuntimeState,
+    NetworkConfig, OnlineHistory, PerfilFfmpeg, PlantillaNombre, RecordingConfig, RetryConfig,
+    RetryPolicy, VolumenSalida, WatchConfig, WatchState, WatchedModels,
+};
+pub use errors::{ClaseFallo, InfrastructureError};
 pub use external::{ChaturbateClient, EstadoStream};
@@ -24,10 +24,19 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub ffmpeg_path: Option<String>,
 
-    /// Calidad de video (240p, 480p, 720p, 1080p, best).
+    /// Perfil de argumentos de FFmpeg definido en `[ffmpeg.profiles]` (sobreescribe config).
+    #[arg(long, global = true, value_name = "NAME")]
+    pub ffmpeg_profile: Option<String>,
+
+    /// Calidad de video (240p, 480p, 720p, 1080p, best); varias separadas por
+    /// coma (ej: best,480p) se graban en paralelo.
     #[arg(short, long, default_value = "best")]
     pub quality: String,
 
+    /// Proxy HTTP o SOCKS para la API y FFmpeg (`none` fuerza conexion directa).
+    #[arg(long, global = true, value_name = "URL")]
+    pub proxy: Option<String>,
+
     /// Limite de grabaciones simultaneas.
     #[arg(short = 'j', long, global = true)]
     pub jobs: Option<usize>,
@@ -36,6 +45,30 @@ pub struct Cli {
     #[arg(long, global = true, value_name = "SECS")]
     pub duration: Option<u64>,
 
+    /// Bitrate maximo de la variante a grabar (ej: 3M, 800k).
+    #[arg(long, global = true, value_name = "RATE")]
+    pub max_bitrate: Option<String>,
+
+    /// Codec de video preferido (h264, hevc).
+    #[arg(long, global = true, value_name = "CODEC")]
+    pub codec: Option<String>,
+
+    /// Frames por segundo preferidos.
+    #[arg(long, global = true, value_name = "FPS")]
+    pub fps: Option<u32>,
+
+    /// Falla si ninguna variante cumple calidad, bitrate, codec y fps en vez de usar la mas cercana.
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// Formato de las grabaciones de solo audio: m4a u opus (sobreescribe config).
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub audio_format: Option<String>,
+
+    /// Empieza por el segmento mas antiguo de la playlist en vivo para recuperar el comienzo.
+    #[arg(long, global = true)]
+    pub from_start: bool,
+
     /// Cookie de sesion de Chaturbate (sobreescribe config).
     /// Ejemplo: "PHPSESSID=abc123; chaturbatesid=xyz"
     /// Obtenerla: DevTools (F12) → Application → Cookies → chaturbate.com
@@ -64,7 +97,8 @@ pub enum Commands {
         /// Directorio base de salida.
         #[arg(short, long)]
         output: Option<String>,
-        /// Calidad de video (240p, 480p, 720p, 1080p, best).
+        /// Calidad de video (240p, 480p, 720p, 1080p, best); varias separadas por
+        /// coma (ej: best,480p) se graban en paralelo.
         #[arg(short, long, default_value = "best")]
         quality: String,
     },
@@ -94,6 +128,18 @@ pub enum Commands {
         json: bool,
     },
 
+    /// Recomienda modelos similares a otro modelo.
+    Similar {
+        /// Nombre o URL del modelo de referencia.
+        model: String,
+        /// Numero maximo de resultados (1-50).
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Emite una unica respuesta JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Explora modelos por tag en una interfaz interactiva.
     Tui {
         /// Tag a buscar, con o sin `#`.
@@ -118,9 +164,44 @@ pub enum Commands {
         /// Directorio base de salida.
         #[arg(short, long)]
         output: Option<String>,
+        /// Calidad de video (240p, 480p, 720p, 1080p, best); varias separadas por
+        /// coma (ej: best,480p) se graban en paralelo.
+        #[arg(short, long, default_value = "best")]
+        quality: String,
+        /// Ignorar cooldowns, omitidos y ritmo de consultas guardados de la ejecucion anterior.
+        #[arg(long)]
+        fresh: bool,
+        /// Añadir cada cambio de estado de los modelos a FILE (JSON por linea).
+        #[arg(long, value_name = "FILE")]
+        timeline: Option<String>,
+    },
+
+    /// Mantiene en disco los ultimos minutos de cada modelo para guardarlos con `clip`.
+    Buffer {
+        /// Modelos o URLs.
+        #[arg(value_name = "MODEL", num_args = 1..)]
+        modelos: Vec<String>,
+        /// Directorio base de salida.
+        #[arg(short, long)]
+        output: Option<String>,
         /// Calidad de video (240p, 480p, 720p, 1080p, best).
         #[arg(short, long, default_value = "best")]
         quality: String,
+        /// Minutos que conserva la ventana (sobreescribe config).
+        #[arg(long, value_name = "MINS")]
+        minutes: Option<u64>,
+    },
+
+    /// Guarda el buffer activo de un modelo mas los minutos siguientes.
+    Clip {
+        /// Nombre o URL del modelo.
+        model: String,
+        /// Minutos a seguir grabando tras la peticion (sobreescribe config).
+        #[arg(long, value_name = "MINS")]
+        after: Option<u64>,
+        /// Directorio base de salida usado por `buffer`.
+        #[arg(short, long)]
+        output: Option<String>,
     },
 
     /// Añade modelos a la lista de seguimiento persistente.
@@ -136,4 +217,36 @@ pub enum Commands {
         #[arg(value_name = "MODEL", num_args = 1..)]
         models: Vec<String>,
     },
+
+    /// Muestra el mapa semanal de horas en que `watch` vio online a un modelo.
+    Stats {
+        /// Nombre o URL del modelo.
+        model: String,
+    },
+
+    /// Mantiene la biblioteca de grabaciones.
+    Library {
+        #[command(subcommand)]
+        command: LibraryCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum LibraryCommands {
+    /// Mueve las grabaciones a la ruta que da la plantilla de nombres actual.
+    Reorganize {
+        /// Directorio base de salida.
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Muestra los movimientos sin tocar ningun archivo.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Devuelve las grabaciones a su sitio usando el registro de una reorganizacion.
+    Rollback {
+        /// Registro `.cbrec-reorganize-*.jsonl` creado por `reorganize`.
+        #[arg(value_name = "LOG")]
+        log: String,
+    },
 }
@@ -2,6 +2,6 @@ pub mod cli;
 pub mod output;
 pub(crate) mod tui;
 
-pub use cli::{Cli, Commands};
+pub use cli::{Cli, Commands, LibraryCommands};
 pub use output::{ConsoleOutput, Output};
-pub(crate) use tui::{run_discovery_tui, TuiRoom};
+pub(crate) use tui::{run_discovery_tui, DiscoverySource, TuiRoom};
@@ -1,5 +1,6 @@
+use crate::domain::value_objects::{ProgresoGrabacion, VarianteDisponible};
 use colored::Colorize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub trait Output: Send + Sync {
     fn is_verbose(&self) -> bool {
@@ -38,8 +39,8 @@ pub trait Output: Send + Sync {
     fn mostrar_estado_modelo(&self, modelo: &str, online: bool);
     fn mostrar_estado_modelo_detalle(&self, _modelo: &str, _estado: &str, _detalle: &str) {}
     fn mostrar_modelo_sin_variantes(&self, modelo: &str);
-    fn mostrar_calidades(&self, modelo: &str, calidades: &[(Option<u32>, Option<u64>)]);
-    fn mostrar_progreso_grabacion(&self, _modelo: &str, _bytes: u64) {}
+    fn mostrar_calidades(&self, modelo: &str, calidades: &[VarianteDisponible]);
+    fn mostrar_progreso_grabacion(&self, _modelo: &str, _progreso: &ProgresoGrabacion) {}
     fn doctor_inicio(&self) {}
     fn doctor_ok(&self, _area: &str, _detalle: &str) {}
     fn doctor_warn(&self, _area: &str, _detalle: &str) {}
@@ -48,6 +49,10 @@ pub trait Output: Send + Sync {
     fn discovery_started(&self, _tag: &str, _count: usize) {}
     fn discovery_room(&self, _username: &str, _viewers: u64, _show: &str, _subject: &str) {}
     fn discovery_empty(&self, _tag: &str) {}
+    fn similar_started(&self, _model: &str, _count: usize) {}
+    fn similar_empty(&self, _model: &str) {}
+    fn stats_mapa_actividad(&self, _modelo: &str, _horas: &[u32]) {}
+    fn stats_sin_datos(&self, _modelo: &str) {}
     fn watch_inicio(&self, modelos: &[&str]);
     fn watch_tick_online(&self, modelo: &str);
     fn watch_tick_offline(&self, modelo: &str);
@@ -56,6 +61,37 @@ pub trait Output: Send + Sync {
     fn watch_modelo_omitido(&self, modelo: &str);
     fn watch_proximo_check(&self, secs: u64);
     fn watch_deteniendo(&self);
+    fn watch_reglas_descubrimiento(&self, _reglas: &[String]) {}
+    fn watch_objetivo_dinamico(&self, _modelo: &str, _regla: &str) {}
+    fn watch_objetivo_expirado(&self, _modelo: &str) {}
+    fn watch_pausa_rate_limit(&self, _secs: u64) {}
+    fn watch_pausa_finalizada(&self) {}
+    fn watch_tick_privado(&self, _modelo: &str, _detalle: &str) {}
+    fn grabacion_sala_restringida(&self, _modelo: &str, _detalle: &str) {}
+    fn grabacion_sala_reanudada(&self, _modelo: &str) {}
+    fn grabacion_url_renovada(&self, _modelo: &str, _motivo: &str) {}
+    fn grabacion_inicio_recuperado(&self, _modelo: &str, _secs: u64) {}
+    fn grabacion_archivo_reintento(&self, _modelo: &str, _error: &str, _secs: u64) {}
+    fn grabacion_volumen_lleno(&self, _modelo: &str, _siguiente: &Path) {}
+    fn grabacion_archivo_en_staging(&self, _modelo: &str, _ruta: &Path, _error: &str) {}
+    fn watch_grabaciones_pausadas(&self, _modelo: &str, _clase: &str, _secs: u64) {}
+    fn watch_reintentos_agotados(&self, _modelo: &str, _clase: &str, _intentos: u64) {}
+    fn mostrar_reintento_grabacion(&self, _modelo: &str, _clase: &str, _secs: u64) {}
+    fn buffer_activo(&self, _modelo: &str, _ventana_mins: u64) {}
+    fn buffer_clip_iniciado(&self, _modelo: &str, _previos_secs: u64, _siguientes_secs: u64) {}
+    fn clip_solicitado(&self, _modelo: &str, _siguientes_mins: u64) {}
+    fn biblioteca_movimiento(&self, _origen: &Path, _destino: &Path, _simulado: bool) {}
+    fn biblioteca_omitido(&self, _ruta: &Path, _motivo: &str) {}
+    fn biblioteca_error(&self, _ruta: &Path, _error: &str) {}
+    fn biblioteca_resumen(
+        &self,
+        _movidos: usize,
+        _sin_cambios: usize,
+        _errores: usize,
+        _registros: &[PathBuf],
+        _simulado: bool,
+    ) {
+    }
 }
 
 pub struct ConsoleOutput {
@@ -299,20 +335,27 @@ impl Output for ConsoleOutput {
         println!("[{}] Sin variantes en playlist", modelo.cyan());
     }
 
-    fn mostrar_calidades(&self, modelo: &str, calidades: &[(Option<u32>, Option<u64>)]) {
-        println!("[{}] {}", modelo.cyan(), formatear_calidades(calidades));
+    fn mostrar_calidades(&self, modelo: &str, calidades: &[VarianteDisponible]) {
+        println!("[{}] {} variante(s):", modelo.cyan(), calidades.len());
+        for variante in calidades {
+            let linea = formatear_variante(variante);
+            if variante.seleccionada {
+                println!("  {} {}", "*".green().bold(), linea.green());
+            } else {
+                println!("    {}", linea);
+            }
+        }
     }
 
-    fn mostrar_progreso_grabacion(&self, modelo: &str, bytes: u64) {
+    fn mostrar_progreso_grabacion(&self, modelo: &str, progreso: &ProgresoGrabacion) {
         if self.quiet {
             return;
         }
-        let mb = bytes as f64 / 1_048_576.0;
         println!(
             "[{}][{}] Grabando... {}",
             ahora().bright_black(),
             modelo.cyan(),
-            format!("{:.1} MB", mb).bright_blue()
+            formatear_progreso(progreso).bright_blue()
         );
     }
 
@@ -375,6 +418,37 @@ impl Output for ConsoleOutput {
         println!("Sin resultados para #{}", tag.cyan());
     }
 
+    fn similar_started(&self, model: &str, count: usize) {
+        if !self.quiet {
+            println!("{} modelo(s) similares a {}", count, model.cyan());
+        }
+    }
+
+    fn similar_empty(&self, model: &str) {
+        println!("Sin recomendaciones para {}", model.cyan());
+    }
+
+    fn stats_mapa_actividad(&self, modelo: &str, horas: &[u32]) {
+        println!(
+            "Actividad semanal de {} (horas vistas online, hora local)",
+            modelo.cyan()
+        );
+        for linea in renderizar_mapa_actividad(horas) {
+            println!("{}", linea);
+        }
+        let mejores = mejores_horas(horas, 3);
+        if !mejores.is_empty() {
+            println!("Horas habituales: {}", mejores.join(", ").green());
+        }
+    }
+
+    fn stats_sin_datos(&self, modelo: &str) {
+        println!(
+            "[{}] Sin historial todavia; `cbrec watch` lo aprende al verlo online",
+            modelo.cyan()
+        );
+    }
+
     fn watch_inicio(&self, modelos: &[&str]) {
         if self.quiet {
             return;
@@ -452,20 +526,345 @@ impl Output for ConsoleOutput {
             "Deteniendo daemon watch...".yellow()
         );
     }
+
+    fn watch_reglas_descubrimiento(&self, reglas: &[String]) {
+        if self.quiet {
+            return;
+        }
+        println!("Reglas de descubrimiento: {}", reglas.join(", ").magenta());
+    }
+
+    fn watch_objetivo_dinamico(&self, modelo: &str, regla: &str) {
+        println!(
+            "[{}][{}] {} {}",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "Objetivo temporal por".magenta(),
+            sanitize_external_text(regla).magenta()
+        );
+    }
+
+    fn watch_objetivo_expirado(&self, modelo: &str) {
+        if self.quiet {
+            return;
+        }
+        println!(
+            "[{}][{}] {}",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "Objetivo temporal expirado".bright_black()
+        );
+    }
+
+    fn watch_pausa_rate_limit(&self, secs: u64) {
+        println!(
+            "[{}] {} {} s",
+            ahora().bright_black(),
+            "Limite de peticiones alcanzado; consultas pausadas durante".yellow(),
+            secs
+        );
+    }
+
+    fn watch_pausa_finalizada(&self) {
+        println!(
+            "[{}] {}",
+            ahora().bright_black(),
+            "Consultas reanudadas tras la pausa por limite".green()
+        );
+    }
+
+    fn watch_grabaciones_pausadas(&self, modelo: &str, clase: &str, secs: u64) {
+        println!(
+            "[{}][{}] {} ({}); {} {}s",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "Grabaciones pausadas".red().bold(),
+            clase,
+            "sin nuevas grabaciones durante".red(),
+            secs
+        );
+    }
+
+    fn watch_reintentos_agotados(&self, modelo: &str, clase: &str, intentos: u64) {
+        println!(
+            "[{}][{}] {} ({} fallos seguidos de tipo {})",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "Se deja de vigilar".red(),
+            intentos,
+            clase
+        );
+    }
+
+    fn mostrar_reintento_grabacion(&self, modelo: &str, clase: &str, secs: u64) {
+        if secs == 0 {
+            println!(
+                "[{}][{}] {} ({})",
+                ahora().bright_black(),
+                modelo.cyan(),
+                "Reintentando grabacion".yellow(),
+                clase
+            );
+        } else {
+            println!(
+                "[{}][{}] {} {}s ({})",
+                ahora().bright_black(),
+                modelo.cyan(),
+                "Reintentando grabacion en".yellow(),
+                secs,
+                clase
+            );
+        }
+    }
+
+    fn grabacion_sala_restringida(&self, modelo: &str, detalle: &str) {
+        println!(
+            "[{}][{}] {} ({})",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "Show no publico; archivo cerrado, esperando a que vuelva".magenta(),
+            detalle
+        );
+    }
+
+    fn grabacion_sala_reanudada(&self, modelo: &str) {
+        println!(
+            "[{}][{}] {}",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "Show publico de nuevo; grabando en un archivo nuevo".green()
+        );
+    }
+
+    fn buffer_activo(&self, modelo: &str, ventana_mins: u64) {
+        if self.quiet {
+            return;
+        }
+        println!(
+            "[{}][{}] {}",
+            ahora().bright_black(),
+            modelo.cyan(),
+            format!("Online; guardando los ultimos {ventana_mins} min en el buffer").green()
+        );
+    }
+
+    fn buffer_clip_iniciado(&self, modelo: &str, previos_secs: u64, siguientes_secs: u64) {
+        println!(
+            "[{}][{}] {}",
+            ahora().bright_black(),
+            modelo.cyan(),
+            format!(
+                "Clip solicitado: {previos_secs} s del buffer y {siguientes_secs} s mas en directo"
+            )
+            .magenta()
+        );
+    }
+
+    fn clip_solicitado(&self, modelo: &str, siguientes_mins: u64) {
+        println!(
+            "[{}] Clip solicitado; el buffer lo guardara tras {} min mas",
+            modelo.cyan(),
+            siguientes_mins
+        );
+    }
+
+    fn biblioteca_movimiento(&self, origen: &Path, destino: &Path, simulado: bool) {
+        if self.quiet {
+            return;
+        }
+        let etiqueta = if simulado { "[SIMULADO]" } else { "[MOVIDO]" };
+        println!(
+            "{} {} -> {}",
+            etiqueta.yellow(),
+            origen.display().to_string().bright_black(),
+            destino.display()
+        );
+    }
+
+    fn biblioteca_omitido(&self, ruta: &Path, motivo: &str) {
+        eprintln!(
+            "{} {}: {}",
+            "[WARN]".yellow().bold(),
+            ruta.display(),
+            motivo
+        );
+    }
+
+    fn biblioteca_error(&self, ruta: &Path, error: &str) {
+        eprintln!("{} {}: {}", "[ERROR]".red().bold(), ruta.display(), error);
+    }
+
+    fn biblioteca_resumen(
+        &self,
+        movidos: usize,
+        sin_cambios: usize,
+        errores: usize,
+        registros: &[PathBuf],
+        simulado: bool,
+    ) {
+        let accion = if simulado { "se moverian" } else { "movidas" };
+        println!(
+            "{} grabacion(es) {}, {} ya en su sitio, {} error(es)",
+            movidos, accion, sin_cambios, errores
+        );
+        for registro in registros {
+            println!(
+                "Registro para deshacer: {}",
+                registro.display().to_string().cyan()
+            );
+        }
+    }
+
+    fn grabacion_inicio_recuperado(&self, modelo: &str, secs: u64) {
+        if self.quiet {
+            return;
+        }
+        println!(
+            "[{}][{}] {}",
+            ahora().bright_black(),
+            modelo.cyan(),
+            format!("Recuperando {secs} s anteriores desde la playlist en vivo").green()
+        );
+    }
+
+    fn grabacion_url_renovada(&self, modelo: &str, motivo: &str) {
+        println!(
+            "[{}][{}] {} ({})",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "URL del stream caducada; renovandola y siguiendo en un archivo nuevo".yellow(),
+            motivo
+        );
+    }
+
+    fn grabacion_volumen_lleno(&self, modelo: &str, siguiente: &Path) {
+        println!(
+            "[{}][{}] {} {}",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "Disco casi lleno; archivo cerrado, siguiendo en".yellow(),
+            siguiente.display()
+        );
+    }
+
+    fn grabacion_archivo_reintento(&self, modelo: &str, error: &str, secs: u64) {
+        println!(
+            "[{}][{}] {} ({})",
+            ahora().bright_black(),
+            modelo.cyan(),
+            format!("No se pudo mover la grabacion al archivo; reintentando en {secs}s").yellow(),
+            error
+        );
+    }
+
+    fn grabacion_archivo_en_staging(&self, modelo: &str, ruta: &Path, error: &str) {
+        eprintln!(
+            "[{}][{}] {} Grabacion conservada en staging: {}: {}",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "[ERROR]".red().bold(),
+            ruta.display(),
+            error
+        );
+    }
+
+    fn watch_tick_privado(&self, modelo: &str, detalle: &str) {
+        if self.quiet {
+            return;
+        }
+        println!(
+            "[{}][{}] {} ({})",
+            ahora().bright_black(),
+            modelo.cyan(),
+            "show no publico".magenta(),
+            detalle
+        );
+    }
 }
 
-fn formatear_calidades(calidades: &[(Option<u32>, Option<u64>)]) -> String {
-    let mut items: Vec<String> = calidades
-        .iter()
-        .map(|(height, bandwidth)| match (height, bandwidth) {
-            (Some(h), Some(bw)) => format!("{}p({}kbps)", h, bw / 1000),
-            (Some(h), None) => format!("{}p", h),
-            (None, Some(bw)) => format!("{}kbps", bw / 1000),
-            (None, None) => "desconocida".to_string(),
-        })
-        .collect();
-    items.sort();
-    items.join(", ")
+/// One `--list` row: resolution, bandwidth, frame rate and codecs.
+fn formatear_variante(variante: &VarianteDisponible) -> String {
+    let resolucion = variante
+        .height
+        .map_or_else(|| "audio".to_string(), |h| format!("{h}p"));
+    let bitrate = variante
+        .bandwidth
+        .map_or_else(|| "? kbps".to_string(), |bw| format!("{} kbps", bw / 1000));
+    let fps = variante
+        .frame_rate
+        .map_or_else(|| "- fps".to_string(), |fps| format!("{} fps", fps.round()));
+    let codecs = variante.codecs.as_deref().unwrap_or("codecs desconocidos");
+    match &variante.audio {
+        Some(audio) => {
+            format!("{resolucion:>6}  {bitrate:>10}  {fps:>7}  {codecs}  + audio {audio}")
+        }
+        None => format!("{resolucion:>6}  {bitrate:>10}  {fps:>7}  {codecs}"),
+    }
+}
+
+/// Size, media time, bitrate, speed and dropped frames of a recording.
+pub(crate) fn formatear_progreso(progreso: &ProgresoGrabacion) -> String {
+    let segundos = progreso.tiempo_media.as_secs();
+    let mut partes = vec![
+        format!("{:.1} MB", progreso.bytes as f64 / 1_048_576.0),
+        format!(
+            "{:02}:{:02}:{:02}",
+            segundos / 3600,
+            segundos / 60 % 60,
+            segundos % 60
+        ),
+    ];
+    if let Some(bitrate) = progreso.bitrate_kbps {
+        partes.push(format!("{bitrate:.0} kbps"));
+    }
+    if let Some(velocidad) = progreso.velocidad {
+        partes.push(format!("{velocidad:.2}x"));
+    }
+    if progreso.frames_perdidos > 0 {
+        partes.push(format!(
+            "{} de {} frames perdidos",
+            progreso.frames_perdidos, progreso.frames
+        ));
+    }
+    partes.join(", ")
+}
+
+const DIAS_SEMANA: [&str; 7] = ["Lun", "Mar", "Mie", "Jue", "Vie", "Sab", "Dom"];
+
+/// Renders 168 hourly counters as one shaded row per weekday.
+fn renderizar_mapa_actividad(horas: &[u32]) -> Vec<String> {
+    let maximo = horas.iter().copied().max().unwrap_or_default().max(1);
+    let mut cabecera = String::from("    ");
+    for hora in (0..24).step_by(3) {
+        cabecera.push_str(&format!("{:02}    ", hora));
+    }
+    let mut lineas = vec![cabecera.trim_end().to_string()];
+    for (dia, nombre) in DIAS_SEMANA.iter().enumerate() {
+        let mut linea = format!("{nombre} ");
+        for hora in 0..24 {
+            let valor = horas.get(dia * 24 + hora).copied().unwrap_or_default();
+            let celda = match valor * 4 / maximo {
+                _ if valor == 0 => "··",
+                0 => "░░",
+                1 => "▒▒",
+                2 | 3 => "▓▓",
+                _ => "██",
+            };
+            linea.push_str(celda);
+        }
+        lineas.push(linea);
+    }
+    lineas
+}
+
+fn mejores_horas(horas: &[u32], max: usize) -> Vec<String> {
+    let mut indices: Vec<usize> = (0..horas.len()).filter(|i| horas[*i] > 0).collect();
+    indices.sort_by(|a, b| horas[*b].cmp(&horas[*a]).then(a.cmp(b)));
+    indices
+        .into_iter()
+        .take(max)
+        .map(|i| format!("{} {:02}h", DIAS_SEMANA[(i / 24) % 7], i % 24))
+        .collect()
 }
 
 fn ahora() -> String {
@@ -489,11 +888,51 @@ pub(crate) fn sanitize_external_text(value: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::sanitize_external_text;
+    use super::{
+        formatear_progreso, mejores_horas, renderizar_mapa_actividad, sanitize_external_text,
+    };
+    use crate::domain::value_objects::ProgresoGrabacion;
+    use std::time::Duration;
 
     #[test]
     fn external_text_cannot_inject_terminal_controls() {
         assert_eq!(sanitize_external_text("ok\n\u{1b}[31m"), "ok  [31m");
         assert_eq!(sanitize_external_text(&"a".repeat(121)).len(), 120);
     }
+
+    #[test]
+    fn activity_map_shades_hours_relative_to_busiest_slot() {
+        let mut horas = vec![0u32; 168];
+        horas[20] = 4;
+        horas[24 + 21] = 1;
+
+        let lineas = renderizar_mapa_actividad(&horas);
+
+        assert_eq!(lineas.len(), 8);
+        assert!(lineas[0].starts_with("    00    03"));
+        assert_eq!(lineas[1].chars().nth(4 + 40), Some('█'));
+        assert_eq!(lineas[2].chars().nth(4 + 42), Some('▒'));
+        assert_eq!(mejores_horas(&horas, 3), ["Lun 20h", "Mar 21h"]);
+    }
+
+    #[test]
+    fn progress_line_shows_media_time_and_only_known_figures() {
+        let progreso = ProgresoGrabacion {
+            bytes: 3 * 1_048_576,
+            tiempo_media: Duration::from_secs(3725),
+            bitrate_kbps: Some(2499.6),
+            velocidad: None,
+            frames: 900,
+            frames_perdidos: 4,
+        };
+
+        assert_eq!(
+            formatear_progreso(&progreso),
+            "3.0 MB, 01:02:05, 2500 kbps, 4 de 900 frames perdidos"
+        );
+        assert_eq!(
+            formatear_progreso(&ProgresoGrabacion::default()),
+            "0.0 MB, 00:00:00"
+        );
+    }
 }
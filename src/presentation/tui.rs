@@ -1,7 +1,8 @@
-//! Renders a read-only tag discovery screen in an alternate terminal buffer.
+//! Renders an interactive tag discovery screen in an alternate terminal buffer.
 //!
-//! Data loading stays outside this module. It owns only terminal lifecycle,
-//! keyboard navigation, and rendering.
+//! Data loading and recording stay outside this module behind
+//! [`DiscoverySource`]. It owns only terminal lifecycle, keyboard handling,
+//! row filtering, and rendering.
 
 use crate::presentation::output::sanitize_external_text;
 use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
@@ -12,6 +13,8 @@ use ratatui::Frame;
 use std::io::{self, IsTerminal};
 use std::time::Duration;
 
+const MAX_INPUT_CHARS: usize = 50;
+
 /// Sanitized public room data ready for terminal rendering.
 pub(crate) struct TuiRoom {
     username: String,
@@ -30,17 +33,206 @@ impl TuiRoom {
             subject: sanitize_external_text(&subject),
         }
     }
+
+    fn matches(&self, filter: &str) -> bool {
+        filter.is_empty()
+            || self.username.contains(filter)
+            || self.subject.to_lowercase().contains(filter)
+    }
+}
+
+/// Loads discovery data and runs actions requested from the interactive view.
+pub(crate) trait DiscoverySource {
+    /// Returns the normalized tag and one page of public rooms for it.
+    fn rooms_by_tag(&mut self, tag: &str, page: usize) -> anyhow::Result<(String, Vec<TuiRoom>)>;
+    /// Returns public rooms recommended as similar to `username`.
+    fn similar(&mut self, username: &str) -> anyhow::Result<Vec<TuiRoom>>;
+    /// Adds `username` to the persistent watch list; `false` if already present.
+    fn add_to_watchlist(&mut self, username: &str) -> anyhow::Result<bool>;
+    /// Starts recording `username` in the background without blocking the view.
+    fn start_recording(&mut self, username: &str) -> anyhow::Result<()>;
+    /// Returns the most recent background event, if any arrived since the last call.
+    fn poll_event(&mut self) -> Option<String>;
+    /// Returns how many background recordings are still running.
+    fn active_recordings(&self) -> usize;
+}
+
+/// Query behind the listed rooms, reused for refresh and paging.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TuiQuery {
+    Tag { tag: String, page: usize },
+    Similar { username: String },
+}
+
+impl TuiQuery {
+    fn title(&self) -> String {
+        match self {
+            TuiQuery::Tag { tag, page } => format!("#{tag} · pagina {}", page + 1),
+            TuiQuery::Similar { username } => format!("similares a {username}"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum InputMode {
+    Normal,
+    Tag(String),
+    Filter,
 }
 
 struct DiscoveryTui {
-    tag: String,
+    query: TuiQuery,
     rooms: Vec<TuiRoom>,
     selected: usize,
+    filter: String,
+    input: InputMode,
+    history: Vec<(TuiQuery, Vec<TuiRoom>, usize)>,
+    status: Option<String>,
+    recording: usize,
 }
 
 impl DiscoveryTui {
+    fn new(tag: String, rooms: Vec<TuiRoom>) -> Self {
+        Self {
+            query: TuiQuery::Tag { tag, page: 0 },
+            rooms,
+            selected: 0,
+            filter: String::new(),
+            input: InputMode::Normal,
+            history: Vec::new(),
+            status: None,
+            recording: 0,
+        }
+    }
+
+    fn visible(&self) -> Vec<&TuiRoom> {
+        self.rooms
+            .iter()
+            .filter(|room| room.matches(&self.filter))
+            .collect()
+    }
+
+    fn selected_username(&self) -> Option<String> {
+        self.visible()
+            .get(self.selected)
+            .map(|room| room.username.clone())
+    }
+
+    fn replace_rooms(&mut self, query: TuiQuery, rooms: Vec<TuiRoom>) {
+        self.query = query;
+        self.rooms = rooms;
+        self.selected = 0;
+        self.status = None;
+    }
+
+    fn fail(&mut self, error: anyhow::Error) {
+        self.status = Some(sanitize_external_text(&error.to_string()));
+    }
+
+    fn load(&mut self, source: &mut dyn DiscoverySource, query: TuiQuery) {
+        let result = match &query {
+            TuiQuery::Tag { tag, page } => source
+                .rooms_by_tag(tag, *page)
+                .map(|(tag, rooms)| (TuiQuery::Tag { tag, page: *page }, rooms)),
+            TuiQuery::Similar { username } => {
+                source.similar(username).map(|rooms| (query.clone(), rooms))
+            }
+        };
+        match result {
+            Ok((query, rooms)) => self.replace_rooms(query, rooms),
+            Err(error) => self.fail(error),
+        }
+    }
+
+    fn refresh(&mut self, source: &mut dyn DiscoverySource) {
+        let selected = self.selected;
+        self.load(source, self.query.clone());
+        self.selected = selected.min(self.visible().len().saturating_sub(1));
+    }
+
+    fn change_page(&mut self, source: &mut dyn DiscoverySource, forward: bool) {
+        let TuiQuery::Tag { tag, page } = &self.query else {
+            return;
+        };
+        let page = if forward {
+            page.saturating_add(1)
+        } else if *page == 0 {
+            return;
+        } else {
+            page - 1
+        };
+        let query = TuiQuery::Tag {
+            tag: tag.clone(),
+            page,
+        };
+        self.load(source, query);
+    }
+
+    fn change_tag(&mut self, source: &mut dyn DiscoverySource, tag: String) {
+        let previous = self.query.clone();
+        self.load(source, TuiQuery::Tag { tag, page: 0 });
+        if self.query != previous {
+            self.history.clear();
+            self.filter.clear();
+        }
+    }
+
+    fn show_similar(&mut self, source: &mut dyn DiscoverySource) {
+        let Some(username) = self.selected_username() else {
+            return;
+        };
+        match source.similar(&username) {
+            Ok(rooms) => {
+                let previous_query = self.query.clone();
+                let previous_rooms = std::mem::take(&mut self.rooms);
+                self.history
+                    .push((previous_query, previous_rooms, self.selected));
+                self.replace_rooms(TuiQuery::Similar { username }, rooms);
+                self.filter.clear();
+            }
+            Err(error) => self.fail(error),
+        }
+    }
+
+    fn go_back(&mut self) {
+        if let Some((query, rooms, selected)) = self.history.pop() {
+            self.replace_rooms(query, rooms);
+            self.selected = selected;
+            self.filter.clear();
+        }
+    }
+
+    fn add_selected(&mut self, source: &mut dyn DiscoverySource) {
+        let Some(username) = self.selected_username() else {
+            return;
+        };
+        match source.add_to_watchlist(&username) {
+            Ok(true) => self.status = Some(format!("añadido a la lista: {username}")),
+            Ok(false) => self.status = Some(format!("ya en lista: {username}")),
+            Err(error) => self.fail(error),
+        }
+    }
+
+    fn record_selected(&mut self, source: &mut dyn DiscoverySource) {
+        let Some(username) = self.selected_username() else {
+            return;
+        };
+        match source.start_recording(&username) {
+            Ok(()) => self.status = Some(format!("grabando en segundo plano: {username}")),
+            Err(error) => self.fail(error),
+        }
+        self.recording = source.active_recordings();
+    }
+
+    fn tick(&mut self, source: &mut dyn DiscoverySource) {
+        if let Some(event) = source.poll_event() {
+            self.status = Some(sanitize_external_text(&event));
+        }
+        self.recording = source.active_recordings();
+    }
+
     fn move_down(&mut self) {
-        if self.selected + 1 < self.rooms.len() {
+        if self.selected + 1 < self.visible().len() {
             self.selected += 1;
         }
     }
@@ -49,25 +241,89 @@ impl DiscoveryTui {
         self.selected = self.selected.saturating_sub(1);
     }
 
-    fn should_quit(key: &KeyEvent) -> bool {
-        matches!(key.code, KeyCode::Esc | KeyCode::Char('q'))
-            || key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+    /// Applies one key press and returns whether the screen should close.
+    fn handle_key(&mut self, key: &KeyEvent, source: &mut dyn DiscoverySource) -> bool {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return true;
+        }
+
+        match std::mem::replace(&mut self.input, InputMode::Normal) {
+            InputMode::Normal => {}
+            InputMode::Tag(mut text) => {
+                match key.code {
+                    KeyCode::Enter if !text.trim().is_empty() => self.change_tag(source, text),
+                    KeyCode::Enter | KeyCode::Esc => {}
+                    KeyCode::Backspace => {
+                        text.pop();
+                        self.input = InputMode::Tag(text);
+                    }
+                    KeyCode::Char(character) => {
+                        if text.chars().count() < MAX_INPUT_CHARS && !character.is_control() {
+                            text.push(character);
+                        }
+                        self.input = InputMode::Tag(text);
+                    }
+                    _ => self.input = InputMode::Tag(text),
+                }
+                return false;
+            }
+            InputMode::Filter => {
+                match key.code {
+                    KeyCode::Enter => {}
+                    KeyCode::Esc => self.filter.clear(),
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                        self.input = InputMode::Filter;
+                    }
+                    KeyCode::Char(character) => {
+                        if self.filter.chars().count() < MAX_INPUT_CHARS && !character.is_control()
+                        {
+                            self.filter.extend(character.to_lowercase());
+                        }
+                        self.input = InputMode::Filter;
+                    }
+                    _ => self.input = InputMode::Filter,
+                }
+                self.selected = self.selected.min(self.visible().len().saturating_sub(1));
+                return false;
+            }
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return true,
+            KeyCode::Down | KeyCode::Char('j') => self.move_down(),
+            KeyCode::Up | KeyCode::Char('k') => self.move_up(),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = self.visible().len().saturating_sub(1),
+            KeyCode::Char('s') => self.show_similar(source),
+            KeyCode::Backspace | KeyCode::Left => self.go_back(),
+            KeyCode::Char('a') => self.add_selected(source),
+            KeyCode::Char('g') => self.record_selected(source),
+            KeyCode::Char('r') | KeyCode::F(5) => self.refresh(source),
+            KeyCode::Char('n') | KeyCode::PageDown => self.change_page(source, true),
+            KeyCode::Char('p') | KeyCode::PageUp => self.change_page(source, false),
+            KeyCode::Char('t') => self.input = InputMode::Tag(String::new()),
+            KeyCode::Char('/') => self.input = InputMode::Filter,
+            _ => {}
+        }
+        false
     }
 }
 
-/// Runs the read-only discovery screen and restores the terminal on exit.
-pub(crate) fn run_discovery_tui(tag: String, rooms: Vec<TuiRoom>) -> anyhow::Result<()> {
+/// Runs the discovery screen and restores the terminal on exit.
+pub(crate) fn run_discovery_tui(
+    tag: String,
+    rooms: Vec<TuiRoom>,
+    source: &mut dyn DiscoverySource,
+) -> anyhow::Result<()> {
     if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
         anyhow::bail!("La TUI requiere una terminal interactiva");
     }
 
-    let mut app = DiscoveryTui {
-        tag,
-        rooms,
-        selected: 0,
-    };
+    let mut app = DiscoveryTui::new(tag, rooms);
     ratatui::run(|terminal| -> io::Result<()> {
         loop {
+            app.tick(source);
             terminal.draw(|frame| render(frame, &app))?;
             if !event::poll(Duration::from_millis(250))? {
                 continue;
@@ -78,35 +334,38 @@ pub(crate) fn run_discovery_tui(tag: String, rooms: Vec<TuiRoom>) -> anyhow::Res
             if !matches!(key.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
                 continue;
             }
-            if DiscoveryTui::should_quit(&key) {
+            if app.handle_key(&key, source) {
                 return Ok(());
             }
-            match key.code {
-                KeyCode::Down | KeyCode::Char('j') => app.move_down(),
-                KeyCode::Up | KeyCode::Char('k') => app.move_up(),
-                KeyCode::Home => app.selected = 0,
-                KeyCode::End if !app.rooms.is_empty() => app.selected = app.rooms.len() - 1,
-                _ => {}
-            }
         }
     })?;
     Ok(())
 }
 
 fn render(frame: &mut Frame, app: &DiscoveryTui) {
-    let [header, rooms_area, detail, footer] = Layout::vertical([
+    let [header, rooms_area, detail, status, footer] = Layout::vertical([
         Constraint::Length(3),
         Constraint::Min(3),
         Constraint::Length(5),
         Constraint::Length(1),
+        Constraint::Length(1),
     ])
     .areas(frame.area());
 
-    let title = Paragraph::new(format!("#{} · {} modelo(s)", app.tag, app.rooms.len()))
-        .block(Block::default().title("cbrec").borders(Borders::ALL));
-    frame.render_widget(title, header);
+    let visible = app.visible();
+    let mut title = format!("{} · {} modelo(s)", app.query.title(), visible.len());
+    if !app.filter.is_empty() {
+        title.push_str(&format!(" · filtro: {}", app.filter));
+    }
+    if app.recording > 0 {
+        title.push_str(&format!(" · {} grabando", app.recording));
+    }
+    frame.render_widget(
+        Paragraph::new(title).block(Block::default().title("cbrec").borders(Borders::ALL)),
+        header,
+    );
 
-    let items = app.rooms.iter().map(|room| {
+    let items = visible.iter().map(|room| {
         ListItem::new(format!(
             "{}  ·  {} espectadores  ·  {}",
             room.username, room.viewers, room.show
@@ -121,11 +380,10 @@ fn render(frame: &mut Frame, app: &DiscoveryTui) {
                 .add_modifier(Modifier::BOLD),
         );
     let mut list_state =
-        ListState::default().with_selected((!app.rooms.is_empty()).then_some(app.selected));
+        ListState::default().with_selected((!visible.is_empty()).then_some(app.selected));
     frame.render_stateful_widget(list, rooms_area, &mut list_state);
 
-    let subject = app
-        .rooms
+    let subject = visible
         .get(app.selected)
         .map(|room| room.subject.as_str())
         .unwrap_or("Sin resultados");
@@ -135,9 +393,21 @@ fn render(frame: &mut Frame, app: &DiscoveryTui) {
             .block(Block::default().title("Descripción").borders(Borders::ALL)),
         detail,
     );
+
+    let status_line = match (&app.input, &app.status) {
+        (InputMode::Tag(text), _) => Paragraph::new(format!("tag: {text}_")),
+        (InputMode::Filter, _) => Paragraph::new(format!("filtrar: {}_", app.filter)),
+        (InputMode::Normal, Some(status)) => {
+            Paragraph::new(status.as_str()).style(Style::default().fg(Color::Yellow))
+        }
+        (InputMode::Normal, None) => Paragraph::new(""),
+    };
+    frame.render_widget(status_line, status);
     frame.render_widget(
-        Paragraph::new("↑/↓ o j/k: navegar · q/Esc: salir")
-            .style(Style::default().fg(Color::DarkGray)),
+        Paragraph::new(
+            "j/k: navegar · a: añadir · g: grabar · r: recargar · t: tag · /: filtrar · n/p: pagina · s: similares · ←: volver · q: salir",
+        )
+        .style(Style::default().fg(Color::DarkGray)),
         footer,
     );
 }
@@ -148,16 +418,86 @@ mod tests {
     use ratatui::backend::TestBackend;
     use ratatui::Terminal;
 
-    #[test]
-    fn navigation_stays_inside_room_bounds() {
-        let mut app = DiscoveryTui {
-            tag: "gaming".to_string(),
-            rooms: vec![
+    #[derive(Default)]
+    struct SourceFake {
+        similar: Option<Vec<TuiRoom>>,
+        pages: Vec<(String, usize)>,
+        requested: Vec<String>,
+        watched: Vec<String>,
+        recordings: Vec<String>,
+        events: Vec<String>,
+    }
+
+    impl DiscoverySource for SourceFake {
+        fn rooms_by_tag(
+            &mut self,
+            tag: &str,
+            page: usize,
+        ) -> anyhow::Result<(String, Vec<TuiRoom>)> {
+            self.pages.push((tag.to_string(), page));
+            Ok((
+                tag.trim_start_matches('#').to_lowercase(),
+                vec![TuiRoom::new(
+                    format!("page{page}"),
+                    1,
+                    "public".into(),
+                    "".into(),
+                )],
+            ))
+        }
+
+        fn similar(&mut self, username: &str) -> anyhow::Result<Vec<TuiRoom>> {
+            self.requested.push(username.to_string());
+            self.similar
+                .take()
+                .ok_or_else(|| anyhow::anyhow!("sin recomendaciones\u{1b}[31m"))
+        }
+
+        fn add_to_watchlist(&mut self, username: &str) -> anyhow::Result<bool> {
+            if self.watched.iter().any(|watched| watched == username) {
+                return Ok(false);
+            }
+            self.watched.push(username.to_string());
+            Ok(true)
+        }
+
+        fn start_recording(&mut self, username: &str) -> anyhow::Result<()> {
+            self.recordings.push(username.to_string());
+            Ok(())
+        }
+
+        fn poll_event(&mut self) -> Option<String> {
+            self.events.pop()
+        }
+
+        fn active_recordings(&self) -> usize {
+            self.recordings.len()
+        }
+    }
+
+    fn app_with_rooms() -> DiscoveryTui {
+        DiscoveryTui::new(
+            "gaming".to_string(),
+            vec![
                 TuiRoom::new("alice".into(), 1, "public".into(), "one".into()),
-                TuiRoom::new("bob".into(), 2, "public".into(), "two".into()),
+                TuiRoom::new("bob".into(), 2, "public".into(), "Two Words".into()),
             ],
-            selected: 0,
-        };
+        )
+    }
+
+    fn press(app: &mut DiscoveryTui, source: &mut SourceFake, keys: &str) {
+        for character in keys.chars() {
+            app.handle_key(&KeyEvent::from(KeyCode::Char(character)), source);
+        }
+    }
+
+    fn press_code(app: &mut DiscoveryTui, source: &mut SourceFake, code: KeyCode) -> bool {
+        app.handle_key(&KeyEvent::from(code), source)
+    }
+
+    #[test]
+    fn navigation_stays_inside_room_bounds() {
+        let mut app = app_with_rooms();
 
         app.move_up();
         assert_eq!(app.selected, 0);
@@ -166,6 +506,138 @@ mod tests {
         assert_eq!(app.selected, 1);
     }
 
+    #[test]
+    fn similar_view_replaces_rooms_and_back_restores_them() {
+        let mut app = app_with_rooms();
+        app.move_down();
+        let mut source = SourceFake {
+            similar: Some(vec![TuiRoom::new(
+                "carol".into(),
+                3,
+                "public".into(),
+                "three".into(),
+            )]),
+            ..SourceFake::default()
+        };
+
+        press(&mut app, &mut source, "s");
+
+        assert_eq!(source.requested, ["bob"]);
+        assert_eq!(app.query.title(), "similares a bob");
+        assert_eq!(app.rooms[0].username, "carol");
+        assert_eq!(app.selected, 0);
+
+        press_code(&mut app, &mut source, KeyCode::Left);
+        assert_eq!(app.query.title(), "#gaming · pagina 1");
+        assert_eq!(app.rooms.len(), 2);
+        assert_eq!(app.selected, 1);
+    }
+
+    #[test]
+    fn similar_error_keeps_rooms_and_shows_sanitized_status() {
+        let mut app = app_with_rooms();
+        let mut source = SourceFake::default();
+
+        press(&mut app, &mut source, "s");
+
+        assert_eq!(app.rooms.len(), 2);
+        assert_eq!(app.status.as_deref(), Some("sin recomendaciones [31m"));
+    }
+
+    #[test]
+    fn filter_matches_username_and_subject_case_insensitively() {
+        let mut app = app_with_rooms();
+        let mut source = SourceFake::default();
+
+        press(&mut app, &mut source, "/WORDS");
+        press_code(&mut app, &mut source, KeyCode::Enter);
+
+        assert_eq!(app.input, InputMode::Normal);
+        assert_eq!(app.selected_username().as_deref(), Some("bob"));
+        press(&mut app, &mut source, "/");
+        press_code(&mut app, &mut source, KeyCode::Esc);
+        assert_eq!(app.visible().len(), 2);
+    }
+
+    #[test]
+    fn filter_keys_do_not_trigger_actions() {
+        let mut app = app_with_rooms();
+        let mut source = SourceFake::default();
+
+        press(&mut app, &mut source, "/qag");
+
+        assert!(source.watched.is_empty());
+        assert!(source.recordings.is_empty());
+        assert_eq!(app.filter, "qag");
+    }
+
+    #[test]
+    fn actions_use_selected_visible_room() {
+        let mut app = app_with_rooms();
+        let mut source = SourceFake::default();
+
+        press(&mut app, &mut source, "jaag");
+
+        assert_eq!(source.watched, ["bob"]);
+        assert_eq!(source.recordings, ["bob"]);
+        assert_eq!(app.recording, 1);
+        assert_eq!(
+            app.status.as_deref(),
+            Some("grabando en segundo plano: bob")
+        );
+    }
+
+    #[test]
+    fn background_events_update_status_line() {
+        let mut app = app_with_rooms();
+        let mut source = SourceFake {
+            events: vec!["alice: guardado\n".to_string()],
+            ..SourceFake::default()
+        };
+
+        app.tick(&mut source);
+
+        assert_eq!(app.status.as_deref(), Some("alice: guardado "));
+    }
+
+    #[test]
+    fn paging_and_tag_changes_reload_from_source() {
+        let mut app = app_with_rooms();
+        let mut source = SourceFake::default();
+
+        press(&mut app, &mut source, "p");
+        assert!(source.pages.is_empty());
+        press(&mut app, &mut source, "nn");
+        press(&mut app, &mut source, "p");
+        press(&mut app, &mut source, "r");
+        press(&mut app, &mut source, "tMusic");
+        press_code(&mut app, &mut source, KeyCode::Enter);
+
+        assert_eq!(
+            source.pages,
+            [
+                ("gaming".to_string(), 1),
+                ("gaming".to_string(), 2),
+                ("gaming".to_string(), 1),
+                ("gaming".to_string(), 1),
+                ("Music".to_string(), 0),
+            ]
+        );
+        assert_eq!(app.query.title(), "#music · pagina 1");
+        assert_eq!(app.rooms[0].username, "page0");
+    }
+
+    #[test]
+    fn quit_keys_close_only_outside_text_input() {
+        let mut app = app_with_rooms();
+        let mut source = SourceFake::default();
+
+        press(&mut app, &mut source, "t");
+        assert!(!press_code(&mut app, &mut source, KeyCode::Char('q')));
+        assert!(!press_code(&mut app, &mut source, KeyCode::Esc));
+        assert!(press_code(&mut app, &mut source, KeyCode::Esc));
+    }
+
     #[test]
     fn room_text_is_sanitized_before_rendering() {
         let room = TuiRoom::new(
@@ -181,16 +653,16 @@ mod tests {
 
     #[test]
     fn render_shows_tag_rooms_and_selected_subject() {
-        let app = DiscoveryTui {
-            tag: "gaming".to_string(),
-            rooms: vec![TuiRoom::new(
+        let mut app = DiscoveryTui::new(
+            "gaming".to_string(),
+            vec![TuiRoom::new(
                 "alice".into(),
                 42,
                 "public".into(),
                 "hello".into(),
             )],
-            selected: 0,
-        };
+        );
+        app.recording = 1;
         let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
 
         terminal.draw(|frame| render(frame, &app)).unwrap();
@@ -203,6 +675,7 @@ mod tests {
             .map(|cell| cell.symbol())
             .collect::<String>();
         assert!(text.contains("#gaming"));
+        assert!(text.contains("1 grabando"));
         assert!(text.contains("alice"));
         assert!(text.contains("42 espectadores"));
         assert!(text.contains("hello"));
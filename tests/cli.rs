@@ -1,4 +1,4 @@
-use cbrec::presentation::{Cli, Commands};
+use cbrec::presentation::{Cli, Commands, LibraryCommands};
 use clap::Parser;
 
 #[test]
@@ -81,6 +81,19 @@ fn parse_json_query_flags() {
     ));
 }
 
+#[test]
+fn parse_similar_command() {
+    let cli = Cli::parse_from(["cbrec", "similar", "alice", "--limit", "5", "--json"]);
+    match cli.command {
+        Some(Commands::Similar { model, limit, json }) => {
+            assert_eq!(model, "alice");
+            assert_eq!(limit, 5);
+            assert!(json);
+        }
+        _ => panic!("Se esperaba subcomando similar"),
+    }
+}
+
 #[test]
 fn parse_tui_command() {
     let cli = Cli::parse_from(["cbrec", "tui", "--tag", "gaming", "--limit", "10"]);
@@ -93,12 +106,61 @@ fn parse_tui_command() {
     }
 }
 
+#[test]
+fn parse_tui_with_recording_flags() {
+    let cli = Cli::parse_from([
+        "cbrec", "-o", "/tmp/out", "-q", "720p", "-j", "2", "tui", "--tag", "gaming",
+    ]);
+    assert_eq!(cli.output.as_deref(), Some("/tmp/out"));
+    assert_eq!(cli.quality, "720p");
+    assert_eq!(cli.jobs, Some(2));
+    assert!(matches!(cli.command, Some(Commands::Tui { .. })));
+}
+
+#[test]
+fn parse_proxy_global() {
+    let cli = Cli::parse_from(["cbrec", "watch", "--proxy", "socks5h://127.0.0.1:1080"]);
+    assert_eq!(cli.proxy.as_deref(), Some("socks5h://127.0.0.1:1080"));
+}
+
+#[test]
+fn parse_stats_model() {
+    let cli = Cli::parse_from(["cbrec", "stats", "alice"]);
+    assert!(matches!(cli.command, Some(Commands::Stats { model }) if model == "alice"));
+}
+
 #[test]
 fn parse_ffmpeg_path_global() {
     let cli = Cli::parse_from(["cbrec", "record", "alice", "--ffmpeg-path", "/tmp/ffmpeg"]);
     assert_eq!(cli.ffmpeg_path.as_deref(), Some("/tmp/ffmpeg"));
 }
 
+#[test]
+fn parse_variant_policy_flags() {
+    let cli = Cli::parse_from([
+        "cbrec",
+        "watch",
+        "--max-bitrate",
+        "3M",
+        "--codec",
+        "hevc",
+        "--fps",
+        "30",
+        "--strict",
+    ]);
+    assert_eq!(cli.max_bitrate.as_deref(), Some("3M"));
+    assert_eq!(cli.codec.as_deref(), Some("hevc"));
+    assert_eq!(cli.fps, Some(30));
+    assert!(cli.strict);
+}
+
+#[test]
+fn parse_from_start_flag() {
+    let cli = Cli::parse_from(["cbrec", "watch", "--from-start"]);
+    assert!(cli.from_start);
+    assert!(!Cli::parse_from(["cbrec", "alice"]).from_start);
+}
+
 #[test]
 fn parse_list_flag() {
     let cli = Cli::parse_from(["cbrec", "alice", "-l"]);
@@ -148,6 +210,26 @@ fn parse_watch_with_jobs() {
     }
 }
 
+#[test]
+fn parse_watch_fresh_flag() {
+    let cli = Cli::parse_from(["cbrec", "watch", "--fresh"]);
+    match cli.command {
+        Some(Commands::Watch { fresh, .. }) => assert!(fresh),
+        _ => panic!("Se esperaba subcomando watch"),
+    }
+}
+
+#[test]
+fn parse_watch_timeline_file() {
+    let cli = Cli::parse_from(["cbrec", "watch", "--timeline", "estados.jsonl"]);
+    match cli.command {
+        Some(Commands::Watch { timeline, .. }) => {
+            assert_eq!(timeline.as_deref(), Some("estados.jsonl"))
+        }
+        _ => panic!("Se esperaba subcomando watch"),
+    }
+}
+
 #[test]
 fn parse_watch_timeout() {
     let cli = Cli::parse_from(["cbrec", "watch", "alice", "--ask", "--timeout", "30"]);
@@ -194,3 +276,79 @@ fn parse_remove_command() {
         _ => panic!("Se esperaba subcomando remove"),
     }
 }
+
+#[test]
+fn parse_buffer_command() {
+    let cli = Cli::parse_from(["cbrec", "buffer", "alice", "bob", "--minutes", "10"]);
+    match cli.command {
+        Some(Commands::Buffer {
+            modelos, minutes, ..
+        }) => {
+            assert_eq!(modelos, vec!["alice", "bob"]);
+            assert_eq!(minutes, Some(10));
+        }
+        _ => panic!("Se esperaba subcomando buffer"),
+    }
+}
+
+#[test]
+fn parse_clip_command() {
+    let cli = Cli::parse_from(["cbrec", "clip", "alice", "--after", "0"]);
+    match cli.command {
+        Some(Commands::Clip { model, after, .. }) => {
+            assert_eq!(model, "alice");
+            assert_eq!(after, Some(0));
+        }
+        _ => panic!("Se esperaba subcomando clip"),
+    }
+}
+
+#[test]
+fn parse_ffmpeg_profile_global() {
+    let cli = Cli::parse_from(["cbrec", "watch", "--ffmpeg-profile", "robusto"]);
+    assert_eq!(cli.ffmpeg_profile.as_deref(), Some("robusto"));
+}
+
+#[test]
+fn parse_audio_format_global() {
+    let cli = Cli::parse_from([
+        "cbrec",
+        "record",
+        "alice",
+        "-q",
+        "audio",
+        "--audio-format",
+        "opus",
+    ]);
+    assert_eq!(cli.audio_format.as_deref(), Some("opus"));
+}
+
+#[test]
+fn parse_watch_quality_list() {
+    let cli = Cli::parse_from(["cbrec", "watch", "alice", "-q", "best,480p"]);
+    match cli.command {
+        Some(Commands::Watch { quality, .. }) => assert_eq!(quality, "best,480p"),
+        _ => panic!("expected watch command"),
+    }
+}
+
+#[test]
+fn parse_library_reorganize_dry_run() {
+    let cli = Cli::parse_from([
+        "cbrec",
+        "library",
+        "reorganize",
+        "--dry-run",
+        "-o",
+        "/tmp/v",
+    ]);
+    match cli.command {
+        Some(Commands::Library {
+            command: LibraryCommands::Reorganize { output, dry_run },
+        }) => {
+            assert!(dry_run);
+            assert_eq!(output.as_deref(), Some("/tmp/v"));
+        }
+        _ => panic!("expected library reorganize command"),
+    }
+}